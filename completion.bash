@@ -36,14 +36,42 @@ _escape_compreply()
 _actions()
 {
     local IFS=$'\n'
-    COMPREPLY=($(compgen -W '$(odo action ls)' -- "$cur"))
+    COMPREPLY=($(compgen -W '$("${odo[@]}" action ls | cut -d " " -f 2-)' -- "$cur"))
+    _escape_compreply
+}
+
+_contexts()
+{
+    local IFS=$'\n'
+    COMPREPLY=($(compgen -W '$("${odo[@]}" context ls | cut -d " " -f 1)' -- "$cur"))
+    _escape_compreply
+}
+
+_tags()
+{
+    local IFS=$'\n'
+    COMPREPLY=($(compgen -W '$("${odo[@]}" tag ls | cut -d " " -f 1)' -- "$cur"))
+    _escape_compreply
+}
+
+_views()
+{
+    local IFS=$'\n'
+    COMPREPLY=($(compgen -W '$("${odo[@]}" view ls | cut -d " " -f 1)' -- "$cur"))
+    _escape_compreply
+}
+
+_profiles()
+{
+    local IFS=$'\n'
+    COMPREPLY=($(compgen -W '$(odo profile ls)' -- "$cur"))
     _escape_compreply
 }
 
 _goals()
 {
     local IFS=$'\n'
-    COMPREPLY=($(compgen -W '$(odo goal ls --all)' -- "$cur"))
+    COMPREPLY=($(compgen -W '$("${odo[@]}" goal ls --all | cut -d " " -f 2-)' -- "$cur"))
     _escape_compreply
 }
 
@@ -52,59 +80,179 @@ _odo()
     local cur prev words cword
     _init_completion -s || return
 
-    if [[ $cword == 1 ]]; then
-	COMPREPLY=($(compgen -W 'action goal' -- "$cur"))
+    if [[ $prev == --database ]]; then
+	_filedir
+	return
+    elif [[ $prev == --profile ]]; then
+	_profiles
+	return
+    fi
+
+    # The options that choose the database are passed on when odo is run to list items.
+    local odo=(odo)
+    while [[ ${words[1]} == --database || ${words[1]} == --profile ]] && ((cword > 2)); do
+	odo+=("${words[@]:1:2}")
+	words=("${words[0]}" "${words[@]:3}")
+	((cword -= 2))
+    done
+
+    if [[ $cword == 1 && $cur == -* ]]; then
+	COMPREPLY=($(compgen -W '--database --profile' -- "$cur"))
+    elif [[ $cword == 1 ]]; then
+	COMPREPLY=($(compgen -W "action backup config context doctor goal next profile restore search show tag view $("${odo[@]}" view ls | cut -d ' ' -f 1)" -- "$cur"))
+    elif [[ $prev == --format ]]; then
+	COMPREPLY=($(compgen -W 'csv json jsonl text tsv' -- "$cur"))
     else
 	case ${words[1]} in
 	    action)
 		if [[ $cword == 2 ]]; then
-		    COMPREPLY=($(compgen -W 'add ls rm set' -- "$cur"))
+		    COMPREPLY=($(compgen -W 'add annotate done ls note rm set tag unset untag' -- "$cur"))
 		else
 		    case ${words[2]} in
-			rm)
+			ls)
+			    if [[ $cur == -* ]]; then
+				COMPREPLY=($(compgen -W '--all --context --done --due-before --format --overdue --sort --template' -- "$cur"))
+			    elif [[ $prev == --context ]]; then
+				_contexts
+			    elif [[ $prev == --sort ]]; then
+				COMPREPLY=($(compgen -W 'due id scheduled urgency' -- "$cur"))
+			    fi
+			    ;;
+			annotate|done|rm)
+			    if [[ $cword == 3 ]]; then
+				_actions
+			    fi
+			    ;;
+			note)
+			    if [[ $cur == -* ]]; then
+				COMPREPLY=($(compgen -W '--edit' -- "$cur"))
+			    elif [[ $cword == 3 || ($cword == 4 && $prev == --edit) ]]; then
+				_actions
+			    fi
+			    ;;
+			tag|untag)
 			    if [[ $cword == 3 ]]; then
 				_actions
+			    else
+				_tags
 			    fi
 			    ;;
 			set)
 			    if [[ $cword == 3 ]]; then
-				COMPREPLY=($(compgen -W 'description' -- "$cur"))
-			    elif [[ $cword = 4 ]]; then
+				COMPREPLY=($(compgen -W 'context description due priority recurrence scheduled' -- "$cur"))
+			    elif [[ $cword == 4 ]]; then
+				_actions
+			    elif [[ $cword == 5 && ${words[3]} == context ]]; then
+				_contexts
+			    fi
+			    ;;
+			unset)
+			    if [[ $cword == 3 ]]; then
+				COMPREPLY=($(compgen -W 'context due note priority recurrence scheduled' -- "$cur"))
+			    elif [[ $cword == 4 ]]; then
 				_actions
 			    fi
 			    ;;
 		    esac
 		fi
 		;;
+	    config)
+		if [[ $cword == 2 ]]; then
+		    COMPREPLY=($(compgen -W 'ls set unset' -- "$cur"))
+		elif [[ $cword == 3 && ${words[2]} != ls ]]; then
+		    COMPREPLY=($(compgen -W 'backup.count backup.days template.action template.goal urgency.age urgency.due urgency.goal urgency.priority' -- "$cur"))
+		fi
+		;;
+	    context)
+		if [[ $cword == 2 ]]; then
+		    COMPREPLY=($(compgen -W 'add ls rm' -- "$cur"))
+		else
+		    case ${words[2]} in
+			add)
+			    if [[ $cur == -* ]]; then
+				COMPREPLY=($(compgen -W '--description --order' -- "$cur"))
+			    fi
+			    ;;
+			rm)
+			    if [[ $cword == 3 ]]; then
+				_contexts
+			    fi
+			    ;;
+		    esac
+		fi
+		;;
 	    goal)
 		if [[ $cword == 2 ]]; then
-		    COMPREPLY=($(compgen -W 'add ls rm set unset' -- "$cur"))
+		    COMPREPLY=($(compgen -W 'actions add add-action annotate done ls move-action note rm rm-action set tag unset untag' -- "$cur"))
 		else
 		    case ${words[2]} in
 			add)
 			    if [[ $cur == -* ]]; then
-				COMPREPLY=($(compgen -W '--action' -- "$cur"))
+				COMPREPLY=($(compgen -W '--action --parent --type' -- "$cur"))
 			    else
 				case $prev in
 				    --action)
 					_actions
 					;;
+				    --parent)
+					_goals
+					;;
+				    --type)
+					COMPREPLY=($(compgen -W 'parallel sequential' -- "$cur"))
+					;;
 				esac
 			    fi
 			    ;;
 			ls)
 			    if [[ $cur == -* ]]; then
-				COMPREPLY=($(compgen -W '--all' -- "$cur"))
+				COMPREPLY=($(compgen -W '--all --done --due-before --everything --format --overdue --sort --template --tree' -- "$cur"))
+			    elif [[ $prev == --sort ]]; then
+				COMPREPLY=($(compgen -W 'due id scheduled urgency' -- "$cur"))
 			    fi
 			    ;;
-			rm)
+			actions|annotate|done)
 			    if [[ $cword == 3 ]]; then
 				_goals
 			    fi
 			    ;;
+			note)
+			    if [[ $cur == -* ]]; then
+				COMPREPLY=($(compgen -W '--edit' -- "$cur"))
+			    elif [[ $cword == 3 || ($cword == 4 && $prev == --edit) ]]; then
+				_goals
+			    fi
+			    ;;
+			tag|untag)
+			    if [[ $cword == 3 ]]; then
+				_goals
+			    else
+				_tags
+			    fi
+			    ;;
+			add-action|rm-action)
+			    if [[ $cword == 3 ]]; then
+				_goals
+			    elif [[ $cword == 4 ]]; then
+				_actions
+			    fi
+			    ;;
+			move-action)
+			    if [[ $cword == 3 ]]; then
+				_goals
+			    elif [[ $cword == 5 ]]; then
+				_actions
+			    fi
+			    ;;
+			rm)
+			    if [[ $cur == -* ]]; then
+				COMPREPLY=($(compgen -W '--recursive' -- "$cur"))
+			    else
+				_goals
+			    fi
+			    ;;
 			set)
 			    if [[ $cword == 3 ]]; then
-				COMPREPLY=($(compgen -W 'action description' -- "$cur"))
+				COMPREPLY=($(compgen -W 'action description due parent priority scheduled type' -- "$cur"))
 			    else
 				case ${words[3]} in
 				    action)
@@ -114,17 +262,29 @@ _odo()
 					    _actions
 					fi
 					;;
-				    description)
+				    description|due|priority|scheduled)
 					if [[ $cword == 4 ]]; then
 					    _goals
 					fi
 					;;
+				    parent)
+					if [[ $cword == 4 || $cword == 5 ]]; then
+					    _goals
+					fi
+					;;
+				    type)
+					if [[ $cword == 4 ]]; then
+					    _goals
+					elif [[ $cword == 5 ]]; then
+					    COMPREPLY=($(compgen -W 'parallel sequential' -- "$cur"))
+					fi
+					;;
 				esac
 			    fi
 			    ;;
 			unset)
 			    if [[ $cword == 3 ]]; then
-				COMPREPLY=($(compgen -W 'action' -- "$cur"))
+				COMPREPLY=($(compgen -W 'action due note parent priority scheduled' -- "$cur"))
 			    else
 				if [[ $cword == 4 ]]; then
 				    _goals
@@ -134,6 +294,61 @@ _odo()
 		    esac
 		fi
 		;;
+	    search)
+		if [[ $cur == -* ]]; then
+		    COMPREPLY=($(compgen -W '--all --format' -- "$cur"))
+		fi
+		;;
+	    show)
+		if [[ $cword == 2 ]]; then
+		    COMPREPLY=($(compgen -W 'action goal' -- "$cur"))
+		elif [[ $cword == 3 && ${words[2]} == action ]]; then
+		    _actions
+		elif [[ $cword == 3 && ${words[2]} == goal ]]; then
+		    _goals
+		fi
+		;;
+	    tag)
+		if [[ $cword == 2 ]]; then
+		    COMPREPLY=($(compgen -W 'ls rename' -- "$cur"))
+		elif [[ ${words[2]} == rename && $cword == 3 ]]; then
+		    _tags
+		fi
+		;;
+	    view)
+		if [[ $cword == 2 ]]; then
+		    _views
+		    COMPREPLY+=($(compgen -W 'add ls rm' -- "$cur"))
+		elif [[ ${words[2]} == add && $cur == -* && $cword == 3 ]]; then
+		    COMPREPLY=($(compgen -W '--goal' -- "$cur"))
+		elif [[ ${words[2]} == rm ]]; then
+		    if [[ $cur == -* ]]; then
+			COMPREPLY=($(compgen -W '--goal' -- "$cur"))
+		    else
+			_views
+		    fi
+		fi
+		;;
+	    backup|restore)
+		if [[ $cword == 2 ]]; then
+		    _filedir
+		fi
+		;;
+	    doctor)
+		COMPREPLY=($(compgen -W '--fix' -- "$cur"))
+		;;
+	    profile)
+		if [[ $cword == 2 ]]; then
+		    COMPREPLY=($(compgen -W 'ls' -- "$cur"))
+		fi
+		;;
+	    next)
+		if [[ $cur == -* ]]; then
+		    COMPREPLY=($(compgen -W '--context --format' -- "$cur"))
+		elif [[ $prev == --context ]]; then
+		    _contexts
+		fi
+		;;
 	esac
     fi
 } &&
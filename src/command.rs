@@ -13,178 +13,718 @@
 // You should have received a copy of the GNU Affero General Public License along with odo.  If not,
 // see <https://www.gnu.org/licenses/>.
 
-use rusqlite::Connection;
-use std::io::{self, Write};
+use crate::{
+    database::{self, Location},
+    error::Error,
+    filter::Filter,
+    format::{self, Format},
+    recurrence::Recurrence,
+    store::{
+        check_setting_name, check_setting_value, Action, Annotation, DateField, Goal, GoalType,
+        Item, Match, NextActions, Selection, Sort, Status, Store,
+    },
+    table,
+    template::Template,
+};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Weekday};
+use std::{
+    collections::{BTreeMap, HashSet},
+    convert::TryFrom,
+    env,
+    ffi::OsString,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    iter,
+    path::PathBuf,
+    process,
+};
 
 #[derive(Debug, PartialEq)]
 pub enum Command {
     Action(ActionSubcommand),
+    Backup {
+        path: Option<PathBuf>,
+    },
+    Config(ConfigSubcommand),
+    Context(ContextSubcommand),
+    Doctor {
+        fix: bool,
+    },
     Goal(GoalSubcommand),
+    Profile(ProfileSubcommand),
+    Restore {
+        path: PathBuf,
+    },
+    Next {
+        context: Option<String>,
+        format: Format,
+    },
+    Search {
+        query: String,
+        all: bool,
+        format: Format,
+    },
+    Show {
+        item: Item,
+        description: String,
+    },
+    Tag(TagSubcommand),
+    View(ViewSubcommand),
 }
 
+// The commands, which views cannot be named after since `odo <view>` runs a view.
+const COMMANDS: &[&str] = &[
+    "action", "backup", "config", "context", "doctor", "goal", "next", "profile", "restore",
+    "search", "show", "tag", "view",
+];
+
 impl Command {
-    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, String> {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
         match args.next() {
             Some(command) => match command.as_str() {
                 "action" => ActionSubcommand::from_args(args).map(Self::Action),
+                "backup" => {
+                    let path = args.next().map(PathBuf::from);
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    Ok(Self::Backup { path })
+                }
+                "config" => ConfigSubcommand::from_args(args).map(Self::Config),
+                "context" => ContextSubcommand::from_args(args).map(Self::Context),
+                "doctor" => {
+                    let mut fix = false;
+                    for arg in args {
+                        match arg.as_str() {
+                            "--fix" => fix = true,
+                            _ => return Err(Error::Usage(format!("extra argument: `{}`", arg))),
+                        }
+                    }
+                    Ok(Self::Doctor { fix })
+                }
                 "goal" => GoalSubcommand::from_args(args).map(Self::Goal),
-                _ => Err(format!("no such command: `{}`", command)),
-            },
-            None => Err("missing command".into()),
-        }
-    }
-
-    pub fn run(self, connection: &Connection) -> Result<(), String> {
-        match self {
-            Self::Action(subcommand) => subcommand.run(&connection),
-            Self::Goal(subcommand) => subcommand.run(&connection),
-        }
-    }
-}
-
-#[derive(Debug, PartialEq)]
-pub enum ActionSubcommand {
-    Add {
-        description: String,
-    },
-    List,
-    Remove {
-        description: String,
-    },
-    SetDescription {
-        old_description: String,
-        new_description: String,
-    },
-}
-
-impl ActionSubcommand {
-    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, String> {
-        match args.next() {
-            Some(subcommand) => match subcommand.as_str() {
-                "add" => {
-                    let args = args.collect::<Vec<_>>();
-                    if args.is_empty() {
-                        return Err("missing description".into());
+                "next" => {
+                    let mut context = None;
+                    let mut format = Format::Text;
+                    while let Some(arg) = args.next() {
+                        match arg.as_str() {
+                            "--context" => {
+                                context = Some(
+                                    context_name(args.next().ok_or_else(|| {
+                                        Error::Usage(
+                                            "option `--context` requires an argument".into(),
+                                        )
+                                    })?)
+                                    .map_err(Error::Usage)?,
+                                )
+                            }
+                            "--format" => format = format_arg(&mut args)?,
+                            _ => return Err(Error::Usage(format!("extra argument: `{}`", arg))),
+                        }
                     }
-                    Ok(Self::Add {
-                        description: args.join(" "),
-                    })
+                    Ok(Self::Next { context, format })
                 }
-                "ls" => {
-                    if let Some(arg) = args.next() {
-                        return Err(format!("extra argument: `{}`", arg));
+                "search" => {
+                    let mut args = args.peekable();
+                    let mut all = false;
+                    let mut format = Format::Text;
+                    loop {
+                        if args.next_if_eq("--all").is_some() {
+                            all = true;
+                        } else if args.next_if_eq("--format").is_some() {
+                            format = format_arg(&mut args)?;
+                        } else {
+                            break;
+                        }
+                    }
+                    let query = args.map(search_term).collect::<Vec<_>>();
+                    if query.is_empty() {
+                        return Err(Error::Usage("missing query".into()));
                     }
-                    Ok(Self::List)
+                    Ok(Self::Search {
+                        query: query.join(" "),
+                        all,
+                        format,
+                    })
                 }
-                "rm" => {
+                "show" => {
+                    let item = Item::from_arg(
+                        &args
+                            .next()
+                            .ok_or_else(|| Error::Usage("missing item".into()))?,
+                    )?;
                     let args = args.collect::<Vec<_>>();
                     if args.is_empty() {
-                        return Err("missing description".into());
+                        return Err(Error::Usage("missing description".into()));
                     }
-                    Ok(Self::Remove {
+                    Ok(Self::Show {
+                        item,
                         description: args.join(" "),
                     })
                 }
-                "set" => match args.next() {
-                    Some(field) => match field.as_str() {
-                        "description" => {
-                            let old_description = args
-                                .next()
-                                .ok_or_else(|| "missing old description".to_string())?;
-                            let args = args.collect::<Vec<_>>();
-                            if args.is_empty() {
-                                return Err("missing new description".into());
-                            }
-                            Ok(Self::SetDescription {
-                                old_description,
-                                new_description: args.join(" "),
-                            })
-                        }
-                        _ => Err(format!("no such field: `{}`", field)),
-                    },
-                    None => Err("missing field".into()),
-                },
-                _ => Err(format!("no such subcommand: `{}`", subcommand)),
+                "profile" => ProfileSubcommand::from_args(args).map(Self::Profile),
+                "restore" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing path".into()))?;
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    Ok(Self::Restore { path: path.into() })
+                }
+                "tag" => TagSubcommand::from_args(args).map(Self::Tag),
+                "view" => ViewSubcommand::from_args(args).map(Self::View),
+                _ => Err(Error::Usage(format!("no such command: `{}`", command))),
             },
-            None => Err("missing subcommand".into()),
+            None => Err(Error::Usage("missing command".into())),
         }
     }
 
-    pub fn run(self, connection: &Connection) -> Result<(), String> {
+    // Runs the command on the database at `location`.  Backing up to a path and restoring use the
+    // database as it is, without upgrading it, so that they work on one that this version cannot
+    // use.
+    pub fn run_at(self, location: &Location) -> Result<(), Error> {
         match self {
-            Self::Add { description } => add_action(&connection, description),
-            Self::List => list_actions(&connection, &mut io::stdout()),
-            Self::Remove { description } => remove_action(&connection, description),
-            Self::SetDescription {
-                old_description,
-                new_description,
-            } => set_action_description(&connection, old_description, new_description),
+            Self::Backup { path: Some(path) } => {
+                database::backup(&database::open(location)?, &path)
+            }
+            Self::Restore { path } => database::restore(&database::open(location)?, &path),
+            command => command.run(&Store::open_location(location)?),
+        }
+    }
+
+    pub fn run(self, store: &Store) -> Result<(), Error> {
+        match self {
+            Self::Action(subcommand) => subcommand.run(store),
+            Self::Backup { path } => backup(store, path, &mut io::stdout()),
+            Self::Config(subcommand) => subcommand.run(store),
+            Self::Context(subcommand) => subcommand.run(store),
+            Self::Doctor { fix } => doctor(store, fix, &mut io::stdout()),
+            Self::Goal(subcommand) => subcommand.run(store),
+            Self::Profile(subcommand) => subcommand.run(),
+            Self::Restore { path } => store.restore(&path),
+            Self::Next { context, format } => {
+                list_next_actions(store, context, format, &mut io::stdout())
+            }
+            Self::Search { query, all, format } => {
+                search(store, query, all, format, &mut io::stdout())
+            }
+            Self::Show { item, description } => match item {
+                Item::Action => show_action(store, description, &mut io::stdout()),
+                Item::Goal => show_goal(store, description, &mut io::stdout()),
+            },
+            Self::Tag(subcommand) => subcommand.run(store),
+            Self::View(subcommand) => subcommand.run(store),
+        }
+    }
+}
+
+// Takes the options that choose the database from the front of `args`.  `--database` and `--profile`
+// override `database`, the value of `ODO_DATABASE`.
+pub fn parse_location(
+    args: Vec<String>,
+    database: Option<OsString>,
+) -> Result<(Location, Vec<String>), Error> {
+    let mut args = args.into_iter().peekable();
+    let mut location = None;
+    while let Some(option) = args.next_if(|arg| arg == "--database" || arg == "--profile") {
+        let value = args
+            .next()
+            .ok_or_else(|| Error::Usage(format!("option `{}` requires an argument", option)))?;
+        if location.is_some() {
+            return Err(Error::Usage(
+                "options `--database` and `--profile` cannot be combined".into(),
+            ));
+        }
+        location = Some(if option == "--database" {
+            Location::Path(value.into())
+        } else {
+            profile_location(value)?
+        });
+    }
+    let location = match (location, database) {
+        (Some(location), _) => location,
+        (None, Some(database)) if !database.is_empty() => Location::Path(database.into()),
+        (None, _) => Location::Default,
+    };
+    Ok((location, args.collect()))
+}
+
+// Profile names become file names, so they are limited to letters, digits, hyphens, and
+// underscores.  The `default` profile is the database used when no profile is given.
+fn profile_location(name: String) -> Result<Location, Error> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Err(Error::Usage(format!("invalid profile name: `{}`", name)))
+    } else if name == "default" {
+        Ok(Location::Default)
+    } else {
+        Ok(Location::Profile(name))
+    }
+}
+
+// Whether `args` may run a view, which `expand_view` needs the database to find out.  Other
+// arguments are parsed before the database is opened, so that invalid ones leave no database
+// behind.
+pub fn may_name_view(args: &[String]) -> bool {
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["action" | "goal", "ls"] => true,
+        ["view", name, ..] | [name, ..] => view_name(name.into()).is_ok(),
+        [] => false,
+    }
+}
+
+// Rewrites a command that runs a view, `odo <view>` or `odo view <view>`, as the listing the view
+// saves, followed by any further arguments.  A bare `action ls` or `goal ls` runs the view called
+// `default` for that kind of item if there is one.
+pub fn expand_view(store: &Store, args: Vec<String>) -> Result<Vec<String>, Error> {
+    let (view, rest) = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [item @ ("action" | "goal"), "ls"] => {
+            let item = Item::from_arg(item)?;
+            match store.view("default", Some(item))? {
+                Some(view) => (view, 2),
+                None => return Ok(args),
+            }
+        }
+        ["view", name, ..] if !["add", "ls", "rm"].contains(&name) => (
+            store
+                .view(name, None)?
+                .ok_or_else(|| Error::NotFound("view does not exist".into()))?,
+            2,
+        ),
+        [name, ..] if !COMMANDS.contains(&name) => match store.view(name, None)? {
+            Some(view) => (view, 1),
+            None => return Ok(args),
+        },
+        _ => return Ok(args),
+    };
+    let mut expanded = vec![view.item.name().to_string(), "ls".to_string()];
+    expanded.extend(view.arguments);
+    expanded.extend(args.into_iter().skip(rest));
+    Ok(expanded)
+}
+
+fn format_arg<T: Iterator<Item = String>>(args: &mut T) -> Result<Format, Error> {
+    let format = args
+        .next()
+        .ok_or_else(|| Error::Usage("option `--format` requires an argument".into()))?;
+    Format::from_arg(&format).map_err(Error::Usage)
+}
+
+// Parses the arguments of a listing whose only option is `--format`.
+fn parse_format<T: Iterator<Item = String>>(mut args: T) -> Result<Format, Error> {
+    let mut format = Format::Text;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = format_arg(&mut args)?,
+            _ => return Err(Error::Usage(format!("extra argument: `{}`", arg))),
         }
     }
+    Ok(format)
+}
+
+// Arguments are FTS5 query terms, so `net*` matches a prefix and `OR` combines terms, except that an
+// argument containing whitespace, which the shell must have quoted, is searched for as a phrase.
+fn search_term(arg: String) -> String {
+    if arg.chars().any(char::is_whitespace) {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        arg
+    }
+}
+
+// Priorities run from 1 (highest) to 5, and H, M and L stand for 1, 3 and 5.
+pub(crate) fn parse_priority(arg: &str) -> Result<i64, String> {
+    match arg.to_uppercase().as_str() {
+        "H" => Ok(1),
+        "M" => Ok(3),
+        "L" => Ok(5),
+        _ => arg
+            .parse()
+            .ok()
+            .filter(|priority| (1..=5).contains(priority))
+            .ok_or_else(|| format!("invalid priority: `{}`", arg)),
+    }
 }
 
+// The filtering and sorting options `action ls` and `goal ls` share.
 #[derive(Debug, PartialEq)]
-pub enum GoalSubcommand {
+pub struct ListOptions {
+    selection: Selection,
+    format: Format,
+    template: Option<Template>,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            selection: Selection::default(),
+            format: Format::Text,
+            template: None,
+        }
+    }
+}
+
+impl ListOptions {
+    // Returns whether `arg` was one of the shared options, taking its value from `args` if it has
+    // one.
+    fn parse_arg<T: Iterator<Item = String>>(
+        &mut self,
+        arg: &str,
+        args: &mut T,
+    ) -> Result<bool, Error> {
+        match arg {
+            "--due-before" => {
+                let date = args.next().ok_or_else(|| {
+                    Error::Usage("option `--due-before` requires an argument".into())
+                })?;
+                self.selection.due_before = Some(parse_date(&date, today()).map_err(Error::Usage)?);
+            }
+            "--format" => self.format = format_arg(args)?,
+            "--overdue" => self.selection.overdue = true,
+            "--sort" => {
+                let sort = args
+                    .next()
+                    .ok_or_else(|| Error::Usage("option `--sort` requires an argument".into()))?;
+                self.selection.sort = Sort::from_arg(&sort)?;
+            }
+            "--template" => {
+                let template = args.next().ok_or_else(|| {
+                    Error::Usage("option `--template` requires an argument".into())
+                })?;
+                self.template =
+                    Some(Template::parse(&template, TEMPLATE_FIELDS).map_err(Error::Usage)?);
+            }
+            _ if arg.starts_with('+') => self
+                .selection
+                .include
+                .push(tag_name(arg.into()).map_err(Error::Usage)?),
+            _ if arg.starts_with('-') && !arg.starts_with("--") => self
+                .selection
+                .exclude
+                .push(tag_name(arg[1..].into()).map_err(Error::Usage)?),
+            _ => return Ok(false),
+        }
+        if self.format != Format::Text && self.template.is_some() {
+            return Err(Error::Usage(
+                "options `--format` and `--template` cannot be combined".into(),
+            ));
+        }
+        Ok(true)
+    }
+
+    // The template that text listings of `item` use: the one given, if any, or else the one that is
+    // configured.
+    fn template(&self, store: &Store, item: Item) -> Result<Option<Template>, Error> {
+        if self.format != Format::Text || self.template.is_some() {
+            return Ok(self.template.clone());
+        }
+        let name = format!("template.{}", item.name());
+        store
+            .config(&name)?
+            .map(|template| {
+                Template::parse(&template, TEMPLATE_FIELDS)
+                    .map_err(|e| Error::Storage(format!("invalid value for `{}`: {}", name, e)))
+            })
+            .transpose()
+    }
+
+    // Arguments that are not options make up a filter expression.
+    fn parse_filter(&mut self, args: &[String], item: Item) -> Result<(), Error> {
+        if !args.is_empty() {
+            self.selection.filter =
+                Some(Filter::parse(&args.join(" "), item).map_err(Error::Usage)?);
+        }
+        Ok(())
+    }
+}
+
+// Tags are written with a leading `+`, which is optional on the command line and not stored.
+pub(crate) fn tag_name(arg: String) -> Result<String, String> {
+    let name = arg.strip_prefix('+').unwrap_or(&arg);
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(format!("invalid tag: `{}`", arg));
+    }
+    Ok(name.into())
+}
+
+// Parses a date given as `YYYY-MM-DD`, `today`, `tomorrow`, `yesterday`, a weekday such as `fri` or
+// `next monday` (the first such day after today), an offset such as `+3d`, `-1w`, `+2m` or `+1y`, or
+// the end of the week, month or year (`eow`, `eom` or `eoy`).
+pub(crate) fn parse_date(arg: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let invalid = || format!("invalid date: `{}`", arg);
+    let expression = arg.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&expression, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    match expression.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return today.succ_opt().ok_or_else(invalid),
+        "yesterday" => return today.pred_opt().ok_or_else(invalid),
+        // Weeks end on Sunday.
+        "eow" => {
+            return add_days(today, 6 - i64::from(today.weekday().num_days_from_monday()))
+                .ok_or_else(invalid)
+        }
+        "eom" => {
+            return NaiveDate::from_ymd_opt(
+                today.year(),
+                today.month(),
+                days_in_month(today.year(), today.month()),
+            )
+            .ok_or_else(invalid)
+        }
+        "eoy" => return NaiveDate::from_ymd_opt(today.year(), 12, 31).ok_or_else(invalid),
+        _ => {}
+    }
+    if let Some(offset) = expression
+        .strip_prefix('+')
+        .map(|offset| (1, offset))
+        .or_else(|| expression.strip_prefix('-').map(|offset| (-1, offset)))
+    {
+        let (sign, offset) = offset;
+        if offset.len() < 2 || !offset.is_char_boundary(offset.len() - 1) {
+            return Err(invalid());
+        }
+        let (count, unit) = offset.split_at(offset.len() - 1);
+        let count = sign * count.parse::<i64>().map_err(|_| invalid())?;
+        return match unit {
+            "d" => add_days(today, count),
+            "w" => count.checked_mul(7).and_then(|days| add_days(today, days)),
+            "m" => add_months(today, count),
+            "y" => count
+                .checked_mul(12)
+                .and_then(|months| add_months(today, months)),
+            _ => None,
+        }
+        .ok_or_else(invalid);
+    }
+    let weekday = expression
+        .strip_prefix("next ")
+        .unwrap_or(&expression)
+        .trim_start();
+    let weekday = match weekday {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return Err(invalid()),
+    };
+    let days = (i64::from(weekday.num_days_from_monday())
+        - i64::from(today.weekday().num_days_from_monday())
+        + 6)
+        % 7
+        + 1;
+    add_days(today, days).ok_or_else(invalid)
+}
+
+pub(crate) fn add_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    date.checked_add_signed(Duration::try_days(days)?)
+}
+
+// Adds months, moving to the end of the month if the day does not exist in it.
+pub(crate) fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let month = i64::from(date.year()) * 12 + i64::from(date.month0()) + months;
+    let year = i32::try_from(month.div_euclid(12)).ok()?;
+    let month = month.rem_euclid(12) as u32 + 1;
+    NaiveDate::from_ymd_opt(year, month, date.day().min(days_in_month(year, month)))
+}
+
+pub(crate) fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if NaiveDate::from_ymd_opt(year, 2, 29).is_some() => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+pub(crate) fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ActionSubcommand {
     Add {
         description: String,
-        action: Option<String>,
+    },
+    Annotate {
+        description: String,
+        text: String,
+    },
+    Done {
+        description: String,
     },
     List {
-        all: bool,
+        status: Status,
+        context: Option<String>,
+        options: ListOptions,
+    },
+    EditNote {
+        description: String,
     },
     Remove {
         description: String,
     },
-    SetAction {
+    SetContext {
         description: String,
-        action: String,
+        context: String,
+    },
+    SetDate {
+        description: String,
+        field: DateField,
+        date: NaiveDate,
     },
     SetDescription {
         old_description: String,
         new_description: String,
     },
-    UnsetAction {
+    SetNote {
+        description: String,
+        note: String,
+    },
+    SetPriority {
+        description: String,
+        priority: i64,
+    },
+    SetRecurrence {
+        description: String,
+        recurrence: Recurrence,
+    },
+    Tag {
+        description: String,
+        tags: Vec<String>,
+    },
+    UnsetContext {
+        description: String,
+    },
+    UnsetDate {
+        description: String,
+        field: DateField,
+    },
+    UnsetNote {
+        description: String,
+    },
+    UnsetPriority {
+        description: String,
+    },
+    UnsetRecurrence {
+        description: String,
+    },
+    Untag {
         description: String,
+        tags: Vec<String>,
     },
 }
 
-impl GoalSubcommand {
-    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, String> {
+impl ActionSubcommand {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
         match args.next() {
             Some(subcommand) => match subcommand.as_str() {
                 "add" => {
-                    let mut action = None;
-                    let mut args = args.collect::<Vec<_>>();
-                    if !args.is_empty() && args[0] == "--action" {
-                        args.remove(0);
-                        if args.is_empty() {
-                            return Err("option `--action` requires an argument".into());
-                        }
-                        action = Some(args.remove(0));
-                    }
+                    let args = args.collect::<Vec<_>>();
                     if args.is_empty() {
-                        return Err("missing description".into());
+                        return Err(Error::Usage("missing description".into()));
                     }
                     Ok(Self::Add {
-                        action,
+                        description: args.join(" "),
+                    })
+                }
+                "annotate" => {
+                    let description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing annotation".into()));
+                    }
+                    Ok(Self::Annotate {
+                        description,
+                        text: args.join(" "),
+                    })
+                }
+                "done" => {
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing description".into()));
+                    }
+                    Ok(Self::Done {
                         description: args.join(" "),
                     })
                 }
                 "ls" => {
-                    let mut all = false;
+                    let mut status = Status::Open;
+                    let mut context = None;
+                    let mut options = ListOptions::default();
+                    let mut filter = Vec::new();
                     while let Some(arg) = args.next() {
-                        if arg == "--all" {
-                            all = true;
-                        } else {
-                            return Err(format!("extra argument: `{}`", arg));
+                        match arg.as_str() {
+                            "--all" => {
+                                status = Status::All;
+                                options.selection.later = true;
+                            }
+                            "--context" => {
+                                context = Some(
+                                    context_name(args.next().ok_or_else(|| {
+                                        Error::Usage(
+                                            "option `--context` requires an argument".into(),
+                                        )
+                                    })?)
+                                    .map_err(Error::Usage)?,
+                                )
+                            }
+                            "--done" => status = Status::Done,
+                            _ if arg.starts_with("--") => {
+                                if !options.parse_arg(&arg, &mut args)? {
+                                    return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                                }
+                            }
+                            _ => {
+                                if !options.parse_arg(&arg, &mut args)? {
+                                    filter.push(arg);
+                                }
+                            }
+                        }
+                    }
+                    options.parse_filter(&filter, Item::Action)?;
+                    Ok(Self::List {
+                        status,
+                        context,
+                        options,
+                    })
+                }
+                "note" => {
+                    let mut description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    if description == "--edit" {
+                        description = args
+                            .next()
+                            .ok_or_else(|| Error::Usage("missing description".into()))?;
+                        if let Some(arg) = args.next() {
+                            return Err(Error::Usage(format!("extra argument: `{}`", arg)));
                         }
+                        return Ok(Self::EditNote { description });
+                    }
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing note".into()));
                     }
-                    Ok(Self::List { all })
+                    Ok(Self::SetNote {
+                        description,
+                        note: args.join(" "),
+                    })
                 }
                 "rm" => {
                     let args = args.collect::<Vec<_>>();
                     if args.is_empty() {
-                        return Err("missing description".into());
+                        return Err(Error::Usage("missing description".into()));
                     }
                     Ok(Self::Remove {
                         description: args.join(" "),
@@ -192,1120 +732,6133 @@ impl GoalSubcommand {
                 }
                 "set" => match args.next() {
                     Some(field) => match field.as_str() {
-                        "action" => {
+                        "context" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let context = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing context".into()))?;
+                            if let Some(arg) = args.next() {
+                                return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                            }
+                            Ok(Self::SetContext {
+                                description,
+                                context: context_name(context).map_err(Error::Usage)?,
+                            })
+                        }
+                        "due" | "scheduled" => {
                             let description = args
                                 .next()
-                                .ok_or_else(|| "missing description".to_string())?;
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
                             let args = args.collect::<Vec<_>>();
                             if args.is_empty() {
-                                return Err("missing action".into());
+                                return Err(Error::Usage("missing date".into()));
                             }
-                            Ok(Self::SetAction {
+                            Ok(Self::SetDate {
                                 description,
-                                action: args.join(" "),
+                                field: if field == "due" {
+                                    DateField::Due
+                                } else {
+                                    DateField::Scheduled
+                                },
+                                date: parse_date(&args.join(" "), today()).map_err(Error::Usage)?,
                             })
                         }
                         "description" => {
                             let old_description = args
                                 .next()
-                                .ok_or_else(|| "missing old description".to_string())?;
+                                .ok_or_else(|| Error::Usage("missing old description".into()))?;
                             let args = args.collect::<Vec<_>>();
                             if args.is_empty() {
-                                return Err("missing new description".into());
+                                return Err(Error::Usage("missing new description".into()));
                             }
                             Ok(Self::SetDescription {
                                 old_description,
                                 new_description: args.join(" "),
                             })
                         }
-                        _ => Err(format!("no such field: `{}`", field)),
-                    },
-                    None => Err("missing field".into()),
-                },
-                "unset" => match args.next() {
-                    Some(field) => match field.as_str() {
-                        "action" => {
-                            let args = args.collect::<Vec<_>>();
-                            if args.is_empty() {
-                                return Err("missing description".into());
-                            }
-                            Ok(Self::UnsetAction {
+                        "priority" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let priority = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing priority".into()))?;
+                            if let Some(arg) = args.next() {
+                                return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                            }
+                            Ok(Self::SetPriority {
+                                description,
+                                priority: parse_priority(&priority).map_err(Error::Usage)?,
+                            })
+                        }
+                        "recurrence" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let recurrence = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing recurrence".into()))?;
+                            if let Some(arg) = args.next() {
+                                return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                            }
+                            Ok(Self::SetRecurrence {
+                                description,
+                                recurrence: Recurrence::from_arg(&recurrence)
+                                    .map_err(Error::Usage)?,
+                            })
+                        }
+                        _ => Err(Error::Usage(format!("no such field: `{}`", field))),
+                    },
+                    None => Err(Error::Usage("missing field".into())),
+                },
+                "tag" | "untag" => {
+                    let description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    let tags = args
+                        .map(tag_name)
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(Error::Usage)?;
+                    if tags.is_empty() {
+                        return Err(Error::Usage("missing tag".into()));
+                    }
+                    if subcommand == "tag" {
+                        Ok(Self::Tag { description, tags })
+                    } else {
+                        Ok(Self::Untag { description, tags })
+                    }
+                }
+                "unset" => match args.next() {
+                    Some(field) => match field.as_str() {
+                        "context" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetContext {
+                                description: args.join(" "),
+                            })
+                        }
+                        "due" | "scheduled" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetDate {
+                                description: args.join(" "),
+                                field: if field == "due" {
+                                    DateField::Due
+                                } else {
+                                    DateField::Scheduled
+                                },
+                            })
+                        }
+                        "note" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetNote {
+                                description: args.join(" "),
+                            })
+                        }
+                        "priority" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetPriority {
+                                description: args.join(" "),
+                            })
+                        }
+                        "recurrence" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetRecurrence {
                                 description: args.join(" "),
                             })
                         }
-                        _ => Err(format!("no such field: `{}`", field)),
+                        _ => Err(Error::Usage(format!("no such field: `{}`", field))),
                     },
-                    None => Err("missing field".into()),
+                    None => Err(Error::Usage("missing field".into())),
                 },
-                _ => Err(format!("no such subcommand: `{}`", subcommand)),
+                _ => Err(Error::Usage(format!(
+                    "no such subcommand: `{}`",
+                    subcommand
+                ))),
+            },
+            None => Err(Error::Usage("missing subcommand".into())),
+        }
+    }
+
+    pub fn run(self, store: &Store) -> Result<(), Error> {
+        match self {
+            Self::Add { description } => store.add_action(&description),
+            Self::Annotate { description, text } => store.annotate_action(&description, &text),
+            Self::Done { description } => {
+                let goals = store.complete_action(&description)?;
+                write_goals(store, &goals, Format::Text, None, &mut io::stdout())
+            }
+            Self::List {
+                status,
+                context,
+                options,
+            } => list_actions(store, status, context, &options, &mut io::stdout()),
+            Self::EditNote { description } => {
+                let note = store.action(&description)?.note;
+                let note = edit(note.as_deref().unwrap_or(""))?;
+                store.set_action_note(&description, Some(&note))
+            }
+            Self::Remove { description } => store.remove_action(&description),
+            Self::SetContext {
+                description,
+                context,
+            } => store.set_action_context(&description, Some(&context)),
+            Self::SetDate {
+                description,
+                field,
+                date,
+            } => store.set_action_date(&description, field, Some(date)),
+            Self::SetDescription {
+                old_description,
+                new_description,
+            } => store.set_action_description(&old_description, &new_description),
+            Self::SetNote { description, note } => store.set_action_note(&description, Some(&note)),
+            Self::SetPriority {
+                description,
+                priority,
+            } => store.set_action_priority(&description, Some(priority)),
+            Self::SetRecurrence {
+                description,
+                recurrence,
+            } => store.set_action_recurrence(&description, Some(recurrence)),
+            Self::Tag { description, tags } => store.tag_action(&description, &tags),
+            Self::UnsetContext { description } => store.set_action_context(&description, None),
+            Self::UnsetDate { description, field } => {
+                store.set_action_date(&description, field, None)
+            }
+            Self::UnsetNote { description } => store.set_action_note(&description, None),
+            Self::UnsetPriority { description } => store.set_action_priority(&description, None),
+            Self::UnsetRecurrence { description } => {
+                store.set_action_recurrence(&description, None)
+            }
+            Self::Untag { description, tags } => store.untag_action(&description, &tags),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigSubcommand {
+    List { format: Format },
+    Set { name: String, value: String },
+    Unset { name: String },
+}
+
+impl ConfigSubcommand {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
+        match args.next() {
+            Some(subcommand) => match subcommand.as_str() {
+                "ls" => Ok(Self::List {
+                    format: parse_format(args)?,
+                }),
+                "set" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    check_setting_name(&name)?;
+                    let value = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing value".into()))?;
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    check_setting_value(&name, &value)?;
+                    Ok(Self::Set { name, value })
+                }
+                "unset" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    check_setting_name(&name)?;
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    Ok(Self::Unset { name })
+                }
+                _ => Err(Error::Usage(format!(
+                    "no such subcommand: `{}`",
+                    subcommand
+                ))),
+            },
+            None => Err(Error::Usage("missing subcommand".into())),
+        }
+    }
+
+    pub fn run(self, store: &Store) -> Result<(), Error> {
+        match self {
+            Self::List { format } => list_config(store, format, &mut io::stdout()),
+            Self::Set { name, value } => store.set_config(&name, Some(&value)),
+            Self::Unset { name } => store.set_config(&name, None),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TagSubcommand {
+    List { format: Format },
+    Rename { old_name: String, new_name: String },
+}
+
+impl TagSubcommand {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
+        match args.next() {
+            Some(subcommand) => match subcommand.as_str() {
+                "ls" => Ok(Self::List {
+                    format: parse_format(args)?,
+                }),
+                "rename" => {
+                    let old_name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing old name".into()))?;
+                    let new_name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing new name".into()))?;
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    Ok(Self::Rename {
+                        old_name: tag_name(old_name).map_err(Error::Usage)?,
+                        new_name: tag_name(new_name).map_err(Error::Usage)?,
+                    })
+                }
+                _ => Err(Error::Usage(format!(
+                    "no such subcommand: `{}`",
+                    subcommand
+                ))),
+            },
+            None => Err(Error::Usage("missing subcommand".into())),
+        }
+    }
+
+    pub fn run(self, store: &Store) -> Result<(), Error> {
+        match self {
+            Self::List { format } => list_tags(store, format, &mut io::stdout()),
+            Self::Rename { old_name, new_name } => store.rename_tag(&old_name, &new_name),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProfileSubcommand {
+    List { format: Format },
+}
+
+impl ProfileSubcommand {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
+        match args.next() {
+            Some(subcommand) => match subcommand.as_str() {
+                "ls" => Ok(Self::List {
+                    format: parse_format(args)?,
+                }),
+                _ => Err(Error::Usage(format!(
+                    "no such subcommand: `{}`",
+                    subcommand
+                ))),
+            },
+            None => Err(Error::Usage("missing subcommand".into())),
+        }
+    }
+
+    pub fn run(self) -> Result<(), Error> {
+        match self {
+            Self::List { format } => {
+                list_profiles(&database::profiles()?, format, &mut io::stdout())
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ViewSubcommand {
+    Add {
+        name: String,
+        item: Item,
+        arguments: Vec<String>,
+    },
+    List {
+        format: Format,
+    },
+    Remove {
+        name: String,
+        item: Option<Item>,
+    },
+}
+
+impl ViewSubcommand {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
+        match args.next() {
+            Some(subcommand) => match subcommand.as_str() {
+                "add" => {
+                    let mut name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    let mut item = Item::Action;
+                    if name == "--goal" {
+                        item = Item::Goal;
+                        name = args
+                            .next()
+                            .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    }
+                    let arguments = args.collect::<Vec<_>>();
+                    check_view(&name, item, &arguments)?;
+                    Ok(Self::Add {
+                        name,
+                        item,
+                        arguments,
+                    })
+                }
+                "ls" => Ok(Self::List {
+                    format: parse_format(args)?,
+                }),
+                "rm" => {
+                    let mut name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    let mut item = None;
+                    if name == "--goal" {
+                        item = Some(Item::Goal);
+                        name = args
+                            .next()
+                            .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    }
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    Ok(Self::Remove { name, item })
+                }
+                _ => Err(Error::Usage(format!(
+                    "no such subcommand: `{}`",
+                    subcommand
+                ))),
+            },
+            None => Err(Error::Usage("missing subcommand".into())),
+        }
+    }
+
+    pub fn run(self, store: &Store) -> Result<(), Error> {
+        match self {
+            Self::Add {
+                name,
+                item,
+                arguments,
+            } => store.add_view(&name, item, &arguments),
+            Self::List { format } => list_views(store, format, &mut io::stdout()),
+            Self::Remove { name, item } => store.remove_view(&name, item),
+        }
+    }
+}
+
+// The arguments are checked when a view is added but parsed again whenever the view runs, so that
+// dates like `today` stay current.
+pub(crate) fn check_view(name: &str, item: Item, arguments: &[String]) -> Result<(), Error> {
+    let ls = iter::once("ls".to_string()).chain(arguments.iter().cloned());
+    match item {
+        Item::Action => ActionSubcommand::from_args(ls).map(|_| ()),
+        Item::Goal => GoalSubcommand::from_args(ls).map(|_| ()),
+    }?;
+    view_name(name.into()).map(|_| ())
+}
+
+// Views are run as `odo <view>`, so they cannot share a name with a command or an option.
+fn view_name(name: String) -> Result<String, Error> {
+    if name.is_empty()
+        || name.starts_with('-')
+        || name.chars().any(char::is_whitespace)
+        || COMMANDS.contains(&name.as_str())
+        || ["add", "ls", "rm"].contains(&name.as_str())
+    {
+        return Err(Error::Usage(format!("invalid view name: `{}`", name)));
+    }
+    Ok(name)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ContextSubcommand {
+    Add {
+        name: String,
+        description: Option<String>,
+        order: Option<i64>,
+    },
+    List {
+        format: Format,
+    },
+    Remove {
+        name: String,
+    },
+}
+
+impl ContextSubcommand {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
+        match args.next() {
+            Some(subcommand) => match subcommand.as_str() {
+                "add" => {
+                    let mut description = None;
+                    let mut order = None;
+                    let mut args = args.collect::<Vec<_>>();
+                    while !args.is_empty() {
+                        let value = match args[0].as_str() {
+                            "--description" => &mut description,
+                            "--order" => &mut order,
+                            _ => break,
+                        };
+                        let option = args.remove(0);
+                        if args.is_empty() {
+                            return Err(Error::Usage(format!(
+                                "option `{}` requires an argument",
+                                option
+                            )));
+                        }
+                        *value = Some(args.remove(0));
+                    }
+                    let mut args = args.into_iter();
+                    let name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    Ok(Self::Add {
+                        name: context_name(name).map_err(Error::Usage)?,
+                        description,
+                        order: order
+                            .map(|order| {
+                                order.parse().map_err(|_| {
+                                    Error::Usage(format!("invalid order: `{}`", order))
+                                })
+                            })
+                            .transpose()?,
+                    })
+                }
+                "ls" => Ok(Self::List {
+                    format: parse_format(args)?,
+                }),
+                "rm" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing name".into()))?;
+                    if let Some(arg) = args.next() {
+                        return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                    }
+                    Ok(Self::Remove {
+                        name: context_name(name).map_err(Error::Usage)?,
+                    })
+                }
+                _ => Err(Error::Usage(format!(
+                    "no such subcommand: `{}`",
+                    subcommand
+                ))),
             },
-            None => Err("missing subcommand".into()),
+            None => Err(Error::Usage("missing subcommand".into())),
+        }
+    }
+
+    pub fn run(self, store: &Store) -> Result<(), Error> {
+        match self {
+            Self::Add {
+                name,
+                description,
+                order,
+            } => store.add_context(&name, description.as_deref(), order),
+            Self::List { format } => list_contexts(store, format, &mut io::stdout()),
+            Self::Remove { name } => store.remove_context(&name),
         }
     }
+}
+
+// Contexts are written with a leading `@`, which is optional on the command line and not stored.
+pub(crate) fn context_name(arg: String) -> Result<String, String> {
+    let name = arg.strip_prefix('@').unwrap_or(&arg);
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("invalid context: `{}`", arg));
+    }
+    Ok(name.into())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GoalSubcommand {
+    Actions {
+        description: String,
+        format: Format,
+    },
+    Add {
+        description: String,
+        action: Option<String>,
+        parent: Option<String>,
+        goal_type: GoalType,
+    },
+    AddAction {
+        description: String,
+        action: String,
+    },
+    Annotate {
+        description: String,
+        text: String,
+    },
+    Done {
+        description: String,
+    },
+    EditNote {
+        description: String,
+    },
+    List {
+        status: Status,
+        stuck: bool,
+        tree: bool,
+        options: ListOptions,
+    },
+    MoveAction {
+        description: String,
+        position: usize,
+        action: String,
+    },
+    Remove {
+        description: String,
+        recursive: bool,
+    },
+    RemoveAction {
+        description: String,
+        action: String,
+    },
+    SetAction {
+        description: String,
+        action: String,
+    },
+    SetDate {
+        description: String,
+        field: DateField,
+        date: NaiveDate,
+    },
+    SetDescription {
+        old_description: String,
+        new_description: String,
+    },
+    SetNote {
+        description: String,
+        note: String,
+    },
+    SetParent {
+        description: String,
+        parent: String,
+    },
+    SetPriority {
+        description: String,
+        priority: i64,
+    },
+    SetType {
+        description: String,
+        goal_type: GoalType,
+    },
+    Tag {
+        description: String,
+        tags: Vec<String>,
+    },
+    UnsetAction {
+        description: String,
+    },
+    UnsetDate {
+        description: String,
+        field: DateField,
+    },
+    UnsetNote {
+        description: String,
+    },
+    UnsetParent {
+        description: String,
+    },
+    UnsetPriority {
+        description: String,
+    },
+    Untag {
+        description: String,
+        tags: Vec<String>,
+    },
+}
+
+impl GoalSubcommand {
+    pub fn from_args<T: Iterator<Item = String>>(mut args: T) -> Result<Self, Error> {
+        match args.next() {
+            Some(subcommand) => match subcommand.as_str() {
+                "actions" => {
+                    let mut args = args.peekable();
+                    let mut format = Format::Text;
+                    if args.next_if_eq("--format").is_some() {
+                        format = format_arg(&mut args)?;
+                    }
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing description".into()));
+                    }
+                    Ok(Self::Actions {
+                        description: args.join(" "),
+                        format,
+                    })
+                }
+                "add" => {
+                    let mut action = None;
+                    let mut parent = None;
+                    let mut goal_type = None;
+                    let mut args = args.collect::<Vec<_>>();
+                    while !args.is_empty() {
+                        let value = match args[0].as_str() {
+                            "--action" => &mut action,
+                            "--parent" => &mut parent,
+                            "--type" => &mut goal_type,
+                            _ => break,
+                        };
+                        let option = args.remove(0);
+                        if args.is_empty() {
+                            return Err(Error::Usage(format!(
+                                "option `{}` requires an argument",
+                                option
+                            )));
+                        }
+                        *value = Some(args.remove(0));
+                    }
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing description".into()));
+                    }
+                    Ok(Self::Add {
+                        action,
+                        description: args.join(" "),
+                        parent,
+                        goal_type: goal_type.map_or(Ok(GoalType::Parallel), |goal_type| {
+                            GoalType::from_arg(&goal_type)
+                        })?,
+                    })
+                }
+                "add-action" => {
+                    let description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing action".into()));
+                    }
+                    Ok(Self::AddAction {
+                        description,
+                        action: args.join(" "),
+                    })
+                }
+                "annotate" => {
+                    let description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing annotation".into()));
+                    }
+                    Ok(Self::Annotate {
+                        description,
+                        text: args.join(" "),
+                    })
+                }
+                "done" => {
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing description".into()));
+                    }
+                    Ok(Self::Done {
+                        description: args.join(" "),
+                    })
+                }
+                "ls" => {
+                    let mut status = Status::Open;
+                    // By default only the goals that need a new action are listed.
+                    let mut stuck = true;
+                    let mut tree = false;
+                    let mut options = ListOptions::default();
+                    let mut filter = Vec::new();
+                    while let Some(arg) = args.next() {
+                        match arg.as_str() {
+                            "--all" => {
+                                status = Status::Open;
+                                stuck = false;
+                                options.selection.later = true;
+                            }
+                            "--done" => {
+                                status = Status::Done;
+                                stuck = false;
+                            }
+                            "--everything" => {
+                                status = Status::All;
+                                stuck = false;
+                                options.selection.later = true;
+                            }
+                            "--tree" => tree = true,
+                            _ if arg.starts_with("--") => {
+                                if !options.parse_arg(&arg, &mut args)? {
+                                    return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                                }
+                            }
+                            _ => {
+                                if !options.parse_arg(&arg, &mut args)? {
+                                    filter.push(arg);
+                                }
+                            }
+                        }
+                    }
+                    options.parse_filter(&filter, Item::Goal)?;
+                    Ok(Self::List {
+                        status,
+                        stuck,
+                        tree,
+                        options,
+                    })
+                }
+                "move-action" => {
+                    let description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    let position = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing position".into()))?;
+                    let position = match position.parse::<usize>() {
+                        Ok(position) if position > 0 => position,
+                        _ => return Err(Error::Usage(format!("invalid position: `{}`", position))),
+                    };
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing action".into()));
+                    }
+                    Ok(Self::MoveAction {
+                        description,
+                        position,
+                        action: args.join(" "),
+                    })
+                }
+                "note" => {
+                    let mut description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    if description == "--edit" {
+                        description = args
+                            .next()
+                            .ok_or_else(|| Error::Usage("missing description".into()))?;
+                        if let Some(arg) = args.next() {
+                            return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                        }
+                        return Ok(Self::EditNote { description });
+                    }
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing note".into()));
+                    }
+                    Ok(Self::SetNote {
+                        description,
+                        note: args.join(" "),
+                    })
+                }
+                "rm" => {
+                    let mut args = args.collect::<Vec<_>>();
+                    let recursive = !args.is_empty() && args[0] == "--recursive";
+                    if recursive {
+                        args.remove(0);
+                    }
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing description".into()));
+                    }
+                    Ok(Self::Remove {
+                        description: args.join(" "),
+                        recursive,
+                    })
+                }
+                "rm-action" => {
+                    let description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    let args = args.collect::<Vec<_>>();
+                    if args.is_empty() {
+                        return Err(Error::Usage("missing action".into()));
+                    }
+                    Ok(Self::RemoveAction {
+                        description,
+                        action: args.join(" "),
+                    })
+                }
+                "set" => match args.next() {
+                    Some(field) => match field.as_str() {
+                        "action" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing action".into()));
+                            }
+                            Ok(Self::SetAction {
+                                description,
+                                action: args.join(" "),
+                            })
+                        }
+                        "due" | "scheduled" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing date".into()));
+                            }
+                            Ok(Self::SetDate {
+                                description,
+                                field: if field == "due" {
+                                    DateField::Due
+                                } else {
+                                    DateField::Scheduled
+                                },
+                                date: parse_date(&args.join(" "), today()).map_err(Error::Usage)?,
+                            })
+                        }
+                        "description" => {
+                            let old_description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing old description".into()))?;
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing new description".into()));
+                            }
+                            Ok(Self::SetDescription {
+                                old_description,
+                                new_description: args.join(" "),
+                            })
+                        }
+                        "parent" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing parent".into()));
+                            }
+                            Ok(Self::SetParent {
+                                description,
+                                parent: args.join(" "),
+                            })
+                        }
+                        "priority" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let priority = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing priority".into()))?;
+                            if let Some(arg) = args.next() {
+                                return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                            }
+                            Ok(Self::SetPriority {
+                                description,
+                                priority: parse_priority(&priority).map_err(Error::Usage)?,
+                            })
+                        }
+                        "type" => {
+                            let description = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing description".into()))?;
+                            let goal_type = args
+                                .next()
+                                .ok_or_else(|| Error::Usage("missing type".into()))?;
+                            if let Some(arg) = args.next() {
+                                return Err(Error::Usage(format!("extra argument: `{}`", arg)));
+                            }
+                            Ok(Self::SetType {
+                                description,
+                                goal_type: GoalType::from_arg(&goal_type)?,
+                            })
+                        }
+                        _ => Err(Error::Usage(format!("no such field: `{}`", field))),
+                    },
+                    None => Err(Error::Usage("missing field".into())),
+                },
+                "tag" | "untag" => {
+                    let description = args
+                        .next()
+                        .ok_or_else(|| Error::Usage("missing description".into()))?;
+                    let tags = args
+                        .map(tag_name)
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(Error::Usage)?;
+                    if tags.is_empty() {
+                        return Err(Error::Usage("missing tag".into()));
+                    }
+                    if subcommand == "tag" {
+                        Ok(Self::Tag { description, tags })
+                    } else {
+                        Ok(Self::Untag { description, tags })
+                    }
+                }
+                "unset" => match args.next() {
+                    Some(field) => match field.as_str() {
+                        "action" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetAction {
+                                description: args.join(" "),
+                            })
+                        }
+                        "due" | "scheduled" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetDate {
+                                description: args.join(" "),
+                                field: if field == "due" {
+                                    DateField::Due
+                                } else {
+                                    DateField::Scheduled
+                                },
+                            })
+                        }
+                        "note" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetNote {
+                                description: args.join(" "),
+                            })
+                        }
+                        "parent" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetParent {
+                                description: args.join(" "),
+                            })
+                        }
+                        "priority" => {
+                            let args = args.collect::<Vec<_>>();
+                            if args.is_empty() {
+                                return Err(Error::Usage("missing description".into()));
+                            }
+                            Ok(Self::UnsetPriority {
+                                description: args.join(" "),
+                            })
+                        }
+                        _ => Err(Error::Usage(format!("no such field: `{}`", field))),
+                    },
+                    None => Err(Error::Usage("missing field".into())),
+                },
+                _ => Err(Error::Usage(format!(
+                    "no such subcommand: `{}`",
+                    subcommand
+                ))),
+            },
+            None => Err(Error::Usage("missing subcommand".into())),
+        }
+    }
+
+    pub fn run(self, store: &Store) -> Result<(), Error> {
+        match self {
+            Self::Actions {
+                description,
+                format,
+            } => list_goal_actions(store, description, format, &mut io::stdout()),
+            Self::Add {
+                description,
+                action,
+                parent,
+                goal_type,
+            } => store.add_goal(
+                &description,
+                action.as_deref(),
+                parent.as_deref(),
+                goal_type,
+            ),
+            Self::AddAction {
+                description,
+                action,
+            } => store.add_goal_action(&description, &action),
+            Self::Annotate { description, text } => store.annotate_goal(&description, &text),
+            Self::Done { description } => store.complete_goal(&description),
+            Self::EditNote { description } => {
+                let note = store.goal(&description)?.note;
+                let note = edit(note.as_deref().unwrap_or(""))?;
+                store.set_goal_note(&description, Some(&note))
+            }
+            Self::List {
+                status,
+                stuck,
+                tree,
+                options,
+            } => {
+                if tree {
+                    list_goal_tree(store, status, &options, &mut io::stdout())
+                } else {
+                    list_goals(
+                        store,
+                        status,
+                        stuck,
+                        &options,
+                        terminal_width(),
+                        &mut io::stdout(),
+                    )
+                }
+            }
+            Self::MoveAction {
+                description,
+                position,
+                action,
+            } => store.move_goal_action(&description, position, &action),
+            Self::Remove {
+                description,
+                recursive,
+            } => store.remove_goal(&description, recursive),
+            Self::RemoveAction {
+                description,
+                action,
+            } => store.remove_goal_action(&description, &action),
+            Self::SetAction {
+                description,
+                action,
+            } => store.set_goal_action(&description, &action),
+            Self::SetDate {
+                description,
+                field,
+                date,
+            } => store.set_goal_date(&description, field, Some(date)),
+            Self::SetDescription {
+                old_description,
+                new_description,
+            } => store.set_goal_description(&old_description, &new_description),
+            Self::SetNote { description, note } => store.set_goal_note(&description, Some(&note)),
+            Self::SetParent {
+                description,
+                parent,
+            } => store.set_goal_parent(&description, Some(&parent)),
+            Self::SetPriority {
+                description,
+                priority,
+            } => store.set_goal_priority(&description, Some(priority)),
+            Self::SetType {
+                description,
+                goal_type,
+            } => store.set_goal_type(&description, goal_type),
+            Self::Tag { description, tags } => store.tag_goal(&description, &tags),
+            Self::UnsetAction { description } => store.unset_goal_action(&description),
+            Self::UnsetDate { description, field } => {
+                store.set_goal_date(&description, field, None)
+            }
+            Self::UnsetNote { description } => store.set_goal_note(&description, None),
+            Self::UnsetParent { description } => store.set_goal_parent(&description, None),
+            Self::UnsetPriority { description } => store.set_goal_priority(&description, None),
+            Self::Untag { description, tags } => store.untag_goal(&description, &tags),
+        }
+    }
+}
+
+// Lists the available actions beneath the goals they move forward, followed by the unfinished actions
+// that no unfinished goal references, which are flagged since nothing says why they matter.
+fn list_next_actions<T: AsRef<str>, U: Write>(
+    store: &Store,
+    context: Option<T>,
+    format: Format,
+    writer: &mut U,
+) -> Result<(), Error> {
+    let next = store.next_actions(context.as_ref().map(AsRef::as_ref))?;
+    if format != Format::Text {
+        // An action that serves several goals is written once, where it is first listed.
+        let mut listed = HashSet::new();
+        let rows = next
+            .iter()
+            .flat_map(|next| &next.actions)
+            .filter(|action| listed.insert(action.id))
+            .map(|action| action_record(store, action, ITEM_COLUMNS))
+            .collect::<Result<Vec<_>, _>>()?;
+        return format::write_records(writer, format, ITEM_COLUMNS, &rows);
+    }
+    for NextActions { goal, actions } in next {
+        match goal {
+            Some(goal) => writeln!(writer, "{} {}", goal.id, goal.description)
+                .map_err(|e| Error::Storage(format!("unable to write description: {}", e)))?,
+            None => writeln!(writer, "no goal")
+                .map_err(|e| Error::Storage(format!("unable to write heading: {}", e)))?,
+        }
+        for action in actions {
+            writeln!(writer, "  {} {}", action.id, action.description)
+                .map_err(|e| Error::Storage(format!("unable to write description: {}", e)))?;
+        }
+    }
+    Ok(())
+}
+
+fn list_actions<T: AsRef<str>, U: Write>(
+    store: &Store,
+    status: Status,
+    context: Option<T>,
+    options: &ListOptions,
+    writer: &mut U,
+) -> Result<(), Error> {
+    let actions = store.query_actions(
+        status,
+        context.as_ref().map(AsRef::as_ref),
+        &options.selection,
+    )?;
+    write_actions(
+        store,
+        &actions,
+        options.format,
+        options.template(store, Item::Action)?.as_ref(),
+        writer,
+    )
+}
+
+fn list_contexts<T: Write>(store: &Store, format: Format, writer: &mut T) -> Result<(), Error> {
+    let contexts = store.contexts()?;
+    if format != Format::Text {
+        let rows = contexts
+            .into_iter()
+            .map(|context| {
+                vec![
+                    format::Value::Text(context.name),
+                    format::Value::from(context.description),
+                    format::Value::from(context.position),
+                ]
+            })
+            .collect::<Vec<_>>();
+        return format::write_records(writer, format, &["name", "description", "position"], &rows);
+    }
+    for context in contexts {
+        match context.description {
+            Some(description) => writeln!(writer, "@{} {}", context.name, description),
+            None => writeln!(writer, "@{}", context.name),
+        }
+        .map_err(|e| Error::Storage(format!("unable to write context: {}", e)))?;
+    }
+    Ok(())
+}
+
+// Goals are listed in a table when `width`, the width of the terminal, is given.
+fn list_goals<T: Write>(
+    store: &Store,
+    status: Status,
+    stuck: bool,
+    options: &ListOptions,
+    width: Option<usize>,
+    writer: &mut T,
+) -> Result<(), Error> {
+    let goals = store.query_goals(status, stuck, &options.selection)?;
+    let template = options.template(store, Item::Goal)?;
+    match width {
+        Some(width) if options.format == Format::Text && template.is_none() => {
+            write_goal_table(store, &goals, width, writer)
+        }
+        _ => write_goals(store, &goals, options.format, template.as_ref(), writer),
+    }
+}
+
+// The description of a goal's next unfinished action.
+fn next_action(store: &Store, goal: &Goal) -> Result<Option<String>, Error> {
+    Ok(store
+        .goal_actions(&format!("#{}", goal.id))?
+        .into_iter()
+        .next()
+        .map(|action| action.description))
+}
+
+fn write_goal_table<T: Write>(
+    store: &Store,
+    goals: &[Goal],
+    width: usize,
+    writer: &mut T,
+) -> Result<(), Error> {
+    let rows = goals
+        .iter()
+        .map(|goal| {
+            Ok(vec![
+                goal.id.to_string(),
+                goal.description.clone(),
+                next_action(store, goal)?.unwrap_or_default(),
+                goal.due.clone().unwrap_or_default(),
+            ])
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+    table::write_table(
+        writer,
+        &[
+            table::Column {
+                heading: "ID",
+                align: table::Align::Right,
+                flexible: false,
+            },
+            table::Column {
+                heading: "Goal",
+                align: table::Align::Left,
+                flexible: true,
+            },
+            table::Column {
+                heading: "Action",
+                align: table::Align::Left,
+                flexible: true,
+            },
+            table::Column {
+                heading: "Due",
+                align: table::Align::Left,
+                flexible: false,
+            },
+        ],
+        &rows,
+        width,
+    )
+}
+
+// The width of the terminal that standard output is written to, if it is one.
+fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size_of(io::stdout())
+        .map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+fn list_goal_actions<T: AsRef<str>, U: Write>(
+    store: &Store,
+    goal: T,
+    format: Format,
+    writer: &mut U,
+) -> Result<(), Error> {
+    let actions = store.goal_actions(goal.as_ref())?;
+    write_actions(store, &actions, format, None, writer)
+}
+
+fn list_goal_tree<T: Write>(
+    store: &Store,
+    status: Status,
+    options: &ListOptions,
+    writer: &mut T,
+) -> Result<(), Error> {
+    // Unlike the flat listing, a tree includes the goals that have an unfinished action.
+    let goals = store.query_goals(status, false, &options.selection)?;
+    if options.format != Format::Text {
+        return write_goals(store, &goals, options.format, None, writer);
+    }
+    let template = options.template(store, Item::Goal)?;
+    // A goal whose parent is not listed is shown at the top level rather than hidden.
+    let listed = goals.iter().map(|goal| goal.id).collect::<HashSet<_>>();
+    let mut children = BTreeMap::<Option<i64>, Vec<(i64, String)>>::new();
+    for goal in &goals {
+        let parent = goal.parent.filter(|parent| listed.contains(parent));
+        let line = match &template {
+            Some(template) => template.render(&goal_record(store, goal, TEMPLATE_FIELDS)?),
+            None => format!("{} {}", goal.id, goal.description),
+        };
+        children.entry(parent).or_default().push((goal.id, line));
+    }
+    write_goal_tree(&children, None, 0, writer)
+}
+
+fn write_goal_tree<T: Write>(
+    children: &BTreeMap<Option<i64>, Vec<(i64, String)>>,
+    parent: Option<i64>,
+    depth: usize,
+    writer: &mut T,
+) -> Result<(), Error> {
+    for (id, line) in children.get(&parent).into_iter().flatten() {
+        writeln!(writer, "{:indent$}{}", "", line, indent = 2 * depth)
+            .map_err(|e| Error::Storage(format!("unable to write description: {}", e)))?;
+        write_goal_tree(children, Some(*id), depth + 1, writer)?;
+    }
+    Ok(())
+}
+
+// Writes each action's ID and description, or its record in `format`, or the action with
+// `template` if one is given.
+fn write_actions<T: Write>(
+    store: &Store,
+    actions: &[Action],
+    format: Format,
+    template: Option<&Template>,
+    writer: &mut T,
+) -> Result<(), Error> {
+    let fields = template.map_or(ITEM_COLUMNS, |_| TEMPLATE_FIELDS);
+    let listed = actions
+        .iter()
+        .map(|action| {
+            Ok((
+                action.id,
+                action.description.as_str(),
+                action_record(store, action, fields)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    write_listing(Item::Action, listed, format, template, writer)
+}
+
+// Writes each goal's ID and description, or its record in `format`, or the goal with `template` if
+// one is given.
+fn write_goals<T: Write>(
+    store: &Store,
+    goals: &[Goal],
+    format: Format,
+    template: Option<&Template>,
+    writer: &mut T,
+) -> Result<(), Error> {
+    let fields = template.map_or(ITEM_COLUMNS, |_| TEMPLATE_FIELDS);
+    let listed = goals
+        .iter()
+        .map(|goal| {
+            Ok((
+                goal.id,
+                goal.description.as_str(),
+                goal_record(store, goal, fields)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    write_listing(Item::Goal, listed, format, template, writer)
+}
+
+// Writes the listed items, each given by its ID, description, and record, in `format`, or with
+// `template` if one is given.  Records hold the template's fields when there is a template and the
+// item columns otherwise.
+fn write_listing<T: Write>(
+    item: Item,
+    listed: Vec<(i64, &str, Vec<format::Value>)>,
+    format: Format,
+    template: Option<&Template>,
+    writer: &mut T,
+) -> Result<(), Error> {
+    match template {
+        Some(template) => {
+            for (_, _, record) in listed {
+                writeln!(writer, "{}", template.render(&record)).map_err(|e| {
+                    Error::Storage(format!("unable to write {}: {}", item.name(), e))
+                })?;
+            }
+            Ok(())
+        }
+        None if format == Format::Text => {
+            for (id, description, _) in listed {
+                writeln!(writer, "{} {}", id, description)
+                    .map_err(|e| Error::Storage(format!("unable to write description: {}", e)))?;
+            }
+            Ok(())
+        }
+        None => {
+            let records = listed
+                .into_iter()
+                .map(|(_, _, record)| record)
+                .collect::<Vec<_>>();
+            format::write_records(writer, format, ITEM_COLUMNS, &records)
+        }
+    }
+}
+
+// The fields of the records that listings write for actions and goals.  Scripts depend on these,
+// so fields may be added but never removed or renamed.
+const ITEM_COLUMNS: &[&str] = &[
+    "type",
+    "id",
+    "description",
+    "done",
+    "created",
+    "due",
+    "scheduled",
+    "priority",
+    "context",
+    "recurrence",
+    "parent",
+    "sequential",
+    "goals",
+    "actions",
+    "tags",
+    "note",
+];
+
+// The fields that templates can use: those of the item records, followed by the description of a
+// goal's next unfinished action and of the first unfinished goal an action belongs to.
+pub(crate) const TEMPLATE_FIELDS: &[&str] = &[
+    "type",
+    "id",
+    "description",
+    "done",
+    "created",
+    "due",
+    "scheduled",
+    "priority",
+    "context",
+    "recurrence",
+    "parent",
+    "sequential",
+    "goals",
+    "actions",
+    "tags",
+    "note",
+    "action",
+    "goal",
+];
+
+// An action's values for `fields`, which are item columns or template fields.
+fn action_record(
+    store: &Store,
+    action: &Action,
+    fields: &[&str],
+) -> Result<Vec<format::Value>, Error> {
+    fields
+        .iter()
+        .map(|field| {
+            Ok(match *field {
+                "type" => format::Value::Text(Item::Action.name().into()),
+                "id" => format::Value::Integer(action.id),
+                "description" => format::Value::Text(action.description.clone()),
+                "done" => format::Value::from(action.done.clone()),
+                "created" => format::Value::from(action.created.clone()),
+                "due" => format::Value::from(action.due.clone()),
+                "scheduled" => format::Value::from(action.scheduled.clone()),
+                "priority" => format::Value::from(action.priority),
+                "context" => format::Value::from(action.context.clone()),
+                "recurrence" => format::Value::from(action.recurrence.clone()),
+                "goals" => ids(&action.goals),
+                "tags" => tags(&action.tags),
+                "note" => format::Value::from(action.note.clone()),
+                "goal" => format::Value::from(first_open_goal(store, action)?),
+                _ => format::Value::Null,
+            })
+        })
+        .collect()
+}
+
+// A goal's values for `fields`, which are item columns or template fields.
+fn goal_record(store: &Store, goal: &Goal, fields: &[&str]) -> Result<Vec<format::Value>, Error> {
+    fields
+        .iter()
+        .map(|field| {
+            Ok(match *field {
+                "type" => format::Value::Text(Item::Goal.name().into()),
+                "id" => format::Value::Integer(goal.id),
+                "description" => format::Value::Text(goal.description.clone()),
+                "done" => format::Value::from(goal.done.clone()),
+                "created" => format::Value::from(goal.created.clone()),
+                "due" => format::Value::from(goal.due.clone()),
+                "scheduled" => format::Value::from(goal.scheduled.clone()),
+                "priority" => format::Value::from(goal.priority),
+                "parent" => format::Value::from(goal.parent),
+                "sequential" => format::Value::Boolean(goal.sequential),
+                "actions" => ids(&goal.actions),
+                "tags" => tags(&goal.tags),
+                "note" => format::Value::from(goal.note.clone()),
+                "action" => format::Value::from(next_action(store, goal)?),
+                _ => format::Value::Null,
+            })
+        })
+        .collect()
+}
+
+fn ids(ids: &[i64]) -> format::Value {
+    format::Value::List(ids.iter().copied().map(format::Value::Integer).collect())
+}
+
+fn tags(tags: &[String]) -> format::Value {
+    format::Value::List(tags.iter().cloned().map(format::Value::Text).collect())
+}
+
+// The description of the first unfinished goal an action belongs to.
+fn first_open_goal(store: &Store, action: &Action) -> Result<Option<String>, Error> {
+    for id in &action.goals {
+        let goal = store.goal(&format!("#{}", id))?;
+        if goal.done.is_none() {
+            return Ok(Some(goal.description));
+        }
+    }
+    Ok(None)
+}
+
+// Lists every setting, showing the default for those that are not set.
+fn list_config<T: Write>(store: &Store, format: Format, writer: &mut T) -> Result<(), Error> {
+    let mut rows = Vec::new();
+    for setting in store.settings()? {
+        if format == Format::Text {
+            writeln!(writer, "{} {}", setting.name, setting.value)
+                .map_err(|e| Error::Storage(format!("unable to write setting: {}", e)))?;
+        }
+        rows.push(vec![
+            format::Value::Text(setting.name),
+            format::Value::Text(setting.value),
+        ]);
+    }
+    if format != Format::Text {
+        format::write_records(writer, format, &["name", "value"], &rows)?;
+    }
+    Ok(())
+}
+
+// Lists each tag with the number of actions and goals it is on.
+fn list_tags<T: Write>(store: &Store, format: Format, writer: &mut T) -> Result<(), Error> {
+    let tags = store.tags()?;
+    if format != Format::Text {
+        let rows = tags
+            .into_iter()
+            .map(|tag| {
+                vec![
+                    format::Value::Text(tag.name),
+                    format::Value::Integer(tag.count),
+                ]
+            })
+            .collect::<Vec<_>>();
+        return format::write_records(writer, format, &["name", "count"], &rows);
+    }
+    for tag in tags {
+        writeln!(writer, "+{} {}", tag.name, tag.count)
+            .map_err(|e| Error::Storage(format!("unable to write tag: {}", e)))?;
+    }
+    Ok(())
+}
+
+// Backs the database up to `path`, or, without one, into the backup directory, writing the path
+// of the backup.
+fn backup<T: Write>(store: &Store, path: Option<PathBuf>, writer: &mut T) -> Result<(), Error> {
+    match path {
+        Some(path) => store.backup(&path),
+        None => writeln!(writer, "{}", store.snapshot()?.display())
+            .map_err(|e| Error::Storage(format!("unable to write path: {}", e))),
+    }
+}
+
+// Writes the problems that the database has.  With `fix`, every problem that can be is fixed
+// first.  It is an error for problems to remain.
+fn doctor<T: Write>(store: &Store, fix: bool, writer: &mut T) -> Result<(), Error> {
+    let problems = if fix {
+        store.repair()?
+    } else {
+        store
+            .check()?
+            .into_iter()
+            .map(|problem| (problem, false))
+            .collect()
+    };
+    let mut remaining = 0;
+    for (problem, fixed) in &problems {
+        if *fixed {
+            writeln!(writer, "fixed: {}", problem.description())
+        } else {
+            remaining += 1;
+            writeln!(writer, "{}", problem.description())
+        }
+        .map_err(|e| Error::Storage(format!("unable to write problem: {}", e)))?;
+    }
+    match remaining {
+        0 => Ok(()),
+        1 => Err(Error::Storage("1 problem found".into())),
+        _ => Err(Error::Storage(format!("{} problems found", remaining))),
+    }
+}
+
+fn list_profiles<T: Write>(
+    profiles: &[(String, PathBuf)],
+    format: Format,
+    writer: &mut T,
+) -> Result<(), Error> {
+    if format != Format::Text {
+        let rows = profiles
+            .iter()
+            .map(|(name, path)| {
+                vec![
+                    format::Value::Text(name.clone()),
+                    format::Value::Text(path.display().to_string()),
+                ]
+            })
+            .collect::<Vec<_>>();
+        return format::write_records(writer, format, &["name", "path"], &rows);
+    }
+    for (name, _) in profiles {
+        writeln!(writer, "{}", name)
+            .map_err(|e| Error::Storage(format!("unable to write profile: {}", e)))?;
+    }
+    Ok(())
+}
+
+// Runs `VISUAL` or `EDITOR`, falling back to vi, on a temporary file holding `text` and returns the
+// file's contents once the editor exits.
+fn edit(text: &str) -> Result<String, Error> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".into());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let path = env::temp_dir().join(format!("odo-note-{}.txt", process::id()));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| Error::Storage(format!("unable to create `{}`: {}", path.display(), e)))?;
+    let written = if text.is_empty() {
+        Ok(())
+    } else {
+        writeln!(file, "{}", text)
+    };
+    drop(file);
+    let result = written
+        .map_err(|e| Error::Storage(format!("unable to write `{}`: {}", path.display(), e)))
+        .and_then(|_| {
+            process::Command::new(program)
+                .args(words)
+                .arg(&path)
+                .status()
+                .map_err(|e| Error::Storage(format!("unable to run `{}`: {}", program, e)))
+        })
+        .and_then(|status| {
+            if status.success() {
+                Ok(())
+            } else {
+                Err(Error::Storage(format!("editor failed: {}", status)))
+            }
+        })
+        .and_then(|_| {
+            fs::read_to_string(&path)
+                .map_err(|e| Error::Storage(format!("unable to read `{}`: {}", path.display(), e)))
+        });
+    let _ = fs::remove_file(&path);
+    result
+}
+
+fn search<T: AsRef<str>, U: Write>(
+    store: &Store,
+    query: T,
+    all: bool,
+    format: Format,
+    writer: &mut U,
+) -> Result<(), Error> {
+    let matches = store.search(query.as_ref(), all)?;
+    if format != Format::Text {
+        let rows = matches
+            .iter()
+            .map(|found| match found {
+                Match::Action(action) => action_record(store, action, ITEM_COLUMNS),
+                Match::Goal(goal) => goal_record(store, goal, ITEM_COLUMNS),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        return format::write_records(writer, format, ITEM_COLUMNS, &rows);
+    }
+    for found in matches {
+        match found {
+            Match::Action(action) => {
+                writeln!(writer, "action {} {}", action.id, action.description)
+            }
+            Match::Goal(goal) => writeln!(writer, "goal {} {}", goal.id, goal.description),
+        }
+        .map_err(|e| Error::Storage(format!("unable to write result: {}", e)))?;
+    }
+    Ok(())
+}
+
+fn show_action<T: AsRef<str>, U: Write>(
+    store: &Store,
+    action: T,
+    writer: &mut U,
+) -> Result<(), Error> {
+    let action = store.action(action.as_ref())?;
+    let mut fields = vec![
+        ("done", action.done),
+        (
+            "context",
+            action.context.map(|context| format!("@{}", context)),
+        ),
+        ("due", action.due),
+        ("scheduled", action.scheduled),
+        (
+            "priority",
+            action.priority.map(|priority| priority.to_string()),
+        ),
+        ("recurrence", action.recurrence),
+    ];
+    for id in action.goals {
+        let goal = store.goal(&format!("#{}", id))?;
+        fields.push(("goal", Some(format!("{} {}", goal.id, goal.description))));
+    }
+    let annotations = store.action_annotations(&format!("#{}", action.id))?;
+    write_details(
+        action.id,
+        &action.description,
+        fields,
+        &action.tags,
+        action.note,
+        annotations,
+        writer,
+    )
+}
+
+fn show_goal<T: AsRef<str>, U: Write>(store: &Store, goal: T, writer: &mut U) -> Result<(), Error> {
+    let goal = store.goal(goal.as_ref())?;
+    let parent = goal
+        .parent
+        .map(|parent| store.goal(&format!("#{}", parent)))
+        .transpose()?;
+    let mut fields = vec![
+        ("done", goal.done),
+        (
+            "parent",
+            parent.map(|parent| format!("{} {}", parent.id, parent.description)),
+        ),
+        (
+            "type",
+            Some(
+                if goal.sequential {
+                    "sequential"
+                } else {
+                    "parallel"
+                }
+                .to_string(),
+            ),
+        ),
+        ("due", goal.due),
+        ("scheduled", goal.scheduled),
+        (
+            "priority",
+            goal.priority.map(|priority| priority.to_string()),
+        ),
+    ];
+    for id in goal.actions {
+        let action = store.action(&format!("#{}", id))?;
+        fields.push((
+            "action",
+            Some(format!("{} {}", action.id, action.description)),
+        ));
+    }
+    let annotations = store.goal_annotations(&format!("#{}", goal.id))?;
+    write_details(
+        goal.id,
+        &goal.description,
+        fields,
+        &goal.tags,
+        goal.note,
+        annotations,
+        writer,
+    )
+}
+
+// Writes the heading and fields that `show` prints for an item, followed by its tags, note, and
+// annotations, which are shown in local time.  Fields without a value are left out.
+fn write_details<T: Write>(
+    id: i64,
+    description: &str,
+    mut fields: Vec<(&str, Option<String>)>,
+    tags: &[String],
+    note: Option<String>,
+    annotations: Vec<Annotation>,
+    writer: &mut T,
+) -> Result<(), Error> {
+    if !tags.is_empty() {
+        let tags = tags
+            .iter()
+            .map(|tag| format!("+{}", tag))
+            .collect::<Vec<_>>();
+        fields.push(("tags", Some(tags.join(" "))));
+    }
+    writeln!(writer, "{} {}", id, description)
+        .map_err(|e| Error::Storage(format!("unable to write description: {}", e)))?;
+    for (name, value) in fields {
+        if let Some(value) = value {
+            writeln!(writer, "{}: {}", name, value)
+                .map_err(|e| Error::Storage(format!("unable to write {}: {}", name, e)))?;
+        }
+    }
+    if let Some(note) = note {
+        writeln!(writer, "note:")
+            .map_err(|e| Error::Storage(format!("unable to write note: {}", e)))?;
+        for line in note.lines() {
+            if line.is_empty() {
+                writeln!(writer)
+            } else {
+                writeln!(writer, "  {}", line)
+            }
+            .map_err(|e| Error::Storage(format!("unable to write note: {}", e)))?;
+        }
+    }
+    if !annotations.is_empty() {
+        writeln!(writer, "annotations:")
+            .map_err(|e| Error::Storage(format!("unable to write annotations: {}", e)))?;
+    }
+    for annotation in annotations {
+        let created = NaiveDateTime::parse_from_str(&annotation.created, "%Y-%m-%d %H:%M:%S")
+            .map_err(|e| Error::Storage(format!("unable to read annotation: {}", e)))?;
+        writeln!(
+            writer,
+            "  {} {}",
+            Local
+                .from_utc_datetime(&created)
+                .format("%Y-%m-%d %H:%M:%S"),
+            annotation.text
+        )
+        .map_err(|e| Error::Storage(format!("unable to write annotation: {}", e)))?;
+    }
+    Ok(())
+}
+
+// Lists each view with the kind of item it lists and its arguments, quoted as for the shell where
+// needed.
+fn list_views<T: Write>(store: &Store, format: Format, writer: &mut T) -> Result<(), Error> {
+    let views = store.views()?;
+    if format != Format::Text {
+        let rows = views
+            .into_iter()
+            .map(|view| {
+                vec![
+                    format::Value::Text(view.name),
+                    format::Value::Text(view.item.name().into()),
+                    format::Value::List(
+                        view.arguments
+                            .into_iter()
+                            .map(format::Value::Text)
+                            .collect(),
+                    ),
+                ]
+            })
+            .collect::<Vec<_>>();
+        return format::write_records(writer, format, &["name", "item", "arguments"], &rows);
+    }
+    for view in views {
+        let mut line = format!("{} {}", view.name, view.item.name());
+        for argument in view.arguments {
+            line.push(' ');
+            line.push_str(&shell_quote(&argument));
+        }
+        writeln!(writer, "{}", line)
+            .map_err(|e| Error::Storage(format!("unable to write view: {}", e)))?;
+    }
+    Ok(())
+}
+
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "+-./:=@_,%#".contains(c))
+    {
+        arg.into()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_missing_command() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([])),
+            Err(Error::Usage("missing command".into()))
+        );
+    }
+
+    #[test]
+    fn reports_no_such_command() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["foo".to_string()])),
+            Err(Error::Usage("no such command: `foo`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_next() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["next".to_string()])),
+            Ok(Command::Next {
+                context: None,
+                format: Format::Text
+            })
+        );
+    }
+
+    #[test]
+    fn reports_extra_next_argument() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "next".to_string(),
+                "foo".to_string()
+            ])),
+            Err(Error::Usage("extra argument: `foo`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_next_context() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "next".to_string(),
+                "--context".to_string(),
+                "@home".to_string()
+            ])),
+            Ok(Command::Next {
+                context: Some("home".into()),
+                format: Format::Text
+            })
+        );
+    }
+
+    #[test]
+    fn parses_next_format() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "next".to_string(),
+                "--format".to_string(),
+                "jsonl".to_string()
+            ])),
+            Ok(Command::Next {
+                context: None,
+                format: Format::JsonLines
+            })
+        );
+    }
+
+    #[test]
+    fn reports_invalid_format() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "tag".to_string(),
+                "ls".to_string(),
+                "--format".to_string(),
+                "xml".to_string()
+            ])),
+            Err(Error::Usage("invalid format: `xml`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_next_context() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "next".to_string(),
+                "--context".to_string()
+            ])),
+            Err(Error::Usage(
+                "option `--context` requires an argument".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_action_ls_context() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "--context".to_string(),
+                "@computer".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::List {
+                status: Status::Open,
+                context: Some("computer".into()),
+                options: ListOptions::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_set_context() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "context".to_string(),
+                "1".to_string(),
+                "@home".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::SetContext {
+                description: "1".into(),
+                context: "home".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_action_set_context() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "context".to_string(),
+                "1".to_string()
+            ])),
+            Err(Error::Usage("missing context".into()))
+        );
+    }
+
+    #[test]
+    fn parses_action_unset_context() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "unset".to_string(),
+                "context".to_string(),
+                "Call".to_string(),
+                "Mom".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::UnsetContext {
+                description: "Call Mom".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_ls_dates() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "--overdue".to_string(),
+                "--due-before".to_string(),
+                "2021-06-01".to_string(),
+                "--sort".to_string(),
+                "due".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::List {
+                status: Status::Open,
+                context: None,
+                options: ListOptions {
+                    selection: Selection {
+                        overdue: true,
+                        due_before: Some(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap()),
+                        sort: Sort::Due,
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_ls_template() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "--template".to_string(),
+                "{id:>4} {description}".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::List {
+                status: Status::Open,
+                context: None,
+                options: ListOptions {
+                    template: Some(
+                        Template::parse("{id:>4} {description}", TEMPLATE_FIELDS).unwrap()
+                    ),
+                    ..ListOptions::default()
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_template_with_format() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--template".to_string(),
+                "{id}".to_string(),
+                "--format".to_string(),
+                "json".to_string()
+            ])),
+            Err(Error::Usage(
+                "options `--format` and `--template` cannot be combined".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_sort() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "--sort".to_string(),
+                "priority".to_string()
+            ])),
+            Err(Error::Usage("invalid sort: `priority`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_due_before_date() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--due-before".to_string()
+            ])),
+            Err(Error::Usage(
+                "option `--due-before` requires an argument".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_action_set_due() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "due".to_string(),
+                "1".to_string(),
+                "2021-06-01".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::SetDate {
+                description: "1".into(),
+                field: DateField::Due,
+                date: NaiveDate::from_ymd_opt(2021, 6, 1).unwrap()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_date() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "due".to_string(),
+                "1".to_string(),
+                "2021-02-30".to_string()
+            ])),
+            Err(Error::Usage("invalid date: `2021-02-30`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_date() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "scheduled".to_string(),
+                "1".to_string()
+            ])),
+            Err(Error::Usage("missing date".into()))
+        );
+    }
+
+    // A Friday.
+    fn now() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2021, 5, 14).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> Result<NaiveDate, String> {
+        Ok(NaiveDate::from_ymd_opt(year, month, day).unwrap())
+    }
+
+    #[test]
+    fn parses_absolute_date() {
+        assert_eq!(parse_date("2026-11-01", now()), date(2026, 11, 1));
+    }
+
+    #[test]
+    fn parses_relative_day() {
+        assert_eq!(parse_date("today", now()), date(2021, 5, 14));
+        assert_eq!(parse_date("tomorrow", now()), date(2021, 5, 15));
+        assert_eq!(parse_date("Yesterday", now()), date(2021, 5, 13));
+    }
+
+    #[test]
+    fn parses_weekday() {
+        assert_eq!(parse_date("sat", now()), date(2021, 5, 15));
+        assert_eq!(parse_date("mon", now()), date(2021, 5, 17));
+        assert_eq!(parse_date("thursday", now()), date(2021, 5, 20));
+        assert_eq!(parse_date("fri", now()), date(2021, 5, 21));
+        assert_eq!(parse_date("next monday", now()), date(2021, 5, 17));
+    }
+
+    #[test]
+    fn parses_offset() {
+        assert_eq!(parse_date("+3d", now()), date(2021, 5, 17));
+        assert_eq!(parse_date("-1w", now()), date(2021, 5, 7));
+        assert_eq!(parse_date("+1m", now()), date(2021, 6, 14));
+        assert_eq!(parse_date("+1y", now()), date(2022, 5, 14));
+    }
+
+    #[test]
+    fn clamps_month_offset_to_end_of_month() {
+        let january_31 = NaiveDate::from_ymd_opt(2021, 1, 31).unwrap();
+        assert_eq!(parse_date("+1m", january_31), date(2021, 2, 28));
+        assert_eq!(parse_date("-2m", january_31), date(2020, 11, 30));
+        let leap_january_31 = NaiveDate::from_ymd_opt(2020, 1, 31).unwrap();
+        assert_eq!(parse_date("+1m", leap_january_31), date(2020, 2, 29));
+    }
+
+    #[test]
+    fn parses_end_of_period() {
+        assert_eq!(parse_date("eow", now()), date(2021, 5, 16));
+        assert_eq!(parse_date("eom", now()), date(2021, 5, 31));
+        assert_eq!(parse_date("eoy", now()), date(2021, 12, 31));
+    }
+
+    #[test]
+    fn reports_invalid_date_expression() {
+        for arg in ["someday", "+d", "+3x", "2021-13-01", "next", ""] {
+            assert_eq!(
+                parse_date(arg, now()),
+                Err(format!("invalid date: `{}`", arg))
+            );
+        }
+    }
+
+    #[test]
+    fn parses_action_set_due_expression() {
+        assert!(matches!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "due".to_string(),
+                "1".to_string(),
+                "next".to_string(),
+                "monday".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::SetDate {
+                field: DateField::Due,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn parses_goal_unset_scheduled() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "unset".to_string(),
+                "scheduled".to_string(),
+                "Ship".to_string(),
+                "2.0".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::UnsetDate {
+                description: "Ship 2.0".into(),
+                field: DateField::Scheduled
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_set_recurrence() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "recurrence".to_string(),
+                "1".to_string(),
+                "weekly:mon,thu".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::SetRecurrence {
+                description: "1".into(),
+                recurrence: Recurrence::Weekly {
+                    interval: 1,
+                    days: vec![Weekday::Mon, Weekday::Thu]
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_action_recurrence() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "recurrence".to_string(),
+                "1".to_string(),
+                "fortnightly".to_string()
+            ])),
+            Err(Error::Usage("invalid recurrence: `fortnightly`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_action_unset_recurrence() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "unset".to_string(),
+                "recurrence".to_string(),
+                "Pay".to_string(),
+                "rent".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::UnsetRecurrence {
+                description: "Pay rent".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_set_priority() {
+        for (arg, priority) in [("H", 1), ("m", 3), ("L", 5), ("2", 2)] {
+            assert_eq!(
+                Command::from_args(IntoIterator::into_iter([
+                    "action".to_string(),
+                    "set".to_string(),
+                    "priority".to_string(),
+                    "1".to_string(),
+                    arg.to_string()
+                ])),
+                Ok(Command::Action(ActionSubcommand::SetPriority {
+                    description: "1".into(),
+                    priority
+                }))
+            );
+        }
+    }
+
+    #[test]
+    fn reports_invalid_priority() {
+        for arg in ["0", "6", "urgent"] {
+            assert_eq!(
+                Command::from_args(IntoIterator::into_iter([
+                    "goal".to_string(),
+                    "set".to_string(),
+                    "priority".to_string(),
+                    "1".to_string(),
+                    arg.to_string()
+                ])),
+                Err(Error::Usage(format!("invalid priority: `{}`", arg)))
+            );
+        }
+    }
+
+    #[test]
+    fn parses_goal_unset_priority() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "unset".to_string(),
+                "priority".to_string(),
+                "Ship".to_string(),
+                "2.0".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::UnsetPriority {
+                description: "Ship 2.0".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_config_set() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "config".to_string(),
+                "set".to_string(),
+                "urgency.due".to_string(),
+                "8.5".to_string()
+            ])),
+            Ok(Command::Config(ConfigSubcommand::Set {
+                name: "urgency.due".into(),
+                value: "8.5".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_template_setting() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "config".to_string(),
+                "set".to_string(),
+                "template.goal".to_string(),
+                "{id} {name}".to_string()
+            ])),
+            Err(Error::Usage(
+                "invalid template at column 7: no such field: `name`".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn reports_no_such_setting() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "config".to_string(),
+                "unset".to_string(),
+                "urgency.tags".to_string()
+            ])),
+            Err(Error::Usage("no such setting: `urgency.tags`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_backup() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["backup".to_string()])),
+            Ok(Command::Backup { path: None })
+        );
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "restore".to_string(),
+                "odo.sqlite3".to_string()
+            ])),
+            Ok(Command::Restore {
+                path: "odo.sqlite3".into()
+            })
+        );
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["restore".to_string()])),
+            Err(Error::Usage("missing path".into()))
+        );
+    }
+
+    #[test]
+    fn parses_doctor() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["doctor".to_string()])),
+            Ok(Command::Doctor { fix: false })
+        );
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "doctor".to_string(),
+                "--fix".to_string()
+            ])),
+            Ok(Command::Doctor { fix: true })
+        );
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "doctor".to_string(),
+                "--all".to_string()
+            ])),
+            Err(Error::Usage("extra argument: `--all`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_backup_count() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "config".to_string(),
+                "set".to_string(),
+                "backup.count".to_string(),
+                "-1".to_string()
+            ])),
+            Err(Error::Usage("invalid value: `-1`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_config_value() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "config".to_string(),
+                "set".to_string(),
+                "urgency.due".to_string(),
+                "high".to_string()
+            ])),
+            Err(Error::Usage("invalid value: `high`".into()))
+        );
+        for value in ["NaN", "inf", "1e400"] {
+            assert_eq!(
+                Command::from_args(IntoIterator::into_iter([
+                    "config".to_string(),
+                    "set".to_string(),
+                    "urgency.age".to_string(),
+                    value.to_string()
+                ])),
+                Err(Error::Usage(format!("invalid value: `{}`", value)))
+            );
+        }
+    }
+
+    #[test]
+    fn parses_action_tag() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "tag".to_string(),
+                "1".to_string(),
+                "+work".to_string(),
+                "reading".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::Tag {
+                description: "1".into(),
+                tags: vec!["work".into(), "reading".into()]
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_untag_tag() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "untag".to_string(),
+                "1".to_string()
+            ])),
+            Err(Error::Usage("missing tag".into()))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_tag() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "tag".to_string(),
+                "1".to_string(),
+                "+a,b".to_string()
+            ])),
+            Err(Error::Usage("invalid tag: `+a,b`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_action_ls_tags() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "+work".to_string(),
+                "-reading".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::List {
+                status: Status::Open,
+                context: None,
+                options: ListOptions {
+                    selection: Selection {
+                        include: vec!["work".into()],
+                        exclude: vec!["reading".into()],
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_ls_filter() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "--all".to_string(),
+                "tag:work".to_string(),
+                "or".to_string(),
+                "priority=H".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::List {
+                status: Status::All,
+                context: None,
+                options: ListOptions {
+                    selection: Selection {
+                        later: true,
+                        filter: Some(
+                            Filter::parse("tag:work or priority=H", Item::Action).unwrap()
+                        ),
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_goal_ls_filter() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "tag:work and (due<fri".to_string()
+            ])),
+            Err(Error::Usage(
+                "invalid filter at column 22: expected `)`".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_tag_rename() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "tag".to_string(),
+                "rename".to_string(),
+                "+job".to_string(),
+                "+work".to_string()
+            ])),
+            Ok(Command::Tag(TagSubcommand::Rename {
+                old_name: "job".into(),
+                new_name: "work".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_note() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "note".to_string(),
+                "1".to_string(),
+                "Ask".to_string(),
+                "for".to_string(),
+                "the".to_string(),
+                "large-print".to_string(),
+                "edition.".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::SetNote {
+                description: "1".into(),
+                note: "Ask for the large-print edition.".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_note_edit() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "note".to_string(),
+                "--edit".to_string(),
+                "Read *Network Effect*.".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::EditNote {
+                description: "Read *Network Effect*.".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_note() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "note".to_string(),
+                "1".to_string()
+            ])),
+            Err(Error::Usage("missing note".into()))
+        );
+    }
+
+    #[test]
+    fn parses_action_annotate() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "annotate".to_string(),
+                "1".to_string(),
+                "Called the library; the book arrives Tuesday.".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::Annotate {
+                description: "1".into(),
+                text: "Called the library; the book arrives Tuesday.".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_search() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "search".to_string(),
+                "--all".to_string(),
+                "network effect".to_string(),
+                "OR".to_string(),
+                "libr*".to_string()
+            ])),
+            Ok(Command::Search {
+                query: "\"network effect\" OR libr*".into(),
+                all: true,
+                format: Format::Text
+            })
+        );
+    }
+
+    #[test]
+    fn reports_missing_query() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["search".to_string()])),
+            Err(Error::Usage("missing query".into()))
+        );
+    }
+
+    #[test]
+    fn parses_show() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "show".to_string(),
+                "goal".to_string(),
+                "Read".to_string(),
+                "more".to_string()
+            ])),
+            Ok(Command::Show {
+                item: Item::Goal,
+                description: "Read more".into()
+            })
+        );
+    }
+
+    #[test]
+    fn reports_invalid_show_item() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "show".to_string(),
+                "context".to_string(),
+                "@errands".to_string()
+            ])),
+            Err(Error::Usage("invalid item: `context`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_view_add() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "view".to_string(),
+                "add".to_string(),
+                "today".to_string(),
+                "due<=today or tag:focus".to_string(),
+                "--sort".to_string(),
+                "urgency".to_string()
+            ])),
+            Ok(Command::View(ViewSubcommand::Add {
+                name: "today".into(),
+                item: Item::Action,
+                arguments: vec![
+                    "due<=today or tag:focus".into(),
+                    "--sort".into(),
+                    "urgency".into()
+                ]
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_view_arguments() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "view".to_string(),
+                "add".to_string(),
+                "--goal".to_string(),
+                "stuck".to_string(),
+                "--context".to_string(),
+                "@phone".to_string()
+            ])),
+            Err(Error::Usage("extra argument: `--context`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_view_name() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "view".to_string(),
+                "add".to_string(),
+                "next".to_string()
+            ])),
+            Err(Error::Usage("invalid view name: `next`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_context_add() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "context".to_string(),
+                "add".to_string(),
+                "--description".to_string(),
+                "At my desk".to_string(),
+                "--order".to_string(),
+                "2".to_string(),
+                "@computer".to_string()
+            ])),
+            Ok(Command::Context(ContextSubcommand::Add {
+                name: "computer".into(),
+                description: Some("At my desk".into()),
+                order: Some(2)
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_context() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "context".to_string(),
+                "add".to_string(),
+                "@".to_string()
+            ])),
+            Err(Error::Usage("invalid context: `@`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_context_order() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "context".to_string(),
+                "add".to_string(),
+                "--order".to_string(),
+                "first".to_string(),
+                "@home".to_string()
+            ])),
+            Err(Error::Usage("invalid order: `first`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_context_ls() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "context".to_string(),
+                "ls".to_string()
+            ])),
+            Ok(Command::Context(ContextSubcommand::List {
+                format: Format::Text
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_context_rm() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "context".to_string(),
+                "rm".to_string(),
+                "home".to_string()
+            ])),
+            Ok(Command::Context(ContextSubcommand::Remove {
+                name: "home".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_action_subcommand() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["action".to_string()])),
+            Err(Error::Usage("missing subcommand".into()))
+        );
+    }
+
+    #[test]
+    fn reports_no_such_action_subcommand() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "foo".to_string()
+            ])),
+            Err(Error::Usage("no such subcommand: `foo`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_action_add_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "add".to_string()
+            ])),
+            Err(Error::Usage("missing description".into()))
+        );
+    }
+
+    #[test]
+    fn reports_extra_action_ls_argument() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "--foo".to_string()
+            ])),
+            Err(Error::Usage("extra argument: `--foo`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_action_done_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "done".to_string()
+            ])),
+            Err(Error::Usage("missing description".into()))
+        );
+    }
+
+    #[test]
+    fn parses_action_done() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "done".to_string(),
+                "#1".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::Done {
+                description: "#1".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_action_ls_done() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "ls".to_string(),
+                "--done".to_string()
+            ])),
+            Ok(Command::Action(ActionSubcommand::List {
+                status: Status::Done,
+                context: None,
+                options: ListOptions::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_action_rm_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "rm".to_string()
+            ])),
+            Err(Error::Usage("missing description".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_action_set_field() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string()
+            ])),
+            Err(Error::Usage("missing field".into()))
+        );
+    }
+
+    #[test]
+    fn reports_no_such_action_field() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "foo".to_string(),
+            ])),
+            Err(Error::Usage("no such field: `foo`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_old_action_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "description".to_string(),
+            ])),
+            Err(Error::Usage("missing old description".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_new_action_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "description".to_string(),
+                "Read *Network Efect*.".to_string(),
+            ])),
+            Err(Error::Usage("missing new description".into()))
+        );
+    }
+
+    #[test]
+    fn parses_action_set_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "action".to_string(),
+                "set".to_string(),
+                "description".to_string(),
+                "Read *Network Efect*.".to_string(),
+                "Read".to_string(),
+                "*Network".to_string(),
+                "Effect*.".to_string(),
+            ])),
+            Ok(Command::Action(ActionSubcommand::SetDescription {
+                old_description: "Read *Network Efect*.".into(),
+                new_description: "Read *Network Effect*.".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_subcommand() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter(["goal".to_string()])),
+            Err(Error::Usage("missing subcommand".into()))
+        );
+    }
+
+    #[test]
+    fn reports_no_such_goal_subcommand() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "foo".to_string()
+            ])),
+            Err(Error::Usage("no such subcommand: `foo`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_actions() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "actions".to_string(),
+                "Ship".to_string(),
+                "2.0".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::Actions {
+                description: "Ship 2.0".into(),
+                format: Format::Text
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_add_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add-action".to_string(),
+                "1".to_string(),
+                "Write".to_string(),
+                "release".to_string(),
+                "notes".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::AddAction {
+                description: "1".into(),
+                action: "Write release notes".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_add_action_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add-action".to_string(),
+                "1".to_string(),
+            ])),
+            Err(Error::Usage("missing action".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_move_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "move-action".to_string(),
+                "1".to_string(),
+                "2".to_string(),
+                "#3".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::MoveAction {
+                description: "1".into(),
+                position: 2,
+                action: "#3".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_goal_move_action_position() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "move-action".to_string(),
+                "1".to_string(),
+                "0".to_string(),
+                "#3".to_string(),
+            ])),
+            Err(Error::Usage("invalid position: `0`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_rm_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "rm-action".to_string(),
+                "1".to_string(),
+                "2".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::RemoveAction {
+                description: "1".into(),
+                action: "2".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_add_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add".to_string()
+            ])),
+            Err(Error::Usage("missing description".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add".to_string(),
+                "--action".to_string()
+            ])),
+            Err(Error::Usage(
+                "option `--action` requires an argument".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_parent() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add".to_string(),
+                "--parent".to_string()
+            ])),
+            Err(Error::Usage(
+                "option `--parent` requires an argument".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_goal_add_with_parent_and_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add".to_string(),
+                "--parent".to_string(),
+                "1".to_string(),
+                "--action".to_string(),
+                "2".to_string(),
+                "Read".to_string(),
+                "*Network".to_string(),
+                "Effect*.".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::Add {
+                description: "Read *Network Effect*.".into(),
+                action: Some("2".into()),
+                parent: Some("1".into()),
+                goal_type: GoalType::Parallel,
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_add_with_type() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add".to_string(),
+                "--type".to_string(),
+                "sequential".to_string(),
+                "Ship".to_string(),
+                "2.0".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::Add {
+                description: "Ship 2.0".into(),
+                action: None,
+                parent: None,
+                goal_type: GoalType::Sequential,
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_goal_type() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "add".to_string(),
+                "--type".to_string(),
+                "serial".to_string(),
+                "Ship".to_string(),
+                "2.0".to_string(),
+            ])),
+            Err(Error::Usage("invalid type: `serial`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_extra_goal_ls_argument() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--foo".to_string()
+            ])),
+            Err(Error::Usage("extra argument: `--foo`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_extra_goal_ls_argument_after_all() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--all".to_string(),
+                "--foo".to_string()
+            ])),
+            Err(Error::Usage("extra argument: `--foo`".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_ls() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::List {
+                status: Status::Open,
+                stuck: true,
+                tree: false,
+                options: ListOptions::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_ls_all() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--all".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::List {
+                status: Status::Open,
+                stuck: false,
+                tree: false,
+                options: ListOptions {
+                    selection: Selection {
+                        later: true,
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_ls_everything() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--everything".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::List {
+                status: Status::All,
+                stuck: false,
+                tree: false,
+                options: ListOptions {
+                    selection: Selection {
+                        later: true,
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_ls_done() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--done".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::List {
+                status: Status::Done,
+                stuck: false,
+                tree: false,
+                options: ListOptions::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_ls_tree() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "ls".to_string(),
+                "--tree".to_string(),
+                "--all".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::List {
+                status: Status::Open,
+                stuck: false,
+                tree: true,
+                options: ListOptions {
+                    selection: Selection {
+                        later: true,
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_rm_recursive() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "rm".to_string(),
+                "--recursive".to_string(),
+                "1".to_string()
+            ])),
+            Ok(Command::Goal(GoalSubcommand::Remove {
+                description: "1".into(),
+                recursive: true
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_rm_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "rm".to_string()
+            ])),
+            Err(Error::Usage("missing description".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_set_field() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string()
+            ])),
+            Err(Error::Usage("missing field".into()))
+        );
+    }
+
+    #[test]
+    fn reports_no_such_goal_set_field() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "foo".to_string(),
+            ])),
+            Err(Error::Usage("no such field: `foo`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_set_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "action".to_string(),
+            ])),
+            Err(Error::Usage("missing description".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "action".to_string(),
+                "Read *Network Effect*.".to_string(),
+            ])),
+            Err(Error::Usage("missing action".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_set_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "action".to_string(),
+                "Read *Network Effect*.".to_string(),
+                "Borrow".to_string(),
+                "*Network".to_string(),
+                "Effect*.".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::SetAction {
+                description: "Read *Network Effect*.".into(),
+                action: "Borrow *Network Effect*.".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_old_goal_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "description".to_string(),
+            ])),
+            Err(Error::Usage("missing old description".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_new_goal_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "description".to_string(),
+                "Read *Network Efect*.".to_string(),
+            ])),
+            Err(Error::Usage("missing new description".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_set_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "description".to_string(),
+                "Read *Network Efect*.".to_string(),
+                "Read".to_string(),
+                "*Network".to_string(),
+                "Effect*.".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::SetDescription {
+                old_description: "Read *Network Efect*.".into(),
+                new_description: "Read *Network Effect*.".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_parent() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "parent".to_string(),
+                "Read *Network Effect*.".to_string(),
+            ])),
+            Err(Error::Usage("missing parent".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_set_parent() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "parent".to_string(),
+                "2".to_string(),
+                "Ship".to_string(),
+                "2.0".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::SetParent {
+                description: "2".into(),
+                parent: "Ship 2.0".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_unset_parent() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "unset".to_string(),
+                "parent".to_string(),
+                "2".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::UnsetParent {
+                description: "2".into(),
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_goal_set_type() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "type".to_string(),
+                "Ship 2.0".to_string(),
+                "parallel".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::SetType {
+                description: "Ship 2.0".into(),
+                goal_type: GoalType::Parallel
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_type() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "set".to_string(),
+                "type".to_string(),
+                "Ship 2.0".to_string(),
+            ])),
+            Err(Error::Usage("missing type".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_unset_field() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "unset".to_string()
+            ])),
+            Err(Error::Usage("missing field".into()))
+        );
+    }
+
+    #[test]
+    fn reports_no_such_goal_unset_field() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "unset".to_string(),
+                "foo".to_string(),
+            ])),
+            Err(Error::Usage("no such field: `foo`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_missing_goal_unset_description() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "unset".to_string(),
+                "action".to_string(),
+            ])),
+            Err(Error::Usage("missing description".into()))
+        );
+    }
+
+    #[test]
+    fn parses_goal_unset_action() {
+        assert_eq!(
+            Command::from_args(IntoIterator::into_iter([
+                "goal".to_string(),
+                "unset".to_string(),
+                "action".to_string(),
+                "Read".to_string(),
+                "*Network".to_string(),
+                "Effect*.".to_string(),
+            ])),
+            Ok(Command::Goal(GoalSubcommand::UnsetAction {
+                description: "Read *Network Effect*.".into(),
+            }))
+        );
+    }
+
+    #[test]
+    fn lists_all_actions_of_parallel_goal_as_next() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("2"), None, GoalType::Parallel)
+            .unwrap();
+        store.add_goal_action("1", "1").unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n  2 Tag release\n  1 Write release notes\n"
+        );
+    }
+
+    #[test]
+    fn lists_first_open_action_of_sequential_goal_as_next() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store.add_action("Announce release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Sequential)
+            .unwrap();
+        store.add_goal_action("1", "2").unwrap();
+        store.add_goal_action("1", "3").unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n  1 Write release notes\n"
+        );
+        store.complete_action("1").unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n  2 Tag release\n"
+        );
+    }
+
+    #[test]
+    fn does_not_list_actions_of_done_goal_as_next() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        store.complete_goal("1").unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "no goal\n  1 Write release notes\n"
+        );
+    }
+
+    #[test]
+    fn groups_next_actions_by_goal() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store.add_action("Call the library").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("2"),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
+        store
+            .add_goal_action("Ship 2.0", "Borrow *Network Effect*.")
+            .unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n  1 Write release notes\n  2 Borrow *Network Effect*.\n2 Read *Network Effect*.\n  2 Borrow *Network Effect*.\nno goal\n  3 Call the library\n"
+        );
+    }
+
+    #[test]
+    fn lists_next_actions_in_context() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_context("home", None, None).unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.add_action("Buy stamps").unwrap();
+        store
+            .add_goal("Tidy up", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        store.set_action_context("1", Some("home")).unwrap();
+        store.set_action_context("2", Some("home")).unwrap();
+        store.add_action("Call the bank").unwrap();
+        let mut output = Vec::new();
+        list_next_actions(&store, Some("home"), Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Tidy up\n  1 Water the plants\nno goal\n  2 Buy stamps\n"
+        );
+    }
+
+    #[test]
+    fn adds_context() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store
+            .add_context("computer", Some("At my desk"), Some(2))
+            .unwrap();
+        assert_eq!(
+            connection.query_row(
+                "SELECT name, description, position FROM contexts",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                    ))
+                }
+            ),
+            Ok(("computer".into(), "At my desk".into(), 2))
+        );
+    }
+
+    #[test]
+    fn fails_to_add_duplicate_context() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_context("home", None, None).unwrap();
+        assert_eq!(
+            store.add_context("home", None, None),
+            Err(Error::AlreadyExists("context already exists".into()))
+        );
+    }
+
+    #[test]
+    fn lists_contexts_in_order() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_context("errands", None, None).unwrap();
+        store
+            .add_context("computer", Some("At my desk"), Some(2))
+            .unwrap();
+        store.add_context("home", None, Some(1)).unwrap();
+        let mut output = Vec::new();
+        list_contexts(&store, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "@home\n@computer At my desk\n@errands\n"
+        );
+    }
+
+    #[test]
+    fn removing_context_clears_action_context() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_context("home", None, None).unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.set_action_context("1", Some("home")).unwrap();
+        store.remove_context("home").unwrap();
+        assert_eq!(
+            connection.query_row("SELECT context FROM actions", [], |row| row
+                .get::<_, Option<i64>>(0)),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn fails_to_remove_nonexistent_context() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(
+            store.remove_context("home"),
+            Err(Error::NotFound("context does not exist".into()))
+        );
+    }
+
+    #[test]
+    fn lists_actions_in_context() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_context("home", None, None).unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.add_action("Buy stamps").unwrap();
+        store.set_action_context("2", Some("home")).unwrap();
+        let mut output = Vec::new();
+        list_actions(
+            &store,
+            Status::Open,
+            Some("home"),
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "2 Buy stamps\n");
+        store.set_action_context("2", None).unwrap();
+        let mut output = Vec::new();
+        list_actions(
+            &store,
+            Status::Open,
+            Some("home"),
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+    }
+
+    #[test]
+    fn fails_to_set_nonexistent_context() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Water the plants").unwrap();
+        assert_eq!(
+            store.set_action_context("1", Some("home")),
+            Err(Error::NotFound("context does not exist".into()))
+        );
+    }
+
+    #[test]
+    fn does_not_list_scheduled_actions_as_next() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Renew passport").unwrap();
+        store.add_action("Water the plants").unwrap();
+        store
+            .set_action_date(
+                "1",
+                DateField::Scheduled,
+                NaiveDate::from_ymd_opt(2999, 1, 1),
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "no goal\n  2 Water the plants\n"
+        );
+    }
+
+    #[test]
+    fn lists_most_urgent_next_actions_first() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store.add_action("Pay rent").unwrap();
+        store
+            .add_goal("Tidy up", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Ship 2.0", Some("2"), None, GoalType::Parallel)
+            .unwrap();
+        store.add_goal_action("2", "3").unwrap();
+        store.set_action_priority("3", Some(1)).unwrap();
+        store.set_action_priority("4", Some(3)).unwrap();
+        store.add_action("Buy stamps").unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "2 Ship 2.0\n  3 Tag release\n  2 Write release notes\n1 Tidy up\n  1 Water the plants\nno goal\n  4 Pay rent\n  5 Buy stamps\n"
+        );
+    }
+
+    #[test]
+    fn sets_goal_type() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        store.add_goal_action("1", "2").unwrap();
+        store
+            .set_goal_type("Ship 2.0", GoalType::Sequential)
+            .unwrap();
+        let mut output = Vec::new();
+        list_next_actions::<&str, _>(&store, None, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n  1 Write release notes\n"
+        );
+    }
+
+    #[test]
+    fn adds_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Read *Network Effect*.").unwrap();
+        assert_eq!(
+            connection
+                .query_row("SELECT description FROM actions", [], |row| row
+                    .get::<usize, String>(0))
+                .unwrap(),
+            "Read *Network Effect*."
+        );
+    }
+
+    #[test]
+    fn fails_to_add_duplicate_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Read *Network Effect*.").unwrap();
+        assert_eq!(
+            store.add_action("Read *Network Effect*."),
+            Err(Error::AlreadyExists("action already exists".into()))
+        );
+    }
+
+    #[test]
+    fn lists_no_actions() {
+        let store = Store::open_in_memory().unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+    }
+
+    #[test]
+    fn lists_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Read *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n"
+        );
+    }
+
+    #[test]
+    fn lists_actions() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Read *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Read *What Were We Thinking*.')",
+                [],
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n2 Read *What Were We Thinking*.\n"
+        );
+    }
+
+    #[test]
+    fn does_not_list_done_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description, done) VALUES('Read *Network Effect*.', '2021-05-13 00:00:00')",
+                [],
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+    }
+
+    #[test]
+    fn lists_done_actions() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description, done) VALUES('Read *Network Effect*.', '2021-05-13 00:00:00')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Read *What Were We Thinking*.')",
+                [],
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Done,
+            None,
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n"
+        );
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::All,
+            None,
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n2 Read *What Were We Thinking*.\n"
+        );
+    }
+
+    #[test]
+    fn lists_actions_by_urgency() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.add_action("Renew passport").unwrap();
+        store.add_action("File taxes").unwrap();
+        store.set_action_priority("2", Some(5)).unwrap();
+        store
+            .set_action_date("3", DateField::Due, NaiveDate::from_ymd_opt(2000, 4, 15))
+            .unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions {
+                selection: Selection {
+                    sort: Sort::Urgency,
+                    ..Selection::default()
+                },
+                ..ListOptions::default()
+            },
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "3 File taxes\n2 Renew passport\n1 Water the plants\n"
+        );
+    }
+
+    #[test]
+    fn lists_configuration() {
+        let store = Store::open_in_memory().unwrap();
+        store.set_config("urgency.goal", Some("0.5")).unwrap();
+        store.set_config("urgency.due", Some("20")).unwrap();
+        store.set_config("urgency.due", None).unwrap();
+        let mut output = Vec::new();
+        list_config(&store, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "backup.count 10\nbackup.days 30\nurgency.age 2\nurgency.due 12\nurgency.goal 0.5\nurgency.priority 6\n"
+        );
+    }
+
+    #[test]
+    fn lists_actions_by_tag() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        store.add_action("Read *Network Effect*.").unwrap();
+        store.add_action("Read the design doc").unwrap();
+        store.tag_action("1", &["work".into()]).unwrap();
+        store.tag_action("2", &["reading".into()]).unwrap();
+        store
+            .tag_action("3", &["work".into(), "reading".into()])
+            .unwrap();
+        let list = |include: &[&str], exclude: &[&str]| {
+            let mut output = Vec::new();
+            list_actions::<&str, _>(
+                &store,
+                Status::Open,
+                None,
+                &ListOptions {
+                    selection: Selection {
+                        include: include.iter().map(|tag| tag.to_string()).collect(),
+                        exclude: exclude.iter().map(|tag| tag.to_string()).collect(),
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                },
+                &mut output,
+            )
+            .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        assert_eq!(
+            list(&["work"], &[]),
+            "1 Email Alice\n3 Read the design doc\n"
+        );
+        assert_eq!(list(&["work", "reading"], &[]), "3 Read the design doc\n");
+        assert_eq!(list(&[], &["work"]), "2 Read *Network Effect*.\n");
+    }
+
+    #[test]
+    fn lists_actions_matching_filter() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_context("phone", None, None).unwrap();
+        store.add_action("Email Alice").unwrap();
+        store.add_action("Call Bob").unwrap();
+        store.add_action("Read the design doc").unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.tag_action("1", &["work".into()]).unwrap();
+        store.tag_action("2", &["work".into()]).unwrap();
+        store.tag_action("3", &["work".into()]).unwrap();
+        store.set_action_context("2", Some("phone")).unwrap();
+        store
+            .set_action_date("1", DateField::Due, Some(today()))
+            .unwrap();
+        store
+            .set_action_date("2", DateField::Due, Some(today()))
+            .unwrap();
+        store
+            .set_action_date("3", DateField::Due, add_days(today(), 30))
+            .unwrap();
+        store.set_action_priority("4", Some(1)).unwrap();
+        let list = |filter: &str| {
+            let mut output = Vec::new();
+            list_actions::<&str, _>(
+                &store,
+                Status::Open,
+                None,
+                &ListOptions {
+                    selection: Selection {
+                        filter: Some(Filter::parse(filter, Item::Action).unwrap()),
+                        ..Selection::default()
+                    },
+                    ..ListOptions::default()
+                },
+                &mut output,
+            )
+            .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        assert_eq!(
+            list("tag:work and due<+1w and not context:@phone"),
+            "1 Email Alice\n"
+        );
+        assert_eq!(
+            list("not due<+1w"),
+            "3 Read the design doc\n4 Water the plants\n"
+        );
+        assert_eq!(
+            list("description~READ or priority:H"),
+            "3 Read the design doc\n4 Water the plants\n"
+        );
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_location() {
+        assert_eq!(
+            parse_location(args(&["goal", "ls"]), None),
+            Ok((Location::Default, args(&["goal", "ls"])))
+        );
+        assert_eq!(
+            parse_location(args(&["goal", "ls"]), Some("/tmp/odo.sqlite3".into())),
+            Ok((
+                Location::Path("/tmp/odo.sqlite3".into()),
+                args(&["goal", "ls"])
+            ))
+        );
+        assert_eq!(
+            parse_location(
+                args(&["--profile", "work", "goal", "ls", "--profile"]),
+                Some("/tmp/odo.sqlite3".into())
+            ),
+            Ok((
+                Location::Profile("work".into()),
+                args(&["goal", "ls", "--profile"])
+            ))
+        );
+        assert_eq!(
+            parse_location(args(&["--database", "todo.db", "next"]), None),
+            Ok((Location::Path("todo.db".into()), args(&["next"])))
+        );
+        assert_eq!(
+            parse_location(args(&["--profile", "default", "next"]), None),
+            Ok((Location::Default, args(&["next"])))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_location() {
+        assert_eq!(
+            parse_location(args(&["--profile", "../work", "next"]), None),
+            Err(Error::Usage("invalid profile name: `../work`".into()))
+        );
+        assert_eq!(
+            parse_location(args(&["--database"]), None),
+            Err(Error::Usage(
+                "option `--database` requires an argument".into()
+            ))
+        );
+        assert_eq!(
+            parse_location(
+                args(&["--database", "todo.db", "--profile", "work", "next"]),
+                None
+            ),
+            Err(Error::Usage(
+                "options `--database` and `--profile` cannot be combined".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn recognizes_arguments_that_may_name_view() {
+        for (arguments, expected) in [
+            (&["action", "ls"][..], true),
+            (&["goal", "ls"], true),
+            (&["view", "work"], true),
+            (&["work", "--all"], true),
+            (&["action", "ls", "--all"], false),
+            (&["view", "ls"], false),
+            (&["action"], false),
+            (&["--format", "json"], false),
+            (&[], false),
+        ] {
+            assert_eq!(may_name_view(&args(arguments)), expected, "{:?}", arguments);
+        }
+    }
+
+    #[test]
+    fn expands_view() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_view(
+                "today",
+                Item::Action,
+                &args(&["due<=today", "--sort", "urgency"]),
+            )
+            .unwrap();
+        assert_eq!(
+            expand_view(&store, args(&["today", "+work"])),
+            Ok(args(&[
+                "action",
+                "ls",
+                "due<=today",
+                "--sort",
+                "urgency",
+                "+work"
+            ]))
+        );
+        assert_eq!(
+            expand_view(&store, args(&["view", "today"])),
+            Ok(args(&["action", "ls", "due<=today", "--sort", "urgency"]))
+        );
+        assert_eq!(
+            expand_view(&store, args(&["tomorrow"])),
+            Ok(args(&["tomorrow"]))
+        );
+        assert_eq!(
+            expand_view(&store, args(&["view", "tomorrow"])),
+            Err(Error::NotFound("view does not exist".into()))
+        );
+    }
+
+    #[test]
+    fn expands_default_views() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_view("default", Item::Goal, &args(&["--tree"]))
+            .unwrap();
+        assert_eq!(
+            expand_view(&store, args(&["goal", "ls"])),
+            Ok(args(&["goal", "ls", "--tree"]))
+        );
+        assert_eq!(
+            expand_view(&store, args(&["goal", "ls", "--all"])),
+            Ok(args(&["goal", "ls", "--all"]))
+        );
+        assert_eq!(
+            expand_view(&store, args(&["action", "ls"])),
+            Ok(args(&["action", "ls"]))
+        );
+        store
+            .add_view("default", Item::Action, &args(&["--sort", "due"]))
+            .unwrap();
+        store.remove_view("default", Some(Item::Goal)).unwrap();
+        assert_eq!(
+            expand_view(&store, args(&["goal", "ls"])),
+            Ok(args(&["goal", "ls"]))
+        );
+        assert_eq!(
+            expand_view(&store, args(&["action", "ls"])),
+            Ok(args(&["action", "ls", "--sort", "due"]))
+        );
+    }
+
+    #[test]
+    fn lists_views() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_view(
+                "today",
+                Item::Action,
+                &args(&["due<=today or tag:focus", "--sort", "urgency"]),
+            )
+            .unwrap();
+        store
+            .add_view("stuck", Item::Goal, &args(&["note~it's"]))
+            .unwrap();
+        let mut output = Vec::new();
+        list_views(&store, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "stuck goal 'note~it'\\''s'\ntoday action 'due<=today or tag:focus' --sort urgency\n"
+        );
+    }
+
+    #[test]
+    fn fails_to_add_duplicate_view() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_view("today", Item::Action, &Vec::new()).unwrap();
+        assert_eq!(
+            store.add_view("today", Item::Goal, &Vec::new()),
+            Err(Error::AlreadyExists("view already exists".into()))
+        );
+    }
+
+    #[test]
+    fn lists_profiles() {
+        let profiles = [
+            ("default".to_string(), PathBuf::from("odo.sqlite3")),
+            ("work".to_string(), PathBuf::from("profiles/work.sqlite3")),
+        ];
+        let mut output = Vec::new();
+        list_profiles(&profiles, Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "default\nwork\n");
+        let mut output = Vec::new();
+        list_profiles(&profiles[1..], Format::Csv, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "name,path\nwork,profiles/work.sqlite3\n"
+        );
+    }
+
+    #[test]
+    fn lists_tags_with_counts() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .tag_action("1", &["work".into(), "email".into()])
+            .unwrap();
+        store.tag_goal("1", &["work".into()]).unwrap();
+        store.untag_action("1", &["email".into()]).unwrap();
+        let mut output = Vec::new();
+        list_tags(&store, Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "+work 2\n");
+    }
+
+    #[test]
+    fn lists_actions_as_json() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_context("home", None, None).unwrap();
+        store.add_action("Email Alice").unwrap();
+        store.set_action_context("1", Some("home")).unwrap();
+        store.tag_action("1", &["work".into()]).unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        connection
+            .execute("UPDATE actions SET created = '2021-06-01'", [])
+            .unwrap();
+        let options = ListOptions {
+            format: Format::Json,
+            ..ListOptions::default()
+        };
+        let mut output = Vec::new();
+        list_actions::<&str, _>(&store, Status::All, None, &options, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[\n{\"type\":\"action\",\"id\":1,\"description\":\"Email Alice\",\"done\":null,\"created\":\"2021-06-01\",\"due\":null,\"scheduled\":null,\"priority\":null,\"context\":\"home\",\"recurrence\":null,\"parent\":null,\"sequential\":null,\"goals\":[1],\"actions\":null,\"tags\":[\"work\"],\"note\":null}\n]\n"
+        );
+    }
 
-    pub fn run(self, connection: &Connection) -> Result<(), String> {
-        match self {
-            Self::Add {
-                description,
-                action,
-            } => add_goal(&connection, description, action),
-            Self::List { all } => list_goals(&connection, all, &mut io::stdout()),
-            Self::Remove { description } => remove_goal(&connection, description),
-            Self::SetAction {
-                description,
-                action,
-            } => set_goal_action(&connection, description, action),
-            Self::SetDescription {
-                old_description,
-                new_description,
-            } => set_goal_description(&connection, old_description, new_description),
-            Self::UnsetAction { description } => unset_goal_action(&connection, description),
-        }
+    #[test]
+    fn fails_to_remove_missing_tag() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        assert_eq!(
+            store.untag_action("1", &["work".into()]),
+            Err(Error::NotFound("action does not have tag: `+work`".into()))
+        );
     }
-}
-
-fn add_action<T: AsRef<str>>(connection: &Connection, description: T) -> Result<(), String> {
-    connection
-        .execute(
-            "INSERT INTO actions VALUES(?1)",
-            rusqlite::params![description.as_ref()],
-        )
-        .map(|_| ())
-        .map_err(|e| {
-            if let rusqlite::Error::SqliteFailure(
-                libsqlite3_sys::Error {
-                    code: libsqlite3_sys::ErrorCode::ConstraintViolation,
-                    ..
-                },
-                _,
-            ) = e
-            {
-                "action already exists".into()
-            } else {
-                format!("unable to add action: {}", e)
-            }
-        })
-}
 
-fn list_actions<T: Write>(connection: &Connection, writer: &mut T) -> Result<(), String> {
-    let mut statement = connection
-        .prepare("SELECT * FROM actions")
-        .map_err(|e| format!("unable to prepare statement: {}", e))?;
-    let mut rows = statement
-        .query([])
-        .map_err(|e| format!("unable to execute statement: {}", e))?;
-    while let Some(row) = rows
-        .next()
-        .map_err(|e| format!("unable to read row: {}", e))?
-    {
-        let description: String = row
-            .get(0)
-            .map_err(|e| format!("unable to read description: {}", e))?;
-        writeln!(writer, "{}", description)
-            .map_err(|e| format!("unable to write description: {}", e))?;
+    #[test]
+    fn renames_tag_everywhere() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store.tag_action("1", &["job".into()]).unwrap();
+        store.tag_goal("1", &["job".into()]).unwrap();
+        store.rename_tag("job", "work").unwrap();
+        let mut output = Vec::new();
+        list_tags(&store, Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "+work 2\n");
     }
-    Ok(())
-}
 
-fn remove_action<T: AsRef<str>>(connection: &Connection, description: T) -> Result<(), String> {
-    match connection
-        .execute(
-            "DELETE FROM actions WHERE description = ?1",
-            rusqlite::params![description.as_ref()],
-        )
-        .map_err(|e| format!("unable to remove action: {}", e))?
-    {
-        0 => Err("action does not exist".into()),
-        1 => Ok(()),
-        _ => unreachable!(),
+    #[test]
+    fn renaming_tag_to_existing_tag_merges_them() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        store.add_action("Email Bob").unwrap();
+        store
+            .tag_action("1", &["job".into(), "work".into()])
+            .unwrap();
+        store.tag_action("2", &["job".into()]).unwrap();
+        store.rename_tag("job", "work").unwrap();
+        let mut output = Vec::new();
+        list_tags(&store, Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "+work 2\n");
     }
-}
 
-fn set_action_description<T: AsRef<str>, U: AsRef<str>>(
-    connection: &Connection,
-    old_description: T,
-    new_description: U,
-) -> Result<(), String> {
-    match connection
-        .execute(
-            "UPDATE actions SET description = ?1 WHERE description = ?2",
-            rusqlite::params![new_description.as_ref(), old_description.as_ref()],
-        )
-        .map_err(|e| format!("unable to set description: {}", e))?
-    {
-        0 => Err("action does not exist".into()),
-        1 => Ok(()),
-        _ => unreachable!(),
+    #[test]
+    fn fails_to_rename_nonexistent_tag() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(
+            store.rename_tag("job", "work"),
+            Err(Error::NotFound("tag does not exist".into()))
+        );
     }
-}
 
-fn add_goal<T: AsRef<str>, U: AsRef<str>>(
-    connection: &Connection,
-    description: T,
-    action: Option<U>,
-) -> Result<(), String> {
-    if let Some(action) = action {
-        connection
-            .execute(
-                "INSERT INTO goals VALUES(?1, ?2)",
-                rusqlite::params![description.as_ref(), action.as_ref()],
+    #[test]
+    fn shows_action_details() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_context("errands", None, None).unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None::<&str>,
+                GoalType::Parallel,
             )
-            .map(|_| ())
-            .map_err(|e| {
-                if let rusqlite::Error::SqliteFailure(
-                    libsqlite3_sys::Error {
-                        code: libsqlite3_sys::ErrorCode::ConstraintViolation,
-                        ..
-                    },
-                    _,
-                ) = e
-                {
-                    if e.to_string().starts_with("UNIQUE") {
-                        "goal already exists".into()
-                    } else {
-                        "action does not exist".into()
-                    }
-                } else {
-                    format!("unable to add goal: {}", e)
-                }
-            })
-    } else {
+            .unwrap();
+        store.set_action_context("1", Some("errands")).unwrap();
+        store
+            .set_action_date("1", DateField::Due, NaiveDate::from_ymd_opt(2021, 5, 20))
+            .unwrap();
+        store.tag_action("1", &["library".into()]).unwrap();
+        store
+            .set_action_note(
+                "1",
+                Some("Ask for the large-print edition.\n\nThe branch closes at 6.\n"),
+            )
+            .unwrap();
         connection
             .execute(
-                "INSERT INTO goals (description) VALUES(?1)",
-                rusqlite::params![description.as_ref()],
+                "INSERT INTO action_annotations (action, created, text) VALUES(1, '2021-05-14 10:00:00', 'Called the library.')",
+                [],
             )
-            .map(|_| ())
-            .map_err(|e| {
-                if let rusqlite::Error::SqliteFailure(
-                    libsqlite3_sys::Error {
-                        code: libsqlite3_sys::ErrorCode::ConstraintViolation,
-                        ..
-                    },
-                    _,
-                ) = e
-                {
-                    "goal already exists".into()
-                } else {
-                    format!("unable to add goal: {}", e)
-                }
-            })
+            .unwrap();
+        let created: String = connection
+            .query_row(
+                "SELECT datetime('2021-05-14 10:00:00', 'localtime')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        show_action(&store, "1", &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "1 Borrow *Network Effect*.\ncontext: @errands\ndue: 2021-05-20\ngoal: 1 Read *Network Effect*.\ntags: +library\nnote:\n  Ask for the large-print edition.\n\n  The branch closes at 6.\nannotations:\n  {} Called the library.\n",
+                created
+            )
+        );
     }
-}
 
-fn list_goals<T: Write>(connection: &Connection, all: bool, writer: &mut T) -> Result<(), String> {
-    let statement = if all {
-        "SELECT description FROM goals"
-    } else {
-        "SELECT description FROM goals WHERE action IS NULL"
-    };
-    let mut statement = connection
-        .prepare(statement)
-        .map_err(|e| format!("unable to prepare statement: {}", e))?;
-    let mut rows = statement
-        .query([])
-        .map_err(|e| format!("unable to execute statement: {}", e))?;
-    while let Some(row) = rows
-        .next()
-        .map_err(|e| format!("unable to read row: {}", e))?
-    {
-        let description: String = row
-            .get(0)
-            .map_err(|e| format!("unable to read description: {}", e))?;
-        writeln!(writer, "{}", description)
-            .map_err(|e| format!("unable to write description: {}", e))?;
+    #[test]
+    fn shows_goal_details() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Read more", None, None, GoalType::Parallel)
+            .unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                Some("Read more"),
+                GoalType::Sequential,
+            )
+            .unwrap();
+        store.annotate_goal("2", "Started chapter 1.").unwrap();
+        let mut output = Vec::new();
+        show_goal(&store, "2", &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("2 Read *Network Effect*.\nparent: 1 Read more\ntype: sequential\naction: 1 Borrow *Network Effect*.\nannotations:\n  "));
+        assert!(output.ends_with(" Started chapter 1.\n"));
     }
-    Ok(())
-}
 
-fn remove_goal<T: AsRef<str>>(connection: &Connection, description: T) -> Result<(), String> {
-    match connection
-        .execute(
-            "DELETE FROM goals WHERE description = ?1",
-            rusqlite::params![description.as_ref()],
-        )
-        .map_err(|e| format!("unable to remove goal: {}", e))?
-    {
-        0 => Err("goal does not exist".into()),
-        1 => Ok(()),
-        _ => unreachable!(),
+    #[test]
+    fn removes_blank_note() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .set_action_note("1", Some("Ask at the desk."))
+            .unwrap();
+        store.set_action_note("1", Some(" \n")).unwrap();
+        assert_eq!(
+            connection.query_row("SELECT note FROM actions", [], |row| row
+                .get::<usize, Option<String>>(0)),
+            Ok(None)
+        );
     }
-}
 
-fn set_goal_action<T: AsRef<str>, U: AsRef<str>>(
-    connection: &Connection,
-    description: T,
-    action: U,
-) -> Result<(), String> {
-    match connection
-        .execute(
-            "UPDATE goals SET action = ?1 WHERE description = ?2",
-            rusqlite::params![action.as_ref(), description.as_ref()],
-        )
-        .map_err(|e| {
-            if let rusqlite::Error::SqliteFailure(
-                libsqlite3_sys::Error {
-                    code: libsqlite3_sys::ErrorCode::ConstraintViolation,
-                    ..
-                },
-                _,
-            ) = e
-            {
-                "action does not exist".into()
-            } else {
-                format!("unable to set action: {}", e)
-            }
-        })? {
-        0 => Err("goal does not exist".into()),
-        1 => Ok(()),
-        _ => unreachable!(),
-    }
-}
-
-fn set_goal_description<T: AsRef<str>, U: AsRef<str>>(
-    connection: &Connection,
-    old_description: T,
-    new_description: U,
-) -> Result<(), String> {
-    match connection
-        .execute(
-            "UPDATE goals SET description = ?1 WHERE description = ?2",
-            rusqlite::params![new_description.as_ref(), old_description.as_ref()],
-        )
-        .map_err(|e| format!("unable to set description: {}", e))?
-    {
-        0 => Err("goal does not exist".into()),
-        1 => Ok(()),
-        _ => unreachable!(),
+    #[test]
+    fn fails_to_annotate_nonexistent_goal() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(
+            store.annotate_goal("Read more", "Started."),
+            Err(Error::NotFound("goal does not exist".into()))
+        );
+    }
+
+    #[test]
+    fn searches_descriptions_before_notes() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Call Alice").unwrap();
+        store
+            .set_action_note("1", Some("Ask whether the library has *Network Effect*."))
+            .unwrap();
+        store
+            .add_goal("Visit the library", None, None, GoalType::Parallel)
+            .unwrap();
+        let mut output = Vec::new();
+        search(&store, "library", false, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "goal 1 Visit the library\naction 1 Call Alice\n"
+        );
     }
-}
 
-fn unset_goal_action<T: AsRef<str>>(connection: &Connection, description: T) -> Result<(), String> {
-    match connection
-        .execute(
-            "UPDATE goals SET action = NULL WHERE description = ?1",
-            rusqlite::params![description.as_ref()],
+    #[test]
+    fn searches_phrases_and_prefixes() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store.add_action("Fix the network at home").unwrap();
+        store.add_action("Measure the effect of caching").unwrap();
+        let mut output = Vec::new();
+        search(
+            &store,
+            "\"network effect\"",
+            false,
+            Format::Text,
+            &mut output,
         )
-        .map_err(|e| format!("unable to unset action: {}", e))?
-    {
-        0 => Err("goal does not exist".into()),
-        1 => Ok(()),
-        _ => unreachable!(),
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "action 1 Borrow *Network Effect*.\n"
+        );
+        let mut output = Vec::new();
+        search(&store, "cach*", false, Format::Text, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "action 3 Measure the effect of caching\n"
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rusqlite::Error;
-    use std::array::IntoIter;
+    #[test]
+    fn keeps_search_index_in_sync() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store.add_action("Return *Network Effect*.").unwrap();
+        store
+            .add_goal("Read more", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .set_action_description("1", "Buy *Network Effect*.")
+            .unwrap();
+        store
+            .set_goal_description("1", "Read *Network Effect*.")
+            .unwrap();
+        store.remove_action("2").unwrap();
+        let mut output = Vec::new();
+        search(&store, "network", false, Format::Text, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines().collect::<Vec<_>>();
+        lines.sort_unstable();
+        assert_eq!(
+            lines,
+            [
+                "action 1 Buy *Network Effect*.",
+                "goal 1 Read *Network Effect*."
+            ]
+        );
+        let mut output = Vec::new();
+        search(&store, "borrow OR return", false, Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+    }
 
     #[test]
-    fn reports_missing_command() {
+    fn searches_done_items_only_with_all() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store.complete_action("1").unwrap();
+        let mut output = Vec::new();
+        search(&store, "borrow", false, Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+        let mut output = Vec::new();
+        search(&store, "borrow", true, Format::Text, &mut output).unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([])),
-            Err("missing command".to_string())
+            String::from_utf8(output).unwrap(),
+            "action 1 Borrow *Network Effect*.\n"
         );
     }
 
     #[test]
-    fn reports_no_such_command() {
+    fn reports_invalid_query() {
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["foo".to_string()])),
-            Err("no such command: `foo`".to_string())
+            search(&store, "\"network", false, Format::Text, &mut Vec::new()),
+            Err(Error::Usage("invalid query: `\"network`".into()))
         );
     }
 
     #[test]
-    fn reports_missing_action_subcommand() {
+    fn hides_scheduled_actions_until_start_date() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Renew passport").unwrap();
+        store.add_action("File taxes").unwrap();
+        store
+            .set_action_date(
+                "1",
+                DateField::Scheduled,
+                NaiveDate::from_ymd_opt(2999, 1, 1),
+            )
+            .unwrap();
+        store
+            .set_action_date(
+                "2",
+                DateField::Scheduled,
+                NaiveDate::from_ymd_opt(2000, 1, 1),
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "2 File taxes\n");
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::All,
+            None,
+            &ListOptions {
+                selection: Selection {
+                    later: true,
+                    ..Selection::default()
+                },
+                ..ListOptions::default()
+            },
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["action".to_string()])),
-            Err("missing subcommand".to_string())
+            String::from_utf8(output).unwrap(),
+            "1 Renew passport\n2 File taxes\n"
         );
     }
 
     #[test]
-    fn reports_no_such_action_subcommand() {
+    fn lists_actions_by_due_date() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.add_action("Renew passport").unwrap();
+        store.add_action("File taxes").unwrap();
+        store
+            .set_action_date("2", DateField::Due, NaiveDate::from_ymd_opt(2999, 1, 1))
+            .unwrap();
+        store
+            .set_action_date("3", DateField::Due, NaiveDate::from_ymd_opt(2000, 4, 15))
+            .unwrap();
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions {
+                selection: Selection {
+                    sort: Sort::Due,
+                    ..Selection::default()
+                },
+                ..ListOptions::default()
+            },
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "3 File taxes\n2 Renew passport\n1 Water the plants\n"
+        );
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions {
+                selection: Selection {
+                    overdue: true,
+                    ..Selection::default()
+                },
+                ..ListOptions::default()
+            },
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "3 File taxes\n");
+        let mut output = Vec::new();
+        list_actions::<&str, _>(
+            &store,
+            Status::Open,
+            None,
+            &ListOptions {
+                selection: Selection {
+                    due_before: NaiveDate::from_ymd_opt(3000, 1, 1),
+                    ..Selection::default()
+                },
+                ..ListOptions::default()
+            },
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["action".to_string(), "foo".to_string()])),
-            Err("no such subcommand: `foo`".to_string())
+            String::from_utf8(output).unwrap(),
+            "2 Renew passport\n3 File taxes\n"
         );
     }
 
     #[test]
-    fn reports_missing_action_add_description() {
+    fn unsets_action_date() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("File taxes").unwrap();
+        store
+            .set_action_date("1", DateField::Due, NaiveDate::from_ymd_opt(2000, 4, 15))
+            .unwrap();
+        store.set_action_date("1", DateField::Due, None).unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["action".to_string(), "add".to_string()])),
-            Err("missing description".to_string())
+            connection.query_row("SELECT due FROM actions", [], |row| row
+                .get::<_, Option<String>>(0)),
+            Ok(None)
         );
     }
 
     #[test]
-    fn reports_extra_action_ls_argument() {
+    fn completes_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Read *Network Effect*.").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "action".to_string(),
-                "ls".to_string(),
-                "foo".to_string()
-            ])),
-            Err("extra argument: `foo`".to_string())
+            store.complete_action("Read *Network Effect*."),
+            Ok(Vec::new())
         );
+        assert!(connection
+            .query_row("SELECT done FROM actions", [], |row| row
+                .get::<usize, Option<String>>(0))
+            .unwrap()
+            .is_some());
     }
 
     #[test]
-    fn reports_missing_action_rm_description() {
+    fn completing_action_lists_goals() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["action".to_string(), "rm".to_string()])),
-            Err("missing description".to_string())
+            store
+                .complete_action("Borrow *Network Effect*.")
+                .map(|goals| goals.into_iter().map(|goal| goal.id).collect::<Vec<_>>()),
+            Ok(vec![1])
         );
     }
 
     #[test]
-    fn reports_missing_action_set_field() {
+    fn completing_recurring_action_adds_next_occurrence() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Send invoice").unwrap();
+        store
+            .set_action_date("1", DateField::Due, NaiveDate::from_ymd_opt(2021, 1, 31))
+            .unwrap();
+        store
+            .set_action_date(
+                "1",
+                DateField::Scheduled,
+                NaiveDate::from_ymd_opt(2021, 1, 24),
+            )
+            .unwrap();
+        store
+            .set_action_recurrence("1", Some(Recurrence::from_arg("monthly").unwrap()))
+            .unwrap();
+        store.complete_action("1").unwrap();
+        store.complete_action("2").unwrap();
+        let mut statement = connection
+            .prepare("SELECT id, description, done IS NULL, due, scheduled, recurrence FROM actions ORDER BY id")
+            .unwrap();
+        let actions = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["action".to_string(), "set".to_string()])),
-            Err("missing field".to_string())
+            actions,
+            vec![
+                (
+                    1,
+                    "Send invoice".into(),
+                    false,
+                    "2021-01-31".into(),
+                    "2021-01-24".into(),
+                    "FREQ=MONTHLY;BYMONTHDAY=31".into()
+                ),
+                (
+                    2,
+                    "Send invoice".into(),
+                    false,
+                    "2021-02-28".into(),
+                    "2021-02-21".into(),
+                    "FREQ=MONTHLY;BYMONTHDAY=31".into()
+                ),
+                (
+                    3,
+                    "Send invoice".into(),
+                    true,
+                    "2021-03-31".into(),
+                    "2021-03-24".into(),
+                    "FREQ=MONTHLY;BYMONTHDAY=31".into()
+                ),
+            ]
         );
     }
 
     #[test]
-    fn reports_no_such_action_field() {
+    fn completing_recurring_action_keeps_goal_link() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Write weekly report").unwrap();
+        store
+            .add_goal(
+                "Keep the team informed",
+                Some("1"),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
+        store
+            .set_action_recurrence("1", Some(Recurrence::from_arg("daily").unwrap()))
+            .unwrap();
+        assert_eq!(store.complete_action("1"), Ok(Vec::new()));
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "action".to_string(),
-                "set".to_string(),
-                "foo".to_string(),
-            ])),
-            Err("no such field: `foo`".to_string())
+            connection.query_row("SELECT goal, action FROM goal_actions", [], |row| Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?
+            ))),
+            Ok((1, 2))
         );
     }
 
     #[test]
-    fn reports_missing_old_action_description() {
+    fn completing_recurring_action_keeps_tags() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write weekly report").unwrap();
+        store
+            .tag_action("1", &["work".into(), "writing".into()])
+            .unwrap();
+        store
+            .set_action_recurrence("1", Some(Recurrence::from_arg("weekly").unwrap()))
+            .unwrap();
+        store.complete_action("1").unwrap();
+        for id in [1, 2] {
+            assert_eq!(
+                store.action(&id.to_string()).unwrap().tags,
+                vec!["work", "writing"]
+            );
+        }
+    }
+
+    #[test]
+    fn completing_recurring_action_keeps_note() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.set_action_note("1", Some("Not the cactus")).unwrap();
+        store
+            .set_action_recurrence("1", Some(Recurrence::from_arg("weekly").unwrap()))
+            .unwrap();
+        store.complete_action("1").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "action".to_string(),
-                "set".to_string(),
-                "description".to_string(),
-            ])),
-            Err("missing old description".to_string())
+            store.action("2").unwrap().note.as_deref(),
+            Some("Not the cactus")
         );
     }
 
     #[test]
-    fn reports_missing_new_action_description() {
+    fn unsets_action_recurrence() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Pay rent").unwrap();
+        store
+            .set_action_recurrence("1", Some(Recurrence::from_arg("daily").unwrap()))
+            .unwrap();
+        store.set_action_recurrence("1", None).unwrap();
+        store.complete_action("1").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "action".to_string(),
-                "set".to_string(),
-                "description".to_string(),
-                "Read *Network Efect*.".to_string(),
-            ])),
-            Err("missing new description".to_string())
+            connection.query_row("SELECT count(*) FROM actions", [], |row| row
+                .get::<_, i64>(0)),
+            Ok(1)
         );
     }
 
     #[test]
-    fn parses_action_set_description() {
+    fn fails_to_complete_done_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Read *Network Effect*.").unwrap();
+        store.complete_action("Read *Network Effect*.").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "action".to_string(),
-                "set".to_string(),
-                "description".to_string(),
-                "Read *Network Efect*.".to_string(),
-                "Read".to_string(),
-                "*Network".to_string(),
-                "Effect*.".to_string(),
-            ])),
-            Ok(Command::Action(ActionSubcommand::SetDescription {
-                old_description: "Read *Network Efect*.".into(),
-                new_description: "Read *Network Effect*.".into()
-            }))
+            store.complete_action("Read *Network Effect*."),
+            Err(Error::Constraint("action is already done".into()))
+        );
+    }
+
+    #[test]
+    fn adds_action_with_description_of_done_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Read *Network Effect*.").unwrap();
+        store.complete_action("Read *Network Effect*.").unwrap();
+        store.add_action("Read *Network Effect*.").unwrap();
+        store.remove_action("Read *Network Effect*.").unwrap();
+        assert_eq!(
+            connection
+                .query_row("SELECT id FROM actions", [], |row| row.get::<usize, i64>(0))
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn removes_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Read *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        store.remove_action("Read *Network Effect*.").unwrap();
+        assert_eq!(
+            connection.query_row("SELECT * FROM actions", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
         );
     }
 
     #[test]
-    fn reports_missing_goal_subcommand() {
+    fn fails_to_remove_nonexistent_action() {
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["goal".to_string()])),
-            Err("missing subcommand".to_string())
+            store.remove_action("Read *Network Effect*."),
+            Err(Error::NotFound("action does not exist".into()))
         );
     }
 
     #[test]
-    fn reports_no_such_goal_subcommand() {
+    fn removes_action_by_id() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Read *Network Effect*.").unwrap();
+        store.remove_action("1").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["goal".to_string(), "foo".to_string()])),
-            Err("no such subcommand: `foo`".to_string())
+            connection.query_row("SELECT * FROM actions", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
         );
     }
 
     #[test]
-    fn reports_missing_goal_add_description() {
+    fn removes_action_by_hash_id() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Read *Network Effect*.").unwrap();
+        store.remove_action("#1").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["goal".to_string(), "add".to_string()])),
-            Err("missing description".to_string())
+            connection.query_row("SELECT * FROM actions", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
         );
     }
 
     #[test]
-    fn reports_missing_goal_action() {
+    fn removes_action_with_numeric_description() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("42").unwrap();
+        store.remove_action("42").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "add".to_string(),
-                "--action".to_string()
-            ])),
-            Err("option `--action` requires an argument".to_string())
+            connection.query_row("SELECT * FROM actions", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
         );
     }
 
     #[test]
-    fn reports_extra_goal_ls_argument() {
+    fn fails_to_remove_action_with_nonexistent_id() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Read *Network Effect*.").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "ls".to_string(),
-                "foo".to_string()
-            ])),
-            Err("extra argument: `foo`".to_string())
+            store.remove_action("#2"),
+            Err(Error::NotFound("action does not exist".into()))
         );
     }
 
     #[test]
-    fn reports_extra_goal_ls_argument_after_all() {
+    fn removing_action_clears_goal_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Borrow *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.'); INSERT INTO goal_actions VALUES(1, 1, 0);",
+            )
+            .unwrap();
+        store.remove_action("Borrow *Network Effect*.").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "ls".to_string(),
-                "--all".to_string(),
-                "foo".to_string()
-            ])),
-            Err("extra argument: `foo`".to_string())
+            connection.query_row("SELECT * FROM goal_actions", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
         );
     }
 
     #[test]
-    fn parses_goal_ls() {
+    fn sets_action_description() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Read *Network Efect*.')",
+                [],
+            )
+            .unwrap();
+        store
+            .set_action_description("Read *Network Efect*.", "Read *Network Effect*.")
+            .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["goal".to_string(), "ls".to_string()])),
-            Ok(Command::Goal(GoalSubcommand::List { all: false }))
+            connection
+                .query_row("SELECT description FROM actions", [], |row| row
+                    .get::<usize, String>(0))
+                .unwrap(),
+            "Read *Network Effect*."
         );
     }
 
     #[test]
-    fn parses_goal_ls_all() {
+    fn fails_to_set_nonexistent_action_description() {
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "ls".to_string(),
-                "--all".to_string()
-            ])),
-            Ok(Command::Goal(GoalSubcommand::List { all: true }))
+            store.set_action_description("Read *Network Efect*.", "Read *Network Effect*."),
+            Err(Error::NotFound("action does not exist".into()))
         );
     }
 
     #[test]
-    fn reports_missing_goal_rm_description() {
+    fn fails_to_set_duplicate_action_description() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Read *Network Effect*.").unwrap();
+        store.add_action("Read *Network Efect*.").unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["goal".to_string(), "rm".to_string()])),
-            Err("missing description".to_string())
+            store.set_action_description("#2", "Read *Network Effect*."),
+            Err(Error::AlreadyExists("action already exists".into()))
         );
     }
 
     #[test]
-    fn reports_missing_goal_set_field() {
+    fn updates_goal_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Borrow *Network Efect*.')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.'); INSERT INTO goal_actions VALUES(1, 1, 0);",
+            )
+            .unwrap();
+        store
+            .set_action_description("Borrow *Network Efect*.", "Borrow *Network Effect*.")
+            .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["goal".to_string(), "set".to_string()])),
-            Err("missing field".to_string())
+            connection
+                .query_row("SELECT action FROM goal_actions", [], |row| row
+                    .get::<usize, i64>(0))
+                .unwrap(),
+            1,
         );
     }
 
     #[test]
-    fn reports_no_such_goal_set_field() {
-        assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "set".to_string(),
-                "foo".to_string(),
-            ])),
-            Err("no such field: `foo`".to_string())
-        );
+    fn adds_goal() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
+            .unwrap();
+        let (description, action): (String, Option<i64>) = connection
+            .query_row("SELECT goals.description, goal_actions.action FROM goals LEFT JOIN goal_actions ON goal_actions.goal = goals.id", [], |row| {
+                Ok((row.get_unwrap(0), row.get_unwrap(1)))
+            })
+            .unwrap();
+        assert_eq!(description, "Read *Network Effect*.");
+        assert_eq!(action, None);
     }
 
     #[test]
-    fn reports_missing_goal_set_description() {
-        assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "set".to_string(),
-                "action".to_string(),
-            ])),
-            Err("missing description".to_string())
-        );
+    fn adds_goal_with_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Borrow *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
+        let (description, action): (String, Option<i64>) = connection
+            .query_row("SELECT goals.description, goal_actions.action FROM goals LEFT JOIN goal_actions ON goal_actions.goal = goals.id", [], |row| {
+                Ok((row.get_unwrap(0), row.get_unwrap(1)))
+            })
+            .unwrap();
+        assert_eq!(description, "Read *Network Effect*.");
+        assert_eq!(action, Some(1));
     }
 
     #[test]
-    fn reports_missing_action() {
+    fn fails_to_add_duplicate_goal() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
+            .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "set".to_string(),
-                "action".to_string(),
-                "Read *Network Effect*.".to_string(),
-            ])),
-            Err("missing action".to_string())
+            store.add_goal("Read *Network Effect*.", None, None, GoalType::Parallel),
+            Err(Error::AlreadyExists("goal already exists".into()))
         );
     }
 
     #[test]
-    fn parses_goal_set_action() {
+    fn fails_to_add_duplicate_goal_with_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
+            .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "set".to_string(),
-                "action".to_string(),
-                "Read *Network Effect*.".to_string(),
-                "Borrow".to_string(),
-                "*Network".to_string(),
-                "Effect*.".to_string(),
-            ])),
-            Ok(Command::Goal(GoalSubcommand::SetAction {
-                description: "Read *Network Effect*.".into(),
-                action: "Borrow *Network Effect*.".into()
-            }))
+            store.add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel
+            ),
+            Err(Error::AlreadyExists("goal already exists".into()))
         );
     }
 
     #[test]
-    fn reports_missing_old_goal_description() {
+    fn fails_to_add_goal_with_nonexistent_action() {
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "set".to_string(),
-                "description".to_string(),
-            ])),
-            Err("missing old description".to_string())
+            store.add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel
+            ),
+            Err(Error::NotFound("action does not exist".into()))
         );
     }
 
     #[test]
-    fn reports_missing_new_goal_description() {
+    fn lists_no_goals() {
+        let store = Store::open_in_memory().unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+    }
+
+    #[test]
+    fn lists_goal() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "set".to_string(),
-                "description".to_string(),
-                "Read *Network Efect*.".to_string(),
-            ])),
-            Err("missing new description".to_string())
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n"
         );
     }
 
     #[test]
-    fn parses_goal_set_description() {
+    fn lists_goals() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO goals (description) VALUES('Read *What Were We Thinking*.')",
+                [],
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "set".to_string(),
-                "description".to_string(),
-                "Read *Network Efect*.".to_string(),
-                "Read".to_string(),
-                "*Network".to_string(),
-                "Effect*.".to_string(),
-            ])),
-            Ok(Command::Goal(GoalSubcommand::SetDescription {
-                old_description: "Read *Network Efect*.".into(),
-                new_description: "Read *Network Effect*.".into()
-            }))
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n2 Read *What Were We Thinking*.\n"
         );
     }
 
     #[test]
-    fn reports_missing_goal_unset_field() {
+    fn does_not_list_goal_with_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Borrow *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.'); INSERT INTO goal_actions VALUES(1, 1, 0);",
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+    }
+
+    #[test]
+    fn lists_goal_with_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Borrow *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.'); INSERT INTO goal_actions VALUES(1, 1, 0);",
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::All,
+            false,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new(["goal".to_string(), "unset".to_string()])),
-            Err("missing field".to_string())
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n"
         );
     }
 
     #[test]
-    fn reports_no_such_goal_unset_field() {
+    fn lists_goal_with_done_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
+        store.complete_action("Borrow *Network Effect*.").unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "unset".to_string(),
-                "foo".to_string(),
-            ])),
-            Err("no such field: `foo`".to_string())
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n"
         );
     }
 
     #[test]
-    fn reports_missing_goal_unset_description() {
+    fn lists_overdue_goals() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Ship 3.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Ship 4.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .set_goal_date("1", DateField::Due, NaiveDate::from_ymd_opt(2000, 1, 1))
+            .unwrap();
+        store
+            .set_goal_date(
+                "3",
+                DateField::Scheduled,
+                NaiveDate::from_ymd_opt(2999, 1, 1),
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "unset".to_string(),
-                "action".to_string(),
-            ])),
-            Err("missing description".to_string())
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n2 Ship 3.0\n"
         );
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions {
+                selection: Selection {
+                    overdue: true,
+                    ..Selection::default()
+                },
+                ..ListOptions::default()
+            },
+            None,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "1 Ship 2.0\n");
     }
 
     #[test]
-    fn parses_goal_unset_action() {
+    fn completes_goal() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
+            .unwrap();
+        store.complete_goal("Read *Network Effect*.").unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "");
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Done,
+            false,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            Command::from_args(IntoIter::new([
-                "goal".to_string(),
-                "unset".to_string(),
-                "action".to_string(),
-                "Read".to_string(),
-                "*Network".to_string(),
-                "Effect*.".to_string(),
-            ])),
-            Ok(Command::Goal(GoalSubcommand::UnsetAction {
-                description: "Read *Network Effect*.".into(),
-            }))
+            String::from_utf8(output).unwrap(),
+            "1 Read *Network Effect*.\n"
         );
     }
 
     #[test]
-    fn adds_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        add_action(&connection, "Read *Network Effect*.").unwrap();
+    fn lists_all_unfinished_goals() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Ship 1.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store.complete_goal("2").unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::Open,
+            false,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
-            connection
-                .query_row("SELECT * FROM actions", [], |row| row
-                    .get::<usize, String>(0))
-                .unwrap(),
-            "Read *Network Effect*."
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0
+"
+        );
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::All,
+            false,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n2 Ship 1.0\n"
         );
     }
 
     #[test]
-    fn fails_to_add_duplicate_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        add_action(&connection, "Read *Network Effect*.").unwrap();
+    fn fails_to_complete_done_goal() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
+            .unwrap();
+        store.complete_goal("1").unwrap();
         assert_eq!(
-            add_action(&connection, "Read *Network Effect*."),
-            Err("action already exists".to_string())
+            store.complete_goal("1"),
+            Err(Error::Constraint("goal is already done".into()))
         );
     }
 
     #[test]
-    fn lists_no_actions() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+    fn does_not_list_goal_with_one_open_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
+            .unwrap();
+        store.add_goal_action("1", "2").unwrap();
+        store.complete_action("1").unwrap();
         let mut output = Vec::new();
-        list_actions(&connection, &mut output).unwrap();
+        list_goals(
+            &store,
+            Status::Open,
+            true,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(String::from_utf8(output).unwrap(), "");
     }
 
     #[test]
-    fn lists_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Read *Network Effect*.')", [])
+    fn lists_goal_actions_in_order() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store.add_action("Announce release").unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
             .unwrap();
+        store.add_goal_action("1", "2").unwrap();
+        store.add_goal_action("1", "1").unwrap();
+        store.add_goal_action("1", "3").unwrap();
         let mut output = Vec::new();
-        list_actions(&connection, &mut output).unwrap();
+        list_goal_actions(&store, "Ship 2.0", Format::Text, &mut output).unwrap();
         assert_eq!(
             String::from_utf8(output).unwrap(),
-            "Read *Network Effect*.\n"
+            "2 Tag release\n1 Write release notes\n3 Announce release\n"
         );
     }
 
     #[test]
-    fn lists_actions() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Read *Network Effect*.')", [])
+    fn fails_to_add_goal_action_twice() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
             .unwrap();
-        connection
-            .execute(
-                "INSERT INTO actions VALUES('Read *What Were We Thinking*.')",
-                [],
-            )
+        assert_eq!(
+            store.add_goal_action("1", "1"),
+            Err(Error::AlreadyExists("goal already has action".into()))
+        );
+    }
+
+    #[test]
+    fn moves_goal_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store.add_action("Announce release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
             .unwrap();
+        store.add_goal_action("1", "2").unwrap();
+        store.add_goal_action("1", "3").unwrap();
+        store.move_goal_action("1", 1, "3").unwrap();
+        store.move_goal_action("1", 5, "1").unwrap();
         let mut output = Vec::new();
-        list_actions(&connection, &mut output).unwrap();
+        list_goal_actions(&store, "1", Format::Text, &mut output).unwrap();
         assert_eq!(
             String::from_utf8(output).unwrap(),
-            "Read *Network Effect*.\nRead *What Were We Thinking*.\n"
+            "3 Announce release\n2 Tag release\n1 Write release notes\n"
         );
     }
 
     #[test]
-    fn removes_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Read *Network Effect*.')", [])
+    fn fails_to_move_unlinked_goal_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
             .unwrap();
-        remove_action(&connection, "Read *Network Effect*.").unwrap();
         assert_eq!(
-            connection.query_row("SELECT * FROM actions", [], |_| Ok(())),
-            Err(Error::QueryReturnedNoRows)
+            store.move_goal_action("1", 1, "1"),
+            Err(Error::NotFound("goal does not have action".into()))
         );
     }
 
     #[test]
-    fn fails_to_remove_nonexistent_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+    fn fails_to_move_goal_action_to_position_0() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store
+            .add_goal(
+                "Ship 2.0",
+                Some("Write release notes"),
+                None::<&str>,
+                GoalType::Parallel,
+            )
+            .unwrap();
         assert_eq!(
-            remove_action(&connection, "Read *Network Effect*."),
-            Err("action does not exist".to_string())
+            store.move_goal_action("1", 0, "1"),
+            Err(Error::Usage("invalid position: `0`".into()))
         );
     }
 
     #[test]
-    fn removing_action_clears_goal_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Borrow *Network Effect*.')", [])
+    fn removes_goal_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
             .unwrap();
+        store.add_goal_action("1", "2").unwrap();
+        store.remove_goal_action("1", "1").unwrap();
+        assert_eq!(
+            store.remove_goal_action("1", "1"),
+            Err(Error::NotFound("goal does not have action".into()))
+        );
+        let mut output = Vec::new();
+        list_goal_actions(&store, "1", Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "2 Tag release\n");
+    }
+
+    #[test]
+    fn removes_goal() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
         connection
             .execute(
-                "INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Effect*.')",
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
                 [],
             )
             .unwrap();
-        remove_action(&connection, "Borrow *Network Effect*.").unwrap();
+        store.remove_goal("Read *Network Effect*.", false).unwrap();
+        assert_eq!(
+            connection.query_row("SELECT * FROM goals", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        );
+    }
+
+    #[test]
+    fn adds_goal_with_parent() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal(
+                "Write release notes",
+                None,
+                Some("Ship 2.0"),
+                GoalType::Parallel,
+            )
+            .unwrap();
         assert_eq!(
             connection
-                .query_row("SELECT action FROM goals", [], |row| row
-                    .get::<usize, Option<String>>(0))
+                .query_row("SELECT parent FROM goals WHERE id = 2", [], |row| row
+                    .get::<usize, Option<i64>>(0))
                 .unwrap(),
-            None
+            Some(1)
         );
     }
 
     #[test]
-    fn sets_action_description() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute(
-                "INSERT INTO actions (description) VALUES('Read *Network Efect*.')",
-                [],
+    fn fails_to_remove_goal_with_subgoals() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Write release notes", None, Some("1"), GoalType::Parallel)
+            .unwrap();
+        assert_eq!(
+            store.remove_goal("Ship 2.0", false),
+            Err(Error::Constraint("goal has sub-goals".into()))
+        );
+    }
+
+    #[test]
+    fn removes_goal_recursively() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Write release notes", None, Some("1"), GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal(
+                "Proofread release notes",
+                None,
+                Some("2"),
+                GoalType::Parallel,
             )
             .unwrap();
-        set_action_description(
-            &connection,
-            "Read *Network Efect*.",
-            "Read *Network Effect*.",
-        )
-        .unwrap();
+        store
+            .add_goal("Ship 3.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store.remove_goal("Ship 2.0", true).unwrap();
         assert_eq!(
             connection
-                .query_row("SELECT description FROM actions", [], |row| row
+                .query_row("SELECT group_concat(description) FROM goals", [], |row| row
                     .get::<usize, String>(0))
                 .unwrap(),
-            "Read *Network Effect*."
+            "Ship 3.0"
         );
     }
 
     #[test]
-    fn fails_to_set_nonexistent_action_description() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+    fn fails_to_remove_nonexistent_goal() {
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            set_action_description(
-                &connection,
-                "Read *Network Efect*.",
-                "Read *Network Effect*."
-            ),
-            Err("action does not exist".to_string())
+            store.remove_goal("Read *Network Effect*.", false),
+            Err(Error::NotFound("goal does not exist".into()))
         );
     }
 
     #[test]
-    fn updates_goal_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+    fn sets_goal_action() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
         connection
-            .execute("INSERT INTO actions VALUES('Borrow *Network Efect*.')", [])
+            .execute(
+                "INSERT INTO actions (description) VALUES('Borrow *Network Effect*.')",
+                [],
+            )
             .unwrap();
         connection
             .execute(
-                "INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Efect*.')",
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
                 [],
             )
             .unwrap();
-        set_action_description(
-            &connection,
-            "Borrow *Network Efect*.",
-            "Borrow *Network Effect*.",
-        )
-        .unwrap();
+        store
+            .set_goal_action("Read *Network Effect*.", "Borrow *Network Effect*.")
+            .unwrap();
         assert_eq!(
             connection
-                .query_row("SELECT action FROM goals", [], |row| row
-                    .get::<usize, String>(0))
+                .query_row("SELECT action FROM goal_actions", [], |row| row
+                    .get::<usize, i64>(0))
                 .unwrap(),
-            "Borrow *Network Effect*.",
+            1
         );
     }
 
     #[test]
-    fn adds_goal() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        add_goal::<&str, &str>(&connection, "Read *Network Effect*.", None).unwrap();
-        let (description, action): (String, Option<String>) = connection
-            .query_row("SELECT * FROM goals", [], |row| {
-                Ok((row.get_unwrap(0), row.get_unwrap(1)))
-            })
+    fn sets_goal_action_by_id() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
             .unwrap();
-        assert_eq!(description, "Read *Network Effect*.");
-        assert_eq!(action, None);
+        store.set_goal_action("#1", "1").unwrap();
+        assert_eq!(
+            connection
+                .query_row("SELECT action FROM goal_actions", [], |row| row
+                    .get::<usize, i64>(0))
+                .unwrap(),
+            1
+        );
     }
 
     #[test]
-    fn adds_goal_with_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Borrow *Network Effect*.')", [])
-            .unwrap();
-        add_goal(
-            &connection,
-            "Read *Network Effect*.",
-            Some("Borrow *Network Effect*."),
-        )
-        .unwrap();
-        let (description, action): (String, Option<String>) = connection
-            .query_row("SELECT * FROM goals", [], |row| {
-                Ok((row.get_unwrap(0), row.get_unwrap(1)))
-            })
+    fn setting_goal_action_replaces_actions() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Write release notes").unwrap();
+        store.add_action("Tag release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Parallel)
             .unwrap();
-        assert_eq!(description, "Read *Network Effect*.");
-        assert_eq!(action, Some("Borrow *Network Effect*.".to_string()));
+        store.set_goal_action("1", "2").unwrap();
+        let mut output = Vec::new();
+        list_goal_actions(&store, "1", Format::Text, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "2 Tag release\n");
     }
 
     #[test]
-    fn fails_to_add_duplicate_goal() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        add_goal::<&str, &str>(&connection, "Read *Network Effect*.", None).unwrap();
+    fn fails_to_set_nonexistent_action_of_goal() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
+            .unwrap();
         assert_eq!(
-            add_goal::<&str, &str>(&connection, "Read *Network Effect*.", None),
-            Err("goal already exists".to_string())
+            store.set_goal_action("Read *Network Effect*.", "Borrow *Network Effect*."),
+            Err(Error::NotFound("action does not exist".into()))
         );
     }
 
     #[test]
-    fn fails_to_add_duplicate_goal_with_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        add_action(&connection, "Borrow *Network Effect*.").unwrap();
-        add_goal::<&str, &str>(&connection, "Read *Network Effect*.", None).unwrap();
+    fn fails_to_set_nonexistent_goal_action() {
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            add_goal(
-                &connection,
-                "Read *Network Effect*.",
-                Some("Borrow *Network Effect*.")
-            ),
-            Err("goal already exists".to_string())
+            store.set_goal_description("Read *Network Effect*.", "Borrow *Network Effect*."),
+            Err(Error::NotFound("goal does not exist".into()))
         );
     }
 
     #[test]
-    fn fails_to_add_goal_with_nonexistent_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+    fn sets_goal_parent() {
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Write release notes", None, None, GoalType::Parallel)
+            .unwrap();
+        store.set_goal_parent("2", Some("1")).unwrap();
         assert_eq!(
-            add_goal(
-                &connection,
-                "Read *Network Effect*.",
-                Some("Borrow *Network Effect*."),
-            ),
-            Err("action does not exist".to_string())
+            connection
+                .query_row("SELECT parent FROM goals WHERE id = 2", [], |row| row
+                    .get::<usize, Option<i64>>(0))
+                .unwrap(),
+            Some(1)
+        );
+        store.set_goal_parent("2", None).unwrap();
+        assert_eq!(
+            connection
+                .query_row("SELECT parent FROM goals WHERE id = 2", [], |row| row
+                    .get::<usize, Option<i64>>(0))
+                .unwrap(),
+            None
         );
     }
 
     #[test]
-    fn lists_no_goals() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        let mut output = Vec::new();
-        list_goals(&connection, false, &mut output).unwrap();
-        assert_eq!(String::from_utf8(output).unwrap(), "");
-    }
-
-    #[test]
-    fn lists_goal() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute(
-                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
-                [],
-            )
+    fn fails_to_make_goal_its_own_parent() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
             .unwrap();
-        let mut output = Vec::new();
-        list_goals(&connection, false, &mut output).unwrap();
         assert_eq!(
-            String::from_utf8(output).unwrap(),
-            "Read *Network Effect*.\n"
+            store.set_goal_parent("1", Some("1")),
+            Err(Error::Constraint(
+                "goal cannot be a sub-goal of itself".into()
+            ))
         );
     }
 
     #[test]
-    fn lists_goals() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute(
-                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
-                [],
-            )
+    fn fails_to_make_goal_sub_goal_of_descendant() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
             .unwrap();
-        connection
-            .execute(
-                "INSERT INTO goals (description) VALUES('Read *What Were We Thinking*.')",
-                [],
+        store
+            .add_goal("Write release notes", None, Some("1"), GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal(
+                "Proofread release notes",
+                None,
+                Some("2"),
+                GoalType::Parallel,
             )
             .unwrap();
-        let mut output = Vec::new();
-        list_goals(&connection, false, &mut output).unwrap();
         assert_eq!(
-            String::from_utf8(output).unwrap(),
-            "Read *Network Effect*.\nRead *What Were We Thinking*.\n"
+            store.set_goal_parent("1", Some("3")),
+            Err(Error::Constraint(
+                "goal cannot be a sub-goal of itself".into()
+            ))
         );
     }
 
     #[test]
-    fn does_not_list_goal_with_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Borrow *Network Effect*.')", [])
-            .unwrap();
-        connection
-            .execute(
-                "INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Effect*.')",
-                [],
-            )
+    fn lists_goals_with_template() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        store.add_action("Tag release").unwrap();
+        store
+            .add_goal("Ship 2.0", Some("1"), None, GoalType::Sequential)
             .unwrap();
-        let mut output = Vec::new();
-        list_goals(&connection, false, &mut output).unwrap();
-        assert_eq!(String::from_utf8(output).unwrap(), "");
-    }
-
-    #[test]
-    fn lists_goal_with_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Borrow *Network Effect*.')", [])
+        store.add_goal_action("1", "2").unwrap();
+        store
+            .add_goal("Write release notes", None, Some("1"), GoalType::Parallel)
             .unwrap();
-        connection
-            .execute(
-                "INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Effect*.')",
-                [],
-            )
+        store
+            .set_config("template.goal", Some("{id:>3} {description:.8} [{action}]"))
             .unwrap();
         let mut output = Vec::new();
-        list_goals(&connection, true, &mut output).unwrap();
+        list_goals(
+            &store,
+            Status::All,
+            false,
+            &ListOptions::default(),
+            None,
+            &mut output,
+        )
+        .unwrap();
         assert_eq!(
             String::from_utf8(output).unwrap(),
-            "Read *Network Effect*.\n"
+            "  1 Ship 2.0 [Email Alice]\n  2 Write r… []\n"
         );
-    }
-
-    #[test]
-    fn removes_goal() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute(
-                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
-                [],
-            )
-            .unwrap();
-        remove_goal(&connection, "Read *Network Effect*.").unwrap();
+        let options = ListOptions {
+            template: Some(Template::parse("{id}: {goal}", TEMPLATE_FIELDS).unwrap()),
+            ..ListOptions::default()
+        };
+        let mut output = Vec::new();
+        list_actions::<&str, _>(&store, Status::All, None, &options, &mut output).unwrap();
         assert_eq!(
-            connection.query_row("SELECT * FROM goals", [], |_| Ok(())),
-            Err(Error::QueryReturnedNoRows)
+            String::from_utf8(output).unwrap(),
+            "1: Ship 2.0\n2: Ship 2.0\n"
         );
-    }
-
-    #[test]
-    fn fails_to_remove_nonexistent_goal() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+        let mut output = Vec::new();
+        list_goal_tree(&store, Status::All, &ListOptions::default(), &mut output).unwrap();
         assert_eq!(
-            remove_goal(&connection, "Read *Network Effect*."),
-            Err("goal does not exist".to_string())
+            String::from_utf8(output).unwrap(),
+            "  1 Ship 2.0 [Email Alice]\n    2 Write r… []\n"
         );
     }
 
     #[test]
-    fn sets_goal_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Borrow *Network Effect*.')", [])
-            .unwrap();
-        connection
-            .execute(
-                "INSERT INTO goals (description) VALUES('Read *Network Effect*.')",
-                [],
+    fn lists_goals_in_table() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email 王小明 about the 🎂").unwrap();
+        store
+            .add_goal(
+                "Plan the birthday party",
+                Some("1"),
+                None,
+                GoalType::Parallel,
             )
             .unwrap();
-        set_goal_action(
-            &connection,
-            "Read *Network Effect*.",
-            "Borrow *Network Effect*.",
+        store
+            .add_goal("Read 三体", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .set_goal_date("2", DateField::Due, NaiveDate::from_ymd_opt(2021, 6, 1))
+            .unwrap();
+        let mut output = Vec::new();
+        list_goals(
+            &store,
+            Status::All,
+            false,
+            &ListOptions::default(),
+            Some(40),
+            &mut output,
         )
         .unwrap();
         assert_eq!(
-            connection
-                .query_row("SELECT action FROM goals", [], |row| row
-                    .get::<usize, String>(0))
-                .unwrap(),
-            "Borrow *Network Effect*."
+            String::from_utf8(output).unwrap(),
+            "ID Goal         Action        Due\n 1 Plan the bi… Email 王小明…\n 2 Read 三体                  2021-06-01\n"
         );
     }
 
     #[test]
-    fn fails_to_set_nonexistent_goal_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+    fn lists_goal_tree() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Ship 2.0", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Write release notes", None, Some("1"), GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal(
+                "Proofread release notes",
+                None,
+                Some("2"),
+                GoalType::Parallel,
+            )
+            .unwrap();
+        store
+            .add_goal("Tag release", None, Some("1"), GoalType::Parallel)
+            .unwrap();
+        let mut output = Vec::new();
+        list_goal_tree(&store, Status::Open, &ListOptions::default(), &mut output).unwrap();
         assert_eq!(
-            set_goal_description(
-                &connection,
-                "Read *Network Effect*.",
-                "Borrow *Network Effect*."
-            ),
-            Err("goal does not exist".to_string())
+            String::from_utf8(output).unwrap(),
+            "1 Ship 2.0\n  2 Write release notes\n    4 Proofread release notes\n  5 Tag release\n3 Read *Network Effect*.\n"
         );
     }
 
     #[test]
     fn sets_goal_description() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
         connection
             .execute(
                 "INSERT INTO goals (description) VALUES('Read *Network Efect*.')",
                 [],
             )
             .unwrap();
-        set_goal_description(
-            &connection,
-            "Read *Network Efect*.",
-            "Read *Network Effect*.",
-        )
-        .unwrap();
+        store
+            .set_goal_description("Read *Network Efect*.", "Read *Network Effect*.")
+            .unwrap();
         assert_eq!(
             connection
                 .query_row("SELECT description FROM goals", [], |row| row
@@ -1317,48 +6870,41 @@ mod tests {
 
     #[test]
     fn fails_to_set_nonexistent_goal_description() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            set_goal_description(
-                &connection,
-                "Read *Network Efect*.",
-                "Read *Network Effect*."
-            ),
-            Err("goal does not exist".to_string())
+            store.set_goal_description("Read *Network Efect*.", "Read *Network Effect*."),
+            Err(Error::NotFound("goal does not exist".into()))
         );
     }
 
     #[test]
     fn unsets_goal_action() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
-        connection
-            .execute("INSERT INTO actions VALUES('Borrow *Network Effect*.')", [])
-            .unwrap();
+        let store = Store::open_in_memory().unwrap();
+        let connection = store.connection();
         connection
             .execute(
-                "INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Effect*.')",
+                "INSERT INTO actions (description) VALUES('Borrow *Network Effect*.')",
                 [],
             )
             .unwrap();
-        unset_goal_action(&connection, "Read *Network Effect*.").unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO goals (description) VALUES('Read *Network Effect*.'); INSERT INTO goal_actions VALUES(1, 1, 0);",
+            )
+            .unwrap();
+        store.unset_goal_action("Read *Network Effect*.").unwrap();
         assert_eq!(
-            connection
-                .query_row("SELECT action FROM goals", [], |row| row
-                    .get::<usize, Option<String>>(0))
-                .unwrap(),
-            None
+            connection.query_row("SELECT * FROM goal_actions", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
         );
     }
 
     #[test]
     fn fails_to_unset_action_of_nonexistent_goal() {
-        let connection = Connection::open_in_memory().unwrap();
-        crate::database::initialize(&connection).unwrap();
+        let store = Store::open_in_memory().unwrap();
         assert_eq!(
-            unset_goal_action(&connection, "Read *Network Effect*."),
-            Err("goal does not exist".to_string())
+            store.unset_goal_action("Read *Network Effect*."),
+            Err(Error::NotFound("goal does not exist".into()))
         );
     }
 }
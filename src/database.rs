@@ -13,33 +13,811 @@
 // You should have received a copy of the GNU Affero General Public License along with odo.  If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::error::Error;
+use chrono::{Duration, NaiveDateTime, Utc};
 use directories::ProjectDirs;
-use rusqlite::{config::DbConfig, Connection};
-use std::fs::DirBuilder;
+use rusqlite::{backup::Progress, config::DbConfig, params, Connection, DatabaseName, OpenFlags};
 #[cfg(all(unix, not(target_os = "macos")))]
 use std::os::unix::fs::DirBuilderExt;
+use std::{
+    fs::{self, DirBuilder, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
 
-pub fn open() -> Result<Connection, String> {
+// Which database to use: the one in the data directory, a profile's, which lives beside it, or one
+// at a given path.
+#[derive(Debug, PartialEq)]
+pub enum Location {
+    Default,
+    Profile(String),
+    Path(PathBuf),
+}
+
+pub fn open(location: &Location) -> Result<Connection, Error> {
+    let database_path = match location {
+        Location::Default => data_dir()?.join("odo.sqlite3"),
+        Location::Profile(name) => {
+            let profile_dir = data_dir()?.join("profiles");
+            create_dir(&profile_dir)?;
+            profile_dir.join(format!("{}.sqlite3", name))
+        }
+        Location::Path(path) => path.clone(),
+    };
+    Connection::open(&database_path).map_err(|e| {
+        Error::Storage(format!(
+            "unable to open `{}`: {}",
+            database_path.display(),
+            e
+        ))
+    })
+}
+
+// Returns the name and database path of each profile that has been used, starting with `default`
+// for the database that is used when no profile is given.
+pub fn profiles() -> Result<Vec<(String, PathBuf)>, Error> {
+    let data_dir = data_dir()?;
+    let mut profiles = Vec::new();
+    let default = data_dir.join("odo.sqlite3");
+    if default.is_file() {
+        profiles.push(("default".to_string(), default));
+    }
+    let profile_dir = data_dir.join("profiles");
+    let entries = match fs::read_dir(&profile_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(profiles),
+        Err(e) => {
+            return Err(Error::Storage(format!(
+                "unable to read `{}`: {}",
+                profile_dir.display(),
+                e
+            )))
+        }
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| {
+                Error::Storage(format!("unable to read `{}`: {}", profile_dir.display(), e))
+            })?
+            .path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "sqlite3")
+        {
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                names.push((name.to_string(), path.clone()));
+            }
+        }
+    }
+    names.sort();
+    profiles.extend(names);
+    Ok(profiles)
+}
+
+// How many automatic backups to keep and for how many days, where 0 means no limit.
+pub const RETENTION: &[(&str, i64)] = &[("backup.count", 10), ("backup.days", 30)];
+
+// Copies the database to `path` with SQLite's online backup API, so that the copy is consistent
+// even if another odo is writing to the database.
+pub fn backup(connection: &Connection, path: &Path) -> Result<(), Error> {
+    create_file(path)?;
+    write_backup(connection, path)
+}
+
+// Creates an empty file at `path`, failing if one exists, so that a backup never replaces another
+// file, even one created by another odo at the same time.
+fn create_file(path: &Path) -> Result<(), Error> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(Error::AlreadyExists(format!(
+            "`{}` already exists",
+            path.display()
+        ))),
+        Err(e) => Err(Error::Storage(format!(
+            "unable to create `{}`: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+// Backs the database up to the empty file at `path`, which is removed if the backup fails.
+fn write_backup(connection: &Connection, path: &Path) -> Result<(), Error> {
+    connection
+        .backup(DatabaseName::Main, path, None)
+        .map_err(|e| {
+            let _ = fs::remove_file(path);
+            Error::Storage(format!("unable to back up to `{}`: {}", path.display(), e))
+        })
+}
+
+// Backs the database up into its backup directory, which is done before migrations
+// and before commands that change many items at once, and prunes the backups there that are
+// beyond the retention settings.  Returns the path of the backup, or `None` if the database is
+// not a file.
+pub fn snapshot(connection: &Connection) -> Result<Option<PathBuf>, Error> {
+    let database_path = match database_path(connection)? {
+        Some(database_path) => database_path,
+        None => return Ok(None),
+    };
+    let backup_dir = backup_dir(&database_path);
+    create_dir(&backup_dir)?;
+    let prefix = backup_prefix(&database_path);
+    let now = Utc::now().naive_utc();
+    let path = create_backup_file(&backup_dir, &prefix, now)?;
+    write_backup(connection, &path)?;
+    prune(connection, &backup_dir, &prefix, now)?;
+    Ok(Some(path))
+}
+
+const BACKUP_TIMESTAMP: &str = "%Y%m%d-%H%M%S%.3f";
+
+// Backups are kept beside the database in a directory named after its whole file name, so that
+// those of `todo.db` and `todo.sqlite3` are kept apart and no other program's directory is used.
+fn backup_dir(database_path: &Path) -> PathBuf {
+    let mut name = database_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".odo-backups");
+    database_path.with_file_name(name)
+}
+
+// Backups are named after the database's whole file name, so that a copy moved elsewhere still
+// shows which database it came from.
+fn backup_prefix(database_path: &Path) -> String {
+    format!(
+        "{}-",
+        database_path
+            .file_name()
+            .map_or_else(Default::default, |name| name.to_string_lossy())
+    )
+}
+
+// Creates the empty file for a backup taken at `taken`.  A backup taken in the same millisecond as
+// another is named as if it were taken a millisecond later, which keeps the names in order.
+fn create_backup_file(
+    backup_dir: &Path,
+    prefix: &str,
+    mut taken: NaiveDateTime,
+) -> Result<PathBuf, Error> {
+    loop {
+        let path = backup_dir.join(format!(
+            "{}{}.sqlite3",
+            prefix,
+            taken.format(BACKUP_TIMESTAMP)
+        ));
+        match create_file(&path) {
+            Ok(()) => return Ok(path),
+            Err(Error::AlreadyExists(_)) => taken += Duration::milliseconds(1),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Removes the oldest backups beyond `backup.count` and those older than `backup.days`.  Only files
+// named like the backups of this database are considered, and the newest is always kept.
+fn prune(
+    connection: &Connection,
+    backup_dir: &Path,
+    prefix: &str,
+    now: NaiveDateTime,
+) -> Result<(), Error> {
+    let (count, days) = retention(connection)?;
+    let entries = fs::read_dir(backup_dir)
+        .map_err(|e| Error::Storage(format!("unable to read `{}`: {}", backup_dir.display(), e)))?;
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| {
+                Error::Storage(format!("unable to read `{}`: {}", backup_dir.display(), e))
+            })?
+            .path();
+        let taken = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(prefix))
+            .and_then(|name| name.strip_suffix(".sqlite3"))
+            .and_then(|timestamp| NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP).ok());
+        if let Some(taken) = taken {
+            backups.push((taken, path));
+        }
+    }
+    backups.sort_by(|a, b| b.cmp(a));
+    for (index, (taken, path)) in backups.iter().enumerate().skip(1) {
+        if (count > 0 && index >= count as usize)
+            || (days > 0 && now - *taken > Duration::days(days))
+        {
+            fs::remove_file(path).map_err(|e| {
+                Error::Storage(format!("unable to remove `{}`: {}", path.display(), e))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+// The retention settings, which take their defaults until the configuration table exists.
+fn retention(connection: &Connection) -> Result<(i64, i64), Error> {
+    let configured = connection
+        .query_row(
+            "SELECT EXISTS (SELECT * FROM sqlite_master WHERE type = 'table' AND name = 'config')",
+            [],
+            |row| row.get::<usize, bool>(0),
+        )
+        .map_err(|e| Error::Storage(format!("unable to read schema: {}", e)))?;
+    let mut values = Vec::new();
+    for (name, default) in RETENTION {
+        let value = if configured {
+            crate::store::config_value(connection, name)?
+        } else {
+            None
+        };
+        values.push(
+            value
+                .map(|value| {
+                    value.parse::<i64>().map_err(|_| {
+                        Error::Storage(format!("invalid value for `{}`: `{}`", name, value))
+                    })
+                })
+                .transpose()?
+                .unwrap_or(*default),
+        );
+    }
+    Ok((values[0], values[1]))
+}
+
+// Replaces the database with the backup at `path`, after checking that it is an odo database
+// that this version can use and backing up the database it replaces.  Older backups are migrated.
+pub fn restore(connection: &Connection, path: &Path) -> Result<(), Error> {
+    let source = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| Error::Storage(format!("unable to open `{}`: {}", path.display(), e)))?;
+    let check = source
+        .query_row("PRAGMA quick_check", [], |row| row.get::<usize, String>(0))
+        .map_err(|e| Error::Storage(format!("unable to check `{}`: {}", path.display(), e)))?;
+    if check != "ok" {
+        return Err(Error::Storage(format!(
+            "`{}` is corrupt: {}",
+            path.display(),
+            check
+        )));
+    }
+    let version = version(&source)?;
+    let has_actions = source
+        .query_row(
+            "SELECT EXISTS (SELECT * FROM sqlite_master WHERE type = 'table' AND name = 'actions')",
+            [],
+            |row| row.get::<usize, bool>(0),
+        )
+        .map_err(|e| Error::Storage(format!("unable to read schema: {}", e)))?;
+    if !has_actions {
+        return Err(Error::Storage(format!(
+            "`{}` is not an odo database",
+            path.display()
+        )));
+    }
+    if version > MIGRATIONS.len() {
+        return Err(Error::Storage(format!(
+            "backup version {} is newer than supported version {}",
+            version,
+            MIGRATIONS.len()
+        )));
+    }
+    drop(source);
+    let database_path = database_path(connection)?
+        .ok_or_else(|| Error::Storage("unable to restore a database that is not a file".into()))?;
+    // The database being replaced may be too damaged for SQLite to read, in which case its file is
+    // kept as it is.
+    let readable = connection
+        .query_row("PRAGMA quick_check", [], |row| row.get::<usize, String>(0))
+        .is_ok_and(|check| check == "ok");
+    if readable {
+        snapshot(connection)?;
+    } else {
+        let backup_dir = backup_dir(&database_path);
+        create_dir(&backup_dir)?;
+        let path = create_backup_file(
+            &backup_dir,
+            &backup_prefix(&database_path),
+            Utc::now().naive_utc(),
+        )?;
+        fs::copy(&database_path, &path).map_err(|e| {
+            Error::Storage(format!(
+                "unable to copy `{}` to `{}`: {}",
+                database_path.display(),
+                path.display(),
+                e
+            ))
+        })?;
+        // SQLite cannot restore over a file that is not a database, but it can over an empty one.
+        fs::write(&database_path, "").map_err(|e| {
+            Error::Storage(format!(
+                "unable to empty `{}`: {}",
+                database_path.display(),
+                e
+            ))
+        })?;
+    }
+    let mut destination = Connection::open(&database_path).map_err(|e| {
+        Error::Storage(format!(
+            "unable to open `{}`: {}",
+            database_path.display(),
+            e
+        ))
+    })?;
+    destination
+        .restore(DatabaseName::Main, path, None::<fn(Progress)>)
+        .map_err(|e| Error::Storage(format!("unable to restore `{}`: {}", path.display(), e)))?;
+    drop(destination);
+    initialize(connection)
+}
+
+// The path of the database's file, or `None` if it is in memory.
+fn database_path(connection: &Connection) -> Result<Option<PathBuf>, Error> {
+    // The `database_list` pragma, unlike the table-valued function, does not read the schema, so
+    // it works on a database that is too damaged to read.
+    let file = connection
+        .pragma_query_value(None, "database_list", |row| row.get::<usize, String>(2))
+        .map_err(|e| Error::Storage(format!("unable to look up database file: {}", e)))?;
+    Ok(if file.is_empty() {
+        None
+    } else {
+        Some(file.into())
+    })
+}
+
+fn data_dir() -> Result<PathBuf, Error> {
     let project_dirs = ProjectDirs::from("org.ftbfs", "", "odo")
-        .ok_or("unable to determine project directories")?;
+        .ok_or_else(|| Error::Storage("unable to determine project directories".into()))?;
     let data_dir = project_dirs.data_dir();
+    create_dir(data_dir)?;
+    Ok(data_dir.to_path_buf())
+}
+
+fn create_dir(dir: &Path) -> Result<(), Error> {
     let mut builder = DirBuilder::new();
     #[cfg(all(unix, not(target_os = "macos")))]
     builder.mode(0o700);
     builder
         .recursive(true)
-        .create(data_dir)
-        .map_err(|e| format!("unable to create `{}`: {}", data_dir.display(), e))?;
-    let database_path = data_dir.join("odo.sqlite3");
-    Connection::open(&database_path)
-        .map_err(|e| format!("unable to open `{}`: {}", database_path.display(), e))
+        .create(dir)
+        .map_err(|e| Error::Storage(format!("unable to create `{}`: {}", dir.display(), e)))
 }
 
-pub fn initialize(connection: &Connection) -> Result<(), String> {
+// Each migration upgrades the schema by one version.  `PRAGMA user_version` records how many have
+// been applied, so a migration must never be changed once released; append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/001-initialize.sql"),
+    include_str!("migrations/002-ids.sql"),
+    include_str!("migrations/003-done.sql"),
+    include_str!("migrations/004-parents.sql"),
+    include_str!("migrations/005-goal-actions.sql"),
+    include_str!("migrations/006-sequential.sql"),
+    include_str!("migrations/007-contexts.sql"),
+    include_str!("migrations/008-dates.sql"),
+    include_str!("migrations/009-recurrence.sql"),
+    include_str!("migrations/010-urgency.sql"),
+    include_str!("migrations/011-tags.sql"),
+    include_str!("migrations/012-notes.sql"),
+    include_str!("migrations/013-search.sql"),
+    include_str!("migrations/014-views.sql"),
+    include_str!("migrations/015-dropped-goal-actions.sql"),
+];
+
+pub fn initialize(connection: &Connection) -> Result<(), Error> {
+    migrate(connection)?;
     connection
         .set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, true)
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| Error::Storage(e.to_string()))?;
+    crate::urgency::register(connection)
+}
+
+fn migrate(connection: &Connection) -> Result<(), Error> {
+    let version = version(connection)?;
+    if version > MIGRATIONS.len() {
+        return Err(Error::Storage(format!(
+            "database version {} is newer than supported version {}",
+            version,
+            MIGRATIONS.len()
+        )));
+    }
+    if version == MIGRATIONS.len() {
+        return Ok(());
+    }
+    let empty = !connection
+        .query_row("SELECT EXISTS (SELECT * FROM sqlite_master)", [], |row| {
+            row.get::<usize, bool>(0)
+        })
+        .map_err(|e| Error::Storage(format!("unable to read schema: {}", e)))?;
+    if !empty {
+        snapshot(connection)?;
+    }
+    // Migrations may need to rebuild tables, which SQLite only allows while foreign key
+    // enforcement is off.  Violations are checked explicitly before committing instead.  Ones the
+    // database already had are left for `doctor` to repair, so only new ones are refused.
+    connection
+        .set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, false)
+        .map_err(|e| Error::Storage(e.to_string()))?;
+    let transaction = connection
+        .unchecked_transaction()
+        .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+    let violations = foreign_key_violations(&transaction)?;
+    // Migration 002 drops links from goals to actions that do not exist.  They are recorded once
+    // the schema has a place for them, so that `doctor` can report them.
+    let dropped = if version < 2 && !empty {
+        dangling_goal_actions(&transaction)?
+    } else {
+        Vec::new()
+    };
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        transaction.execute_batch(migration).map_err(|e| {
+            Error::Storage(format!("unable to migrate to version {}: {}", index + 1, e))
+        })?;
+    }
+    for (goal, action) in dropped {
+        transaction
+            .execute(
+                "INSERT INTO dropped_goal_actions VALUES (?, ?)",
+                params![goal, action],
+            )
+            .map_err(|e| Error::Storage(format!("unable to record dropped link: {}", e)))?;
+    }
+    if foreign_key_violations(&transaction)? > violations {
+        return Err(Error::Storage(
+            "migration violates foreign key constraints".into(),
+        ));
+    }
+    transaction
+        .pragma_update(None, "user_version", &(MIGRATIONS.len() as i64))
+        .map_err(|e| Error::Storage(format!("unable to set database version: {}", e)))?;
+    transaction
+        .commit()
+        .map_err(|e| Error::Storage(format!("unable to commit migration: {}", e)))
+}
+
+fn foreign_key_violations(connection: &Connection) -> Result<usize, Error> {
     connection
-        .execute_batch(include_str!("initialize.sql"))
-        .map_err(|e| e.to_string())
+        .prepare("PRAGMA foreign_key_check")
+        .and_then(|mut statement| {
+            statement
+                .query_map([], |_| Ok(()))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map(|violations| violations.len())
+        .map_err(|e| Error::Storage(format!("unable to check foreign keys: {}", e)))
+}
+
+// Reads the goals whose action does not exist, in the schema before migration 002.
+fn dangling_goal_actions(connection: &Connection) -> Result<Vec<(String, String)>, Error> {
+    connection
+        .prepare(
+            "SELECT description, action FROM goals WHERE description IS NOT NULL AND action IS \
+             NOT NULL AND action NOT IN (SELECT description FROM actions WHERE description IS NOT \
+             NULL) ORDER BY rowid",
+        )
+        .and_then(|mut statement| {
+            statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect()
+        })
+        .map_err(|e| Error::Storage(format!("unable to read goal actions: {}", e)))
+}
+
+fn version(connection: &Connection) -> Result<usize, Error> {
+    connection
+        .pragma_query_value(None, "user_version", |row| row.get::<usize, i64>(0))
+        .map(|version| version as usize)
+        .map_err(|e| Error::Storage(format!("unable to read database version: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSION_0: &str = "CREATE TABLE IF NOT EXISTS actions (description PRIMARY KEY);
+CREATE TABLE IF NOT EXISTS goals (description PRIMARY KEY, action TEXT REFERENCES actions (description) ON DELETE SET NULL ON UPDATE CASCADE);";
+
+    #[test]
+    fn initializes_empty_database() {
+        let connection = Connection::open_in_memory().unwrap();
+        initialize(&connection).unwrap();
+        assert_eq!(version(&connection), Ok(MIGRATIONS.len()));
+    }
+
+    #[test]
+    fn initializes_database_idempotently() {
+        let connection = Connection::open_in_memory().unwrap();
+        initialize(&connection).unwrap();
+        initialize(&connection).unwrap();
+        assert_eq!(version(&connection), Ok(MIGRATIONS.len()));
+    }
+
+    #[test]
+    fn upgrades_version_0_database() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(VERSION_0).unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO actions VALUES('Borrow *Network Effect*.');
+                 INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Effect*.');",
+            )
+            .unwrap();
+        initialize(&connection).unwrap();
+        assert_eq!(version(&connection), Ok(MIGRATIONS.len()));
+        assert_eq!(
+            connection
+                .query_row("SELECT description FROM actions", [], |row| row
+                    .get::<usize, String>(0))
+                .unwrap(),
+            "Borrow *Network Effect*."
+        );
+        assert_eq!(
+            connection
+                .query_row(
+                    "SELECT goals.description, actions.description FROM goals JOIN goal_actions ON goal_actions.goal = goals.id JOIN actions ON actions.id = goal_actions.action",
+                    [],
+                    |row| Ok((row.get_unwrap::<usize, String>(0), row.get_unwrap::<usize, String>(1)))
+                )
+                .unwrap(),
+            (
+                "Read *Network Effect*.".to_string(),
+                "Borrow *Network Effect*.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn upgrading_version_0_database_records_dangling_goal_action() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(VERSION_0).unwrap();
+        connection
+            .execute(
+                "INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Effect*.')",
+                [],
+            )
+            .unwrap();
+        initialize(&connection).unwrap();
+        assert_eq!(
+            connection.query_row("SELECT * FROM goal_actions", [], |_| Ok(())),
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        );
+        assert_eq!(
+            connection.query_row("SELECT goal, action FROM dropped_goal_actions", [], |row| {
+                Ok((row.get::<usize, String>(0)?, row.get::<usize, String>(1)?))
+            }),
+            Ok((
+                "Read *Network Effect*.".into(),
+                "Borrow *Network Effect*.".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn upgrades_database_with_dangling_links() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, false)
+            .unwrap();
+        for migration in &MIGRATIONS[..MIGRATIONS.len() - 1] {
+            connection.execute_batch(migration).unwrap();
+        }
+        connection
+            .pragma_update(None, "user_version", &(MIGRATIONS.len() as i64 - 1))
+            .unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO goals (id, description) VALUES(1, 'Ship 2.0');
+                 INSERT INTO goal_actions VALUES(1, 7, 0);",
+            )
+            .unwrap();
+        initialize(&connection).unwrap();
+        assert_eq!(version(&connection), Ok(MIGRATIONS.len()));
+        assert_eq!(foreign_key_violations(&connection), Ok(1));
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "user_version", &(MIGRATIONS.len() as i64 + 1))
+            .unwrap();
+        assert_eq!(
+            initialize(&connection),
+            Err(Error::Storage(format!(
+                "database version {} is newer than supported version {}",
+                MIGRATIONS.len() + 1,
+                MIGRATIONS.len()
+            )))
+        );
+    }
+
+    fn backups(database_path: &Path) -> Vec<String> {
+        let mut backups = fs::read_dir(backup_dir(database_path))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        backups.sort();
+        backups
+    }
+
+    #[test]
+    fn backs_up_before_migrating() {
+        let dir = tempfile::tempdir().unwrap();
+        let connection = Connection::open(dir.path().join("odo.sqlite3")).unwrap();
+        initialize(&connection).unwrap();
+        assert!(!backup_dir(&dir.path().join("odo.sqlite3")).exists());
+        let legacy_path = dir.path().join("legacy.sqlite3");
+        let connection = Connection::open(&legacy_path).unwrap();
+        connection.execute_batch(VERSION_0).unwrap();
+        initialize(&connection).unwrap();
+        assert_eq!(
+            backup_dir(&legacy_path),
+            dir.path().join("legacy.sqlite3.odo-backups")
+        );
+        let backups = backups(&legacy_path);
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].starts_with("legacy.sqlite3-"));
+        let backup = Connection::open(backup_dir(&legacy_path).join(&backups[0])).unwrap();
+        assert_eq!(version(&backup), Ok(0));
+    }
+
+    #[test]
+    fn prunes_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let database_path = dir.path().join("odo.sqlite3");
+        let connection = Connection::open(&database_path).unwrap();
+        initialize(&connection).unwrap();
+        fs::create_dir(backup_dir(&database_path)).unwrap();
+        let now = Utc::now().naive_utc();
+        for (days, name) in [
+            (1, "odo.sqlite3-"),
+            (2, "odo.sqlite3-"),
+            (3, "odo.sqlite3-"),
+            (60, "odo.sqlite3-"),
+            (60, "odo.db-"),
+        ] {
+            let taken = (now - Duration::days(days)).format(BACKUP_TIMESTAMP);
+            fs::write(
+                backup_dir(&database_path).join(format!("{}{}.sqlite3", name, taken)),
+                "",
+            )
+            .unwrap();
+        }
+        snapshot(&connection).unwrap();
+        assert_eq!(backups(&database_path).len(), 5);
+        connection
+            .execute("INSERT INTO config VALUES('backup.count', '3')", [])
+            .unwrap();
+        snapshot(&connection).unwrap();
+        let backups = backups(&database_path);
+        assert_eq!(backups.len(), 4);
+        assert!(backups[0].starts_with("odo.db-"));
+    }
+
+    #[test]
+    fn names_backups_taken_at_same_time_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let taken = NaiveDateTime::parse_from_str("20211016-120000.999", BACKUP_TIMESTAMP).unwrap();
+        let first = create_backup_file(dir.path(), "odo.sqlite3-", taken).unwrap();
+        let second = create_backup_file(dir.path(), "odo.sqlite3-", taken).unwrap();
+        assert_eq!(
+            first,
+            dir.path().join("odo.sqlite3-20211016-120000.999.sqlite3")
+        );
+        assert_eq!(
+            second,
+            dir.path().join("odo.sqlite3-20211016-120001.000.sqlite3")
+        );
+    }
+
+    #[test]
+    fn restores_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let database_path = dir.path().join("odo.sqlite3");
+        let connection = Connection::open(&database_path).unwrap();
+        initialize(&connection).unwrap();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Email Alice')",
+                [],
+            )
+            .unwrap();
+        let backup_path = dir.path().join("backup.sqlite3");
+        backup(&connection, &backup_path).unwrap();
+        assert_eq!(
+            backup(&connection, &backup_path),
+            Err(Error::AlreadyExists(format!(
+                "`{}` already exists",
+                backup_path.display()
+            )))
+        );
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Water plants')",
+                [],
+            )
+            .unwrap();
+        restore(&connection, &backup_path).unwrap();
+        assert_eq!(
+            connection.query_row("SELECT count(*) FROM actions", [], |row| row
+                .get::<usize, i64>(0)),
+            Ok(1)
+        );
+        let backups = backups(&database_path);
+        assert_eq!(backups.len(), 1);
+        let replaced = Connection::open(backup_dir(&database_path).join(&backups[0])).unwrap();
+        assert_eq!(
+            replaced.query_row("SELECT count(*) FROM actions", [], |row| row
+                .get::<usize, i64>(0)),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn restores_over_unreadable_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_path = dir.path().join("backup.sqlite3");
+        let connection = Connection::open(&backup_path).unwrap();
+        initialize(&connection).unwrap();
+        connection
+            .execute(
+                "INSERT INTO actions (description) VALUES('Email Alice')",
+                [],
+            )
+            .unwrap();
+        drop(connection);
+        let database_path = dir.path().join("odo.sqlite3");
+        fs::write(&database_path, "not a database").unwrap();
+        let connection = Connection::open(&database_path).unwrap();
+        assert!(initialize(&connection).is_err());
+        restore(&connection, &backup_path).unwrap();
+        assert_eq!(
+            connection.query_row("SELECT description FROM actions", [], |row| row
+                .get::<usize, String>(0)),
+            Ok("Email Alice".into())
+        );
+        let backups = backups(&database_path);
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            fs::read(backup_dir(&database_path).join(&backups[0])).unwrap(),
+            b"not a database"
+        );
+    }
+
+    #[test]
+    fn refuses_to_restore_invalid_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let connection = Connection::open(dir.path().join("odo.sqlite3")).unwrap();
+        initialize(&connection).unwrap();
+        let other_path = dir.path().join("other.sqlite3");
+        let other = Connection::open(&other_path).unwrap();
+        other.execute_batch("CREATE TABLE notes (text)").unwrap();
+        assert_eq!(
+            restore(&connection, &other_path),
+            Err(Error::Storage(format!(
+                "`{}` is not an odo database",
+                other_path.display()
+            )))
+        );
+        other.execute_batch(VERSION_0).unwrap();
+        other
+            .pragma_update(None, "user_version", &(MIGRATIONS.len() as i64 + 1))
+            .unwrap();
+        assert_eq!(
+            restore(&connection, &other_path),
+            Err(Error::Storage(format!(
+                "backup version {} is newer than supported version {}",
+                MIGRATIONS.len() + 1,
+                MIGRATIONS.len()
+            )))
+        );
+        assert!(!backup_dir(&dir.path().join("odo.sqlite3")).exists());
+    }
+
+    #[test]
+    fn enables_foreign_keys() {
+        let connection = Connection::open_in_memory().unwrap();
+        initialize(&connection).unwrap();
+        assert_eq!(
+            connection.pragma_query_value(None, "foreign_keys", |row| row.get::<usize, bool>(0)),
+            Ok(true)
+        );
+    }
 }
@@ -0,0 +1,373 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use rusqlite::Connection;
+
+/// A problem that `odo doctor` found, and what `odo doctor --fix` does about it, if anything.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    description: String,
+    fix: Option<Fix>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Fix {
+    // Removes a row whose link cannot be empty, such as a goal's link to an action.
+    Delete {
+        table: String,
+        rowid: i64,
+    },
+    // Empties a link that may be empty, such as a goal's parent.
+    Unlink {
+        table: String,
+        column: String,
+        rowid: i64,
+    },
+    Trim {
+        table: &'static str,
+        id: i64,
+        description: String,
+    },
+}
+
+impl Problem {
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn is_fixable(&self) -> bool {
+        self.fix.is_some()
+    }
+
+    // Returns whether the problem was fixed, which it cannot be if it has no fix or if trimming a
+    // description would duplicate that of another unfinished item.
+    pub(crate) fn fix(&self, connection: &Connection) -> Result<bool, Error> {
+        let result = match &self.fix {
+            None => return Ok(false),
+            Some(Fix::Delete { table, rowid }) => connection.execute(
+                &format!("DELETE FROM \"{}\" WHERE rowid = ?1", table),
+                rusqlite::params![rowid],
+            ),
+            Some(Fix::Unlink {
+                table,
+                column,
+                rowid,
+            }) => connection.execute(
+                &format!(
+                    "UPDATE \"{}\" SET \"{}\" = NULL WHERE rowid = ?1",
+                    table, column
+                ),
+                rusqlite::params![rowid],
+            ),
+            Some(Fix::Trim {
+                table,
+                id,
+                description,
+            }) => connection.execute(
+                &format!("UPDATE {} SET description = ?2 WHERE id = ?1", table),
+                rusqlite::params![id, description.trim()],
+            ),
+        };
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) if crate::store::is_constraint_violation(&e) => Ok(false),
+            Err(e) => Err(Error::Storage(format!("unable to fix problem: {}", e))),
+        }
+    }
+}
+
+pub fn check(connection: &Connection) -> Result<Vec<Problem>, Error> {
+    let mut problems = Vec::new();
+    check_integrity(connection, &mut problems)?;
+    check_links(connection, &mut problems)?;
+    check_dropped_goal_actions(connection, &mut problems)?;
+    for (item, table) in [("action", "actions"), ("goal", "goals")] {
+        check_descriptions(connection, item, table, &mut problems)?;
+    }
+    Ok(problems)
+}
+
+fn check_integrity(connection: &Connection, problems: &mut Vec<Problem>) -> Result<(), Error> {
+    let mut statement = connection
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let messages = statement
+        .query_map([], |row| row.get::<usize, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to check integrity: {}", e)))?;
+    problems.extend(
+        messages
+            .into_iter()
+            .filter(|message| message != "ok")
+            .map(|message| Problem {
+                description: format!("integrity check failed: {}", message),
+                fix: None,
+            }),
+    );
+    Ok(())
+}
+
+// Links to missing rows can exist in databases written while foreign keys were not enforced.
+fn check_links(connection: &Connection, problems: &mut Vec<Problem>) -> Result<(), Error> {
+    let mut statement = connection
+        .prepare("PRAGMA foreign_key_check")
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let violations = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<usize, String>(0)?,
+                row.get::<usize, Option<i64>>(1)?,
+                row.get::<usize, String>(2)?,
+                row.get::<usize, i64>(3)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to check foreign keys: {}", e)))?;
+    for (table, rowid, parent, key) in violations {
+        let rowid = match rowid {
+            Some(rowid) => rowid,
+            None => {
+                problems.push(Problem {
+                    description: format!("`{}` links to a missing `{}` row", table, parent),
+                    fix: None,
+                });
+                continue;
+            }
+        };
+        let (column, required) = connection
+            .query_row(
+                "SELECT foreign_keys.\"from\", columns.\"notnull\" FROM pragma_foreign_key_list(?1) AS foreign_keys JOIN pragma_table_info(?1) AS columns ON columns.name = foreign_keys.\"from\" WHERE foreign_keys.id = ?2",
+                rusqlite::params![table, key],
+                |row| Ok((row.get::<usize, String>(0)?, row.get::<usize, bool>(1)?)),
+            )
+            .map_err(|e| Error::Storage(format!("unable to read foreign key: {}", e)))?;
+        let value = connection
+            .query_row(
+                &format!("SELECT \"{}\" FROM \"{}\" WHERE rowid = ?1", column, table),
+                rusqlite::params![rowid],
+                |row| row.get::<usize, i64>(0),
+            )
+            .map_err(|e| Error::Storage(format!("unable to read link: {}", e)))?;
+        problems.push(Problem {
+            description: format!(
+                "`{}` row {} links to missing `{}` row {} through `{}`",
+                table, rowid, parent, value, column
+            ),
+            fix: Some(if required {
+                Fix::Delete { table, rowid }
+            } else {
+                Fix::Unlink {
+                    table,
+                    column,
+                    rowid,
+                }
+            }),
+        });
+    }
+    Ok(())
+}
+
+// Upgrading a database written before foreign keys were enforced empties a goal's link to a missing
+// action, recording the link so that it can be reported here.  Fixing it forgets the record.
+fn check_dropped_goal_actions(
+    connection: &Connection,
+    problems: &mut Vec<Problem>,
+) -> Result<(), Error> {
+    let mut statement = connection
+        .prepare("SELECT rowid, goal, action FROM dropped_goal_actions ORDER BY rowid")
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let links = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<usize, i64>(0)?,
+                row.get::<usize, String>(1)?,
+                row.get::<usize, String>(2)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to read dropped links: {}", e)))?;
+    for (rowid, goal, action) in links {
+        problems.push(Problem {
+            description: format!(
+                "goal `{}` linked to missing action `{}`, which was unlinked when upgrading",
+                goal, action
+            ),
+            fix: Some(Fix::Delete {
+                table: "dropped_goal_actions".into(),
+                rowid,
+            }),
+        });
+    }
+    Ok(())
+}
+
+fn check_descriptions(
+    connection: &Connection,
+    item: &str,
+    table: &'static str,
+    problems: &mut Vec<Problem>,
+) -> Result<(), Error> {
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT id, description FROM {} ORDER BY id",
+            table
+        ))
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let items = statement
+        .query_map([], |row| {
+            Ok((row.get::<usize, i64>(0)?, row.get::<usize, String>(1)?))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to read descriptions: {}", e)))?;
+    for (id, description) in items {
+        if description.trim().is_empty() {
+            problems.push(Problem {
+                description: format!("{} {} has an empty description", item, id),
+                fix: None,
+            });
+        } else if description.trim() != description {
+            problems.push(Problem {
+                description: format!("{} {} has whitespace around its description", item, id),
+                fix: Some(Fix::Trim {
+                    table,
+                    id,
+                    description,
+                }),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::config::DbConfig;
+
+    fn connection() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::initialize(&connection).unwrap();
+        connection
+            .set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, false)
+            .unwrap();
+        connection
+    }
+
+    const VERSION_0: &str = "CREATE TABLE actions (description PRIMARY KEY);
+CREATE TABLE goals (description PRIMARY KEY, action TEXT REFERENCES actions (description) ON DELETE SET NULL ON UPDATE CASCADE);";
+
+    fn descriptions(problems: &[Problem]) -> Vec<&str> {
+        problems.iter().map(Problem::description).collect()
+    }
+
+    #[test]
+    fn finds_no_problems() {
+        assert_eq!(check(&connection()), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn fixes_dangling_links() {
+        let connection = connection();
+        connection
+            .execute_batch(
+                "INSERT INTO goals (id, description, parent) VALUES(1, 'Ship 2.0', 9);
+                 INSERT INTO goal_actions VALUES(1, 7, 0);",
+            )
+            .unwrap();
+        let problems = check(&connection).unwrap();
+        assert_eq!(
+            descriptions(&problems),
+            [
+                "`goals` row 1 links to missing `goals` row 9 through `parent`",
+                "`goal_actions` row 1 links to missing `actions` row 7 through `action`",
+            ]
+        );
+        for problem in &problems {
+            assert_eq!(problem.fix(&connection), Ok(true));
+        }
+        assert_eq!(check(&connection), Ok(Vec::new()));
+        assert_eq!(
+            connection.query_row("SELECT count(*) FROM goal_actions", [], |row| row
+                .get::<usize, i64>(0)),
+            Ok(0)
+        );
+        assert_eq!(
+            connection.query_row("SELECT parent FROM goals", [], |row| row
+                .get::<usize, Option<i64>>(0)),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn reports_goal_actions_dropped_when_upgrading() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(VERSION_0).unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO actions VALUES('Email Alice');
+                 INSERT INTO goals VALUES('Ship 2.0', 'Email Alice');
+                 INSERT INTO goals VALUES('Read *Network Effect*.', 'Borrow *Network Effect*.');",
+            )
+            .unwrap();
+        crate::database::initialize(&connection).unwrap();
+        let problems = check(&connection).unwrap();
+        assert_eq!(
+            descriptions(&problems),
+            ["goal `Read *Network Effect*.` linked to missing action `Borrow *Network Effect*.`, which was unlinked when upgrading"]
+        );
+        assert_eq!(problems[0].fix(&connection), Ok(true));
+        assert_eq!(check(&connection), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn fixes_descriptions() {
+        let connection = connection();
+        connection
+            .execute_batch(
+                "INSERT INTO actions (description) VALUES('  ');
+                 INSERT INTO actions (description) VALUES(' Email Alice\n');
+                 INSERT INTO goals (description) VALUES('Ship 2.0');
+                 INSERT INTO goals (description) VALUES('Ship 2.0 ');",
+            )
+            .unwrap();
+        let problems = check(&connection).unwrap();
+        assert_eq!(
+            descriptions(&problems),
+            [
+                "action 1 has an empty description",
+                "action 2 has whitespace around its description",
+                "goal 2 has whitespace around its description",
+            ]
+        );
+        assert_eq!(
+            problems.iter().map(Problem::is_fixable).collect::<Vec<_>>(),
+            [false, true, true]
+        );
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| problem.fix(&connection))
+                .collect::<Vec<_>>(),
+            [Ok(false), Ok(true), Ok(false)]
+        );
+        assert_eq!(
+            connection.query_row("SELECT description FROM actions WHERE id = 2", [], |row| {
+                row.get::<usize, String>(0)
+            }),
+            Ok("Email Alice".into())
+        );
+    }
+}
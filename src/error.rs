@@ -0,0 +1,58 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use std::fmt::{self, Display, Formatter};
+
+/// Why a command failed.  Each kind has its own exit status, which odo(1) documents, so that
+/// scripts can tell a mistyped description from a broken database.
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The arguments, or a value given in them, are invalid.
+    Usage(String),
+    /// An action, goal, context, tag, or view named in the arguments does not exist.
+    NotFound(String),
+    /// Adding or renaming an item would duplicate one that already exists.
+    AlreadyExists(String),
+    /// The change is not allowed in the items' current state, such as finishing an action twice.
+    Constraint(String),
+    /// The database or another file could not be read or written, or is not as expected.
+    Storage(String),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Storage(_) => 1,
+            Self::Usage(_) => 2,
+            Self::NotFound(_) => 3,
+            Self::AlreadyExists(_) => 4,
+            Self::Constraint(_) => 5,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Usage(message)
+            | Self::NotFound(message)
+            | Self::AlreadyExists(message)
+            | Self::Constraint(message)
+            | Self::Storage(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
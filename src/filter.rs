@@ -0,0 +1,532 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    command::{context_name, parse_date, parse_priority, tag_name, today},
+    store::Item,
+};
+use chrono::NaiveDate;
+use rusqlite::types::Value;
+use std::fmt;
+
+// A filter such as `tag:work and due<fri and not context:@phone`, compiled to a SQL condition on the
+// item's table.  Values are bound as parameters rather than written into the SQL.
+#[derive(Debug, PartialEq)]
+pub struct Filter {
+    sql: String,
+    params: Vec<(String, Value)>,
+}
+
+impl Filter {
+    pub fn parse(text: &str, item: Item) -> Result<Self, String> {
+        Self::parse_at(text, item, today())
+    }
+
+    // Dates in the filter are relative to `today`.
+    fn parse_at(text: &str, item: Item, today: NaiveDate) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(text).map_err(error)?,
+            position: 0,
+            end: text.chars().count() + 1,
+            item,
+            today,
+            params: Vec::new(),
+        };
+        let sql = parser.parse_or().map_err(error)?;
+        match parser.tokens.get(parser.position) {
+            Some((column, Token::Close)) => Err(error((*column, "unexpected `)`".into()))),
+            Some((column, _)) => Err(error((*column, "expected `and` or `or`".into()))),
+            None => Ok(Self {
+                sql,
+                params: parser.params,
+            }),
+        }
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn params(&self) -> &[(String, Value)] {
+        &self.params
+    }
+}
+
+// Errors carry the column, counting characters from 1, where the problem was found.
+type ParseError = (usize, String);
+
+fn error((column, message): ParseError) -> String {
+    format!("invalid filter at column {}: {}", column, message)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Is,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Contains,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Is => ":",
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::Contains => "~",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Operator(Operator),
+    Word(String),
+    Quoted(String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let column = index + 1;
+        let token = match chars[index] {
+            c if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ':' => Token::Operator(Operator::Is),
+            '=' => Token::Operator(Operator::Equal),
+            '~' => Token::Operator(Operator::Contains),
+            '<' | '>' | '!' => {
+                let equal = chars.get(index + 1) == Some(&'=');
+                let operator = match (chars[index], equal) {
+                    ('<', false) => Operator::Less,
+                    ('<', true) => Operator::LessEqual,
+                    ('>', false) => Operator::Greater,
+                    ('>', true) => Operator::GreaterEqual,
+                    ('!', true) => Operator::NotEqual,
+                    _ => return Err((column, "expected `=` after `!`".into())),
+                };
+                if equal {
+                    index += 1;
+                }
+                Token::Operator(operator)
+            }
+            quote @ ('"' | '\'') => {
+                let length = chars[index + 1..]
+                    .iter()
+                    .position(|&c| c == quote)
+                    .ok_or_else(|| (column, "unterminated string".to_string()))?;
+                let value = chars[index + 1..index + 1 + length].iter().collect();
+                index += length + 1;
+                Token::Quoted(value)
+            }
+            _ => {
+                let length = chars[index..]
+                    .iter()
+                    .position(|&c| c.is_whitespace() || "()<>=!:~".contains(c))
+                    .unwrap_or(chars.len() - index);
+                let word = chars[index..index + length].iter().collect();
+                index += length - 1;
+                Token::Word(word)
+            }
+        };
+        tokens.push((column, token));
+        index += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end: usize,
+    item: Item,
+    today: NaiveDate,
+    params: Vec<(String, Value)>,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(column, _)| *column)
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some((_, Token::Word(word))) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<String, ParseError> {
+        let mut sql = self.parse_and()?;
+        while self.keyword("or") {
+            sql = format!("({} OR {})", sql, self.parse_and()?);
+        }
+        Ok(sql)
+    }
+
+    // Terms written one after another without `and` must all match, as if `and` joined them.
+    fn parse_and(&mut self) -> Result<String, ParseError> {
+        let mut sql = self.parse_not()?;
+        loop {
+            let implicit = match self.tokens.get(self.position) {
+                Some((_, Token::Close)) | None => false,
+                Some((_, Token::Word(word))) => !word.eq_ignore_ascii_case("or"),
+                Some(_) => true,
+            };
+            if !self.keyword("and") && !implicit {
+                return Ok(sql);
+            }
+            sql = format!("{} AND {}", sql, self.parse_not()?);
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<String, ParseError> {
+        if self.keyword("not") {
+            Ok(format!("NOT {}", self.parse_not()?))
+        } else {
+            self.parse_primary()
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<String, ParseError> {
+        let column = self.column();
+        match self.next() {
+            Some((_, Token::Open)) => {
+                let sql = self.parse_or()?;
+                let column = self.column();
+                match self.next() {
+                    Some((_, Token::Close)) => Ok(format!("({})", sql)),
+                    _ => Err((column, "expected `)`".into())),
+                }
+            }
+            Some((_, Token::Close)) => Err((column, "unexpected `)`".into())),
+            Some((_, Token::Word(field))) => {
+                let operator_column = self.column();
+                let operator = match self.next() {
+                    Some((_, Token::Operator(operator))) => operator,
+                    _ => {
+                        return Err((
+                            operator_column,
+                            format!("expected an operator after `{}`", field),
+                        ))
+                    }
+                };
+                let value_column = self.column();
+                let value = match self.next() {
+                    Some((_, Token::Word(value))) | Some((_, Token::Quoted(value))) => value,
+                    _ => return Err((value_column, "expected a value".into())),
+                };
+                // Comparisons with a field that is not set are false rather than null, so that
+                // `not` selects the items the field is not set on.
+                let sql = self
+                    .compile(&field, operator, &value)
+                    .map_err(|(part, message)| {
+                        let column = match part {
+                            Part::Field => column,
+                            Part::Operator => operator_column,
+                            Part::Value => value_column,
+                        };
+                        (column, message)
+                    })?;
+                Ok(format!("coalesce({}, 0)", sql))
+            }
+            _ => Err((column, "expected a field".into())),
+        }
+    }
+
+    fn param(&mut self, value: Value) -> String {
+        let name = format!(":filter{}", self.params.len() + 1);
+        self.params.push((name.clone(), value));
+        name
+    }
+
+    fn compile(
+        &mut self,
+        field: &str,
+        operator: Operator,
+        value: &str,
+    ) -> Result<String, (Part, String)> {
+        let item = self.item.name();
+        let table = format!("{}s", item);
+        let not_applicable = || {
+            (
+                Part::Operator,
+                format!("`{}` does not apply to `{}`", operator, field),
+            )
+        };
+        match field {
+            "description" | "note" => {
+                let column = format!("{}.{}", table, field);
+                let param = self.param(Value::Text(value.into()));
+                match operator {
+                    Operator::Is | Operator::Equal => Ok(format!("{} = {}", column, param)),
+                    Operator::NotEqual => Ok(format!("{} IS NOT {}", column, param)),
+                    Operator::Contains => Ok(format!("instr(lower({}), lower({}))", column, param)),
+                    _ => Err(not_applicable()),
+                }
+            }
+            "tag" => {
+                let name = tag_name(value.into()).map_err(|e| (Part::Value, e))?;
+                let param = self.param(Value::Text(name));
+                let (negation, condition) = match operator {
+                    Operator::Is | Operator::Equal => ("", format!("tags.name = {}", param)),
+                    Operator::NotEqual => ("NOT ", format!("tags.name = {}", param)),
+                    Operator::Contains => {
+                        ("", format!("instr(lower(tags.name), lower({}))", param))
+                    }
+                    _ => return Err(not_applicable()),
+                };
+                Ok(format!(
+                    "{negation}EXISTS (SELECT * FROM {item}_tags JOIN tags ON tags.id = {item}_tags.tag WHERE {item}_tags.{item} = {table}.id AND {condition})",
+                    negation = negation,
+                    item = item,
+                    table = table,
+                    condition = condition
+                ))
+            }
+            "context" if self.item == Item::Action => {
+                let name = context_name(value.into()).map_err(|e| (Part::Value, e))?;
+                let param = self.param(Value::Text(name));
+                let (negation, condition) = match operator {
+                    Operator::Is | Operator::Equal => ("", format!("contexts.name = {}", param)),
+                    Operator::NotEqual => ("NOT ", format!("contexts.name = {}", param)),
+                    Operator::Contains => {
+                        ("", format!("instr(lower(contexts.name), lower({}))", param))
+                    }
+                    _ => return Err(not_applicable()),
+                };
+                Ok(format!(
+                    "{}EXISTS (SELECT * FROM contexts WHERE contexts.id = actions.context AND {})",
+                    negation, condition
+                ))
+            }
+            "due" | "scheduled" | "created" | "done" => {
+                let comparison = comparison(operator).ok_or_else(not_applicable)?;
+                let date = parse_date(value, self.today).map_err(|e| (Part::Value, e))?;
+                let param = self.param(Value::Text(date.to_string()));
+                // Creation and completion record the time as well as the date.
+                let column = if field == "due" || field == "scheduled" {
+                    format!("{}.{}", table, field)
+                } else {
+                    format!("date({}.{})", table, field)
+                };
+                Ok(format!("{} {} {}", column, comparison, param))
+            }
+            "id" | "priority" => {
+                let comparison = comparison(operator).ok_or_else(not_applicable)?;
+                let number = if field == "id" {
+                    value
+                        .strip_prefix('#')
+                        .unwrap_or(value)
+                        .parse::<i64>()
+                        .map_err(|_| (Part::Value, format!("invalid ID: `{}`", value)))?
+                } else {
+                    parse_priority(value).map_err(|e| (Part::Value, e))?
+                };
+                let param = self.param(Value::Integer(number));
+                Ok(format!("{}.{} {} {}", table, field, comparison, param))
+            }
+            _ => Err((Part::Field, format!("no such field: `{}`", field))),
+        }
+    }
+}
+
+// The part of a term that an error is about.
+enum Part {
+    Field,
+    Operator,
+    Value,
+}
+
+fn comparison(operator: Operator) -> Option<&'static str> {
+    match operator {
+        Operator::Is | Operator::Equal => Some("="),
+        Operator::NotEqual => Some("IS NOT"),
+        Operator::Less => Some("<"),
+        Operator::LessEqual => Some("<="),
+        Operator::Greater => Some(">"),
+        Operator::GreaterEqual => Some(">="),
+        Operator::Contains => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Filter, String> {
+        Filter::parse_at(
+            text,
+            Item::Action,
+            NaiveDate::from_ymd_opt(2021, 5, 14).unwrap(),
+        )
+    }
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.into())
+    }
+
+    #[test]
+    fn compiles_comparison() {
+        assert_eq!(
+            parse("due<fri"),
+            Ok(Filter {
+                sql: "coalesce(actions.due < :filter1, 0)".into(),
+                params: vec![(":filter1".into(), text("2021-05-21"))]
+            })
+        );
+    }
+
+    #[test]
+    fn compiles_boolean_operators() {
+        let filter = parse("tag:work and due<fri and not context:@phone or priority<=2").unwrap();
+        assert_eq!(
+            filter.sql,
+            "(coalesce(EXISTS (SELECT * FROM action_tags JOIN tags ON tags.id = action_tags.tag WHERE action_tags.action = actions.id AND tags.name = :filter1), 0) AND coalesce(actions.due < :filter2, 0) AND NOT coalesce(EXISTS (SELECT * FROM contexts WHERE contexts.id = actions.context AND contexts.name = :filter3), 0) OR coalesce(actions.priority <= :filter4, 0))"
+        );
+        assert_eq!(
+            filter.params,
+            vec![
+                (":filter1".into(), text("work")),
+                (":filter2".into(), text("2021-05-21")),
+                (":filter3".into(), text("phone")),
+                (":filter4".into(), Value::Integer(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_adjacent_terms_with_and() {
+        assert_eq!(
+            parse("(tag:work OR tag:home) description~\"tax return\"")
+                .unwrap()
+                .sql,
+            "((coalesce(EXISTS (SELECT * FROM action_tags JOIN tags ON tags.id = action_tags.tag WHERE action_tags.action = actions.id AND tags.name = :filter1), 0) OR coalesce(EXISTS (SELECT * FROM action_tags JOIN tags ON tags.id = action_tags.tag WHERE action_tags.action = actions.id AND tags.name = :filter2), 0))) AND coalesce(instr(lower(actions.description), lower(:filter3)), 0)"
+        );
+    }
+
+    #[test]
+    fn compiles_goal_filter() {
+        assert_eq!(
+            Filter::parse_at(
+                "note~'library' and id>=3",
+                Item::Goal,
+                NaiveDate::from_ymd_opt(2021, 5, 14).unwrap()
+            ),
+            Ok(Filter {
+                sql: "coalesce(instr(lower(goals.note), lower(:filter1)), 0) AND coalesce(goals.id >= :filter2, 0)".into(),
+                params: vec![
+                    (":filter1".into(), text("library")),
+                    (":filter2".into(), Value::Integer(3))
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn reports_unknown_field() {
+        assert_eq!(
+            parse("tag:work and dew<fri"),
+            Err("invalid filter at column 14: no such field: `dew`".into())
+        );
+    }
+
+    #[test]
+    fn reports_context_on_goals() {
+        assert_eq!(
+            Filter::parse("context:@phone", Item::Goal),
+            Err("invalid filter at column 1: no such field: `context`".into())
+        );
+    }
+
+    #[test]
+    fn reports_missing_operator() {
+        assert_eq!(
+            parse("due fri"),
+            Err("invalid filter at column 5: expected an operator after `due`".into())
+        );
+    }
+
+    #[test]
+    fn reports_missing_value() {
+        assert_eq!(
+            parse("due<"),
+            Err("invalid filter at column 5: expected a value".into())
+        );
+    }
+
+    #[test]
+    fn reports_invalid_value() {
+        assert_eq!(
+            parse("due < someday"),
+            Err("invalid filter at column 7: invalid date: `someday`".into())
+        );
+    }
+
+    #[test]
+    fn reports_inapplicable_operator() {
+        assert_eq!(
+            parse("tag<work"),
+            Err("invalid filter at column 4: `<` does not apply to `tag`".into())
+        );
+    }
+
+    #[test]
+    fn reports_unbalanced_parentheses() {
+        assert_eq!(
+            parse("(tag:work or tag:home"),
+            Err("invalid filter at column 22: expected `)`".into())
+        );
+        assert_eq!(
+            parse("tag:work)"),
+            Err("invalid filter at column 9: unexpected `)`".into())
+        );
+    }
+
+    #[test]
+    fn reports_unterminated_string() {
+        assert_eq!(
+            parse("description~\"tax"),
+            Err("invalid filter at column 13: unterminated string".into())
+        );
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use std::io::Write;
+
+// How a listing is written.  `Text` is the listing's own human-readable form; the others write
+// every record in full, with the fields that odo(1) documents, for scripts to read.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    JsonLines,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub fn from_arg(arg: &str) -> Result<Self, String> {
+        match arg {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::JsonLines),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            _ => Err(format!("invalid format: `{}`", arg)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Text(String),
+    List(Vec<Value>),
+}
+
+impl From<Option<String>> for Value {
+    fn from(value: Option<String>) -> Self {
+        value.map_or(Self::Null, Self::Text)
+    }
+}
+
+impl From<Option<i64>> for Value {
+    fn from(value: Option<i64>) -> Self {
+        value.map_or(Self::Null, Self::Integer)
+    }
+}
+
+// Writes `rows`, each holding a value for every one of `columns`, in `format`, which must not be
+// `Text`.  JSON gives an array of objects and JSON Lines one object per line.  CSV and TSV start with
+// a header and write lists as comma-separated values and null as an empty field.
+pub fn write_records<T: Write>(
+    writer: &mut T,
+    format: Format,
+    columns: &[&str],
+    rows: &[Vec<Value>],
+) -> Result<(), Error> {
+    let mut output = String::new();
+    match format {
+        Format::Text => unreachable!(),
+        Format::Json | Format::JsonLines => {
+            let objects = rows
+                .iter()
+                .map(|row| {
+                    let members = columns
+                        .iter()
+                        .zip(row)
+                        .map(|(column, value)| format!("{}:{}", json_string(column), json(value)))
+                        .collect::<Vec<_>>();
+                    format!("{{{}}}", members.join(","))
+                })
+                .collect::<Vec<_>>();
+            if format == Format::Json {
+                if objects.is_empty() {
+                    output.push_str("[]\n");
+                } else {
+                    output.push_str(&format!("[\n{}\n]\n", objects.join(",\n")));
+                }
+            } else {
+                for object in objects {
+                    output.push_str(&object);
+                    output.push('\n');
+                }
+            }
+        }
+        Format::Csv | Format::Tsv => {
+            let (separator, field): (&str, fn(&str) -> String) = if format == Format::Csv {
+                (",", csv_field)
+            } else {
+                ("\t", tsv_field)
+            };
+            let header = columns
+                .iter()
+                .map(|column| field(column))
+                .collect::<Vec<_>>();
+            output.push_str(&header.join(separator));
+            output.push('\n');
+            for row in rows {
+                let fields = row
+                    .iter()
+                    .map(|value| field(&plain(value)))
+                    .collect::<Vec<_>>();
+                output.push_str(&fields.join(separator));
+                output.push('\n');
+            }
+        }
+    }
+    writer
+        .write_all(output.as_bytes())
+        .map_err(|e| Error::Storage(format!("unable to write records: {}", e)))
+}
+
+fn json(value: &Value) -> String {
+    match value {
+        Value::Null => "null".into(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::Text(value) => json_string(value),
+        Value::List(values) => format!(
+            "[{}]",
+            values.iter().map(json).collect::<Vec<_>>().join(",")
+        ),
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut string = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            c if (c as u32) < 0x20 => string.push_str(&format!("\\u{:04x}", c as u32)),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string
+}
+
+pub(crate) fn plain(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::Text(value) => value.clone(),
+        Value::List(values) => values.iter().map(plain).collect::<Vec<_>>().join(","),
+    }
+}
+
+// Fields are quoted as RFC 4180 describes when they contain a separator, a quote, or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.into()
+    }
+}
+
+// TSV cannot quote, so backslashes, tabs, and line breaks are escaped instead.
+fn tsv_field(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(format: Format, rows: &[Vec<Value>]) -> String {
+        let mut output = Vec::new();
+        write_records(&mut output, format, &["id", "description", "tags"], rows).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn rows() -> Vec<Vec<Value>> {
+        vec![
+            vec![
+                Value::Integer(1),
+                Value::Text("Read \"Network Effect\",\nslowly\t\\".into()),
+                Value::List(vec![
+                    Value::Text("reading".into()),
+                    Value::Text("work".into()),
+                ]),
+            ],
+            vec![
+                Value::Integer(2),
+                Value::Text("Email Alice".into()),
+                Value::Null,
+            ],
+        ]
+    }
+
+    #[test]
+    fn parses_format() {
+        assert_eq!(Format::from_arg("jsonl"), Ok(Format::JsonLines));
+        assert_eq!(Format::from_arg("xml"), Err("invalid format: `xml`".into()));
+    }
+
+    #[test]
+    fn writes_json() {
+        assert_eq!(
+            write(Format::Json, &rows()),
+            "[\n{\"id\":1,\"description\":\"Read \\\"Network Effect\\\",\\nslowly\\t\\\\\",\"tags\":[\"reading\",\"work\"]},\n{\"id\":2,\"description\":\"Email Alice\",\"tags\":null}\n]\n"
+        );
+        assert_eq!(write(Format::Json, &[]), "[]\n");
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        assert_eq!(
+            write(Format::JsonLines, &rows()[1..]),
+            "{\"id\":2,\"description\":\"Email Alice\",\"tags\":null}\n"
+        );
+    }
+
+    #[test]
+    fn writes_csv() {
+        assert_eq!(
+            write(Format::Csv, &rows()),
+            "id,description,tags\n1,\"Read \"\"Network Effect\"\",\nslowly\t\\\",\"reading,work\"\n2,Email Alice,\n"
+        );
+    }
+
+    #[test]
+    fn writes_tsv() {
+        assert_eq!(
+            write(Format::Tsv, &rows()),
+            "id\tdescription\ttags\n1\tRead \"Network Effect\",\\nslowly\\t\\\\\treading,work\n2\tEmail Alice\t\n"
+        );
+    }
+}
@@ -14,14 +14,33 @@
 // see <https://www.gnu.org/licenses/>.
 
 use command::Command;
+pub use doctor::Problem;
+pub use error::Error;
+pub use recurrence::Recurrence;
+use std::env;
+pub use store::{
+    Action, Annotation, Context, DateField, Goal, GoalType, Item, Match, NextActions, Setting,
+    Status, Store, Tag, View,
+};
 
 mod command;
 mod database;
+mod doctor;
+mod error;
+mod filter;
+mod format;
+mod recurrence;
+mod store;
+mod table;
+mod template;
+mod urgency;
 
-pub fn run<T: Iterator<Item = String>>(args: T) -> Result<(), String> {
-    let command = Command::from_args(args)?;
-    let connection = database::open()?;
-    database::initialize(&connection)
-        .map_err(|e| format!("unable to initialize database: {}", e))?;
-    command.run(&connection)
+pub fn run<T: Iterator<Item = String>>(args: T) -> Result<(), Error> {
+    let (location, args) = command::parse_location(args.collect(), env::var_os("ODO_DATABASE"))?;
+    if !command::may_name_view(&args) {
+        return Command::from_args(args.into_iter())?.run_at(&location);
+    }
+    let store = Store::open_location(&location)?;
+    let args = command::expand_view(&store, args)?;
+    Command::from_args(args.into_iter())?.run(&store)
 }
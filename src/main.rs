@@ -18,6 +18,6 @@ use std::{env, process};
 fn main() {
     if let Err(e) = odo::run(env::args().skip(1)) {
         eprintln!("odo: {}", e);
-        process::exit(1);
+        process::exit(e.exit_code());
     }
 }
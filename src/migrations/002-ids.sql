@@ -0,0 +1,25 @@
+-- Copyright 2021 Matthew James Kraai
+--
+-- This file is part of odo.
+--
+-- odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+-- General Public License as published by the Free Software Foundation, either version 3 of the
+-- License, or (at your option) any later version.
+--
+-- odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+-- implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+-- General Public License for more details.
+--
+-- You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+-- see <https://www.gnu.org/licenses/>.
+
+CREATE TABLE new_actions (id INTEGER PRIMARY KEY, description TEXT NOT NULL);
+INSERT INTO new_actions (description) SELECT description FROM actions WHERE description IS NOT NULL ORDER BY rowid;
+CREATE TABLE new_goals (id INTEGER PRIMARY KEY, description TEXT NOT NULL, action INTEGER REFERENCES actions (id) ON DELETE SET NULL);
+INSERT INTO new_goals (description, action) SELECT goals.description, new_actions.id FROM goals LEFT JOIN new_actions ON new_actions.description = goals.action WHERE goals.description IS NOT NULL ORDER BY goals.rowid;
+DROP TABLE goals;
+DROP TABLE actions;
+ALTER TABLE new_actions RENAME TO actions;
+ALTER TABLE new_goals RENAME TO goals;
+CREATE UNIQUE INDEX actions_description ON actions (description);
+CREATE UNIQUE INDEX goals_description ON goals (description);
@@ -0,0 +1,23 @@
+-- Copyright 2021 Matthew James Kraai
+--
+-- This file is part of odo.
+--
+-- odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+-- General Public License as published by the Free Software Foundation, either version 3 of the
+-- License, or (at your option) any later version.
+--
+-- odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+-- implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+-- General Public License for more details.
+--
+-- You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+-- see <https://www.gnu.org/licenses/>.
+
+CREATE TABLE goal_actions (goal INTEGER NOT NULL REFERENCES goals (id) ON DELETE CASCADE, action INTEGER NOT NULL REFERENCES actions (id) ON DELETE CASCADE, position INTEGER NOT NULL, PRIMARY KEY (goal, action));
+INSERT INTO goal_actions SELECT id, action, 0 FROM goals WHERE action IS NOT NULL;
+CREATE TABLE new_goals (id INTEGER PRIMARY KEY, description TEXT NOT NULL, done TEXT, parent INTEGER REFERENCES goals (id));
+INSERT INTO new_goals SELECT id, description, done, parent FROM goals;
+DROP TABLE goals;
+ALTER TABLE new_goals RENAME TO goals;
+CREATE UNIQUE INDEX goals_description ON goals (description) WHERE done IS NULL;
+CREATE INDEX goal_actions_action ON goal_actions (action);
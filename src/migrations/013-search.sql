@@ -0,0 +1,39 @@
+-- Copyright 2021 Matthew James Kraai
+--
+-- This file is part of odo.
+--
+-- odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+-- General Public License as published by the Free Software Foundation, either version 3 of the
+-- License, or (at your option) any later version.
+--
+-- odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+-- implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+-- General Public License for more details.
+--
+-- You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+-- see <https://www.gnu.org/licenses/>.
+
+CREATE VIRTUAL TABLE action_search USING fts5 (description, note, content = 'actions', content_rowid = 'id');
+CREATE TRIGGER action_search_insert AFTER INSERT ON actions BEGIN
+  INSERT INTO action_search (rowid, description, note) VALUES(new.id, new.description, new.note);
+END;
+CREATE TRIGGER action_search_delete AFTER DELETE ON actions BEGIN
+  INSERT INTO action_search (action_search, rowid, description, note) VALUES('delete', old.id, old.description, old.note);
+END;
+CREATE TRIGGER action_search_update AFTER UPDATE OF description, note ON actions BEGIN
+  INSERT INTO action_search (action_search, rowid, description, note) VALUES('delete', old.id, old.description, old.note);
+  INSERT INTO action_search (rowid, description, note) VALUES(new.id, new.description, new.note);
+END;
+INSERT INTO action_search (action_search) VALUES('rebuild');
+CREATE VIRTUAL TABLE goal_search USING fts5 (description, note, content = 'goals', content_rowid = 'id');
+CREATE TRIGGER goal_search_insert AFTER INSERT ON goals BEGIN
+  INSERT INTO goal_search (rowid, description, note) VALUES(new.id, new.description, new.note);
+END;
+CREATE TRIGGER goal_search_delete AFTER DELETE ON goals BEGIN
+  INSERT INTO goal_search (goal_search, rowid, description, note) VALUES('delete', old.id, old.description, old.note);
+END;
+CREATE TRIGGER goal_search_update AFTER UPDATE OF description, note ON goals BEGIN
+  INSERT INTO goal_search (goal_search, rowid, description, note) VALUES('delete', old.id, old.description, old.note);
+  INSERT INTO goal_search (rowid, description, note) VALUES(new.id, new.description, new.note);
+END;
+INSERT INTO goal_search (goal_search) VALUES('rebuild');
@@ -0,0 +1,536 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::command::{add_days, add_months, days_in_month};
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::{convert::TryFrom, fmt};
+
+// Larger intervals are no use for planning, and bounding them keeps date arithmetic in range.
+const MAX_INTERVAL: u32 = 1000;
+
+/// A month and a day of that month.
+pub type MonthDay = (u32, u32);
+
+/// How often an action repeats.  Every rule but `After` counts from the date the action was due (or
+/// scheduled), so that finishing late does not shift later occurrences.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Recurrence {
+    Daily { interval: u32 },
+    // No days means the weekday of the date the rule counts from.
+    Weekly { interval: u32, days: Vec<Weekday> },
+    // No day means the day of the month of the date the rule counts from.  A day past the end of a
+    // month means its last day.
+    Monthly { interval: u32, day: Option<u32> },
+    // No month and day means those of the date the rule counts from.  February 29th means the 28th
+    // in other years.
+    Yearly { interval: u32, on: Option<MonthDay> },
+    // Repeats the given number of days after the action is finished.
+    After { days: u32 },
+}
+
+impl Recurrence {
+    /// Parses `daily`, `weekly`, `weekly:mon,thu`, `monthly`, `monthly:31`, `yearly`, `after:3d`,
+    /// `after:2w`, or an RRULE such as `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`.
+    pub fn from_arg(arg: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid recurrence: `{}`", arg);
+        let rule = arg.trim();
+        let rule = rule
+            .strip_prefix("RRULE:")
+            .or_else(|| rule.strip_prefix("rrule:"))
+            .unwrap_or(rule);
+        if rule.contains('=') {
+            return Self::from_rrule(rule).ok_or_else(invalid);
+        }
+        let (name, value) = match rule.find(':') {
+            Some(index) => (&rule[..index], Some(&rule[index + 1..])),
+            None => (rule, None),
+        };
+        match (name.to_lowercase().as_str(), value) {
+            ("daily", None) => Ok(Self::Daily { interval: 1 }),
+            ("weekly", None) => Ok(Self::Weekly {
+                interval: 1,
+                days: Vec::new(),
+            }),
+            ("weekly", Some(days)) => Ok(Self::Weekly {
+                interval: 1,
+                days: parse_weekdays(days).ok_or_else(invalid)?,
+            }),
+            ("monthly", None) => Ok(Self::Monthly {
+                interval: 1,
+                day: None,
+            }),
+            ("monthly", Some(day)) => Ok(Self::Monthly {
+                interval: 1,
+                day: Some(parse_day(day).ok_or_else(invalid)?),
+            }),
+            ("yearly", None) => Ok(Self::Yearly {
+                interval: 1,
+                on: None,
+            }),
+            ("after", Some(period)) => {
+                let days = if let Some(days) = period.strip_suffix('d') {
+                    days.parse::<u32>().ok()
+                } else if let Some(weeks) = period.strip_suffix('w') {
+                    weeks
+                        .parse::<u32>()
+                        .ok()
+                        .and_then(|weeks| weeks.checked_mul(7))
+                } else {
+                    None
+                }
+                .filter(|days| *days > 0)
+                .ok_or_else(invalid)?;
+                Ok(Self::After { days })
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    fn from_rrule(rule: &str) -> Option<Self> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut days = None;
+        let mut month = None;
+        let mut day = None;
+        for part in rule.split(';') {
+            let (name, value) = part.split_at(part.find('=')?);
+            let value = &value[1..];
+            match name.to_uppercase().as_str() {
+                "FREQ" => frequency = Some(value.to_uppercase()),
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| (1..=MAX_INTERVAL).contains(interval))?
+                }
+                "BYDAY" => days = Some(parse_weekdays(value)?),
+                "BYMONTH" => {
+                    month = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|month| (1..=12).contains(month))?,
+                    )
+                }
+                "BYMONTHDAY" => day = Some(parse_day(value)?),
+                _ => return None,
+            }
+        }
+        match frequency?.as_str() {
+            "DAILY" if days.is_none() && month.is_none() && day.is_none() => {
+                Some(Self::Daily { interval })
+            }
+            "WEEKLY" if month.is_none() && day.is_none() => Some(Self::Weekly {
+                interval,
+                days: days.unwrap_or_default(),
+            }),
+            "MONTHLY" if days.is_none() && month.is_none() => Some(Self::Monthly { interval, day }),
+            // A month and day are given together, and the day must exist in that month.
+            "YEARLY" if days.is_none() && month.is_some() == day.is_some() => {
+                if let (Some(month), Some(day)) = (month, day) {
+                    NaiveDate::from_ymd_opt(2000, month, day)?;
+                }
+                Some(Self::Yearly {
+                    interval,
+                    on: month.zip(day),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Fills in whatever the rule leaves to the date it first counts from, so that later
+    /// occurrences do not drift when a month is too short or a year has no February 29th.
+    pub fn anchor(self, date: NaiveDate) -> Self {
+        match self {
+            Self::Weekly { interval, days } if days.is_empty() => Self::Weekly {
+                interval,
+                days: vec![date.weekday()],
+            },
+            Self::Monthly {
+                interval,
+                day: None,
+            } => Self::Monthly {
+                interval,
+                day: Some(date.day()),
+            },
+            Self::Yearly { interval, on: None } => Self::Yearly {
+                interval,
+                on: Some((date.month(), date.day())),
+            },
+            recurrence => recurrence,
+        }
+    }
+
+    /// Returns the occurrence after `date`, or for `After`, the one following completion on `done`.
+    pub fn next(&self, date: NaiveDate, done: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Self::Daily { interval } => add_days(date, i64::from(*interval)),
+            Self::Weekly { interval, days } => {
+                if days.is_empty() {
+                    return add_days(date, 7 * i64::from(*interval));
+                }
+                // Weeks start on Monday, and only every `interval`th week counts: a later day in
+                // the same week, or else the first day in the week `interval` weeks on.
+                let weekday = i64::from(date.weekday().num_days_from_monday());
+                let day = |day: &Weekday| i64::from(day.num_days_from_monday());
+                match days.iter().map(day).find(|day| *day > weekday) {
+                    Some(day) => add_days(date, day - weekday),
+                    None => add_days(
+                        date,
+                        (7 * i64::from(*interval)).checked_add(day(days.first()?) - weekday)?,
+                    ),
+                }
+            }
+            Self::Monthly { interval, day } => {
+                let day = day.unwrap_or_else(|| date.day());
+                let on_day = |date: NaiveDate| {
+                    NaiveDate::from_ymd_opt(
+                        date.year(),
+                        date.month(),
+                        day.min(days_in_month(date.year(), date.month())),
+                    )
+                };
+                // The day may still be ahead in the same month, e.g. the 31st after a clamped 28th.
+                let this_month = on_day(date)?;
+                if this_month > date {
+                    return Some(this_month);
+                }
+                on_day(add_months(
+                    NaiveDate::from_ymd_opt(date.year(), date.month(), 1)?,
+                    i64::from(*interval),
+                )?)
+            }
+            Self::Yearly { interval, on } => {
+                let (month, day) = on.unwrap_or_else(|| (date.month(), date.day()));
+                let on_date = |year: i32| {
+                    NaiveDate::from_ymd_opt(year, month, day.min(days_in_month(year, month)))
+                };
+                // The date may still be ahead in the same year, e.g. the 29th after a clamped 28th.
+                let this_year = on_date(date.year())?;
+                if this_year > date {
+                    return Some(this_year);
+                }
+                on_date(date.year().checked_add(i32::try_from(*interval).ok()?)?)
+            }
+            Self::After { days } => add_days(done, i64::from(*days)),
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Daily { interval: 1 } => write!(f, "daily"),
+            Self::Daily { interval } => write!(f, "FREQ=DAILY;INTERVAL={}", interval),
+            Self::Weekly { interval, days } => {
+                let days = days
+                    .iter()
+                    .map(|day| format!("{:?}", day).to_uppercase()[..2].to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, "FREQ=WEEKLY")?;
+                if *interval != 1 {
+                    write!(f, ";INTERVAL={}", interval)?;
+                }
+                if !days.is_empty() {
+                    write!(f, ";BYDAY={}", days)?;
+                }
+                Ok(())
+            }
+            Self::Monthly { interval, day } => {
+                write!(f, "FREQ=MONTHLY")?;
+                if *interval != 1 {
+                    write!(f, ";INTERVAL={}", interval)?;
+                }
+                if let Some(day) = day {
+                    write!(f, ";BYMONTHDAY={}", day)?;
+                }
+                Ok(())
+            }
+            Self::Yearly {
+                interval: 1,
+                on: None,
+            } => write!(f, "yearly"),
+            Self::Yearly { interval, on } => {
+                write!(f, "FREQ=YEARLY")?;
+                if *interval != 1 {
+                    write!(f, ";INTERVAL={}", interval)?;
+                }
+                if let Some((month, day)) = on {
+                    write!(f, ";BYMONTH={};BYMONTHDAY={}", month, day)?;
+                }
+                Ok(())
+            }
+            Self::After { days } => write!(f, "after:{}d", days),
+        }
+    }
+}
+
+fn parse_weekdays(days: &str) -> Option<Vec<Weekday>> {
+    let mut weekdays = Vec::new();
+    for day in days.split(',') {
+        let day = match day.trim().to_lowercase().as_str() {
+            "mo" | "mon" | "monday" => Weekday::Mon,
+            "tu" | "tue" | "tuesday" => Weekday::Tue,
+            "we" | "wed" | "wednesday" => Weekday::Wed,
+            "th" | "thu" | "thursday" => Weekday::Thu,
+            "fr" | "fri" | "friday" => Weekday::Fri,
+            "sa" | "sat" | "saturday" => Weekday::Sat,
+            "su" | "sun" | "sunday" => Weekday::Sun,
+            _ => return None,
+        };
+        if !weekdays.contains(&day) {
+            weekdays.push(day);
+        }
+    }
+    weekdays.sort_by_key(|day| day.num_days_from_monday());
+    Some(weekdays)
+}
+
+fn parse_day(day: &str) -> Option<u32> {
+    day.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_shorthand() {
+        assert_eq!(
+            Recurrence::from_arg("daily"),
+            Ok(Recurrence::Daily { interval: 1 })
+        );
+        assert_eq!(
+            Recurrence::from_arg("weekly:thu,mon"),
+            Ok(Recurrence::Weekly {
+                interval: 1,
+                days: vec![Weekday::Mon, Weekday::Thu]
+            })
+        );
+        assert_eq!(
+            Recurrence::from_arg("monthly:31"),
+            Ok(Recurrence::Monthly {
+                interval: 1,
+                day: Some(31)
+            })
+        );
+        assert_eq!(
+            Recurrence::from_arg("yearly"),
+            Ok(Recurrence::Yearly {
+                interval: 1,
+                on: None
+            })
+        );
+        assert_eq!(
+            Recurrence::from_arg("after:2w"),
+            Ok(Recurrence::After { days: 14 })
+        );
+    }
+
+    #[test]
+    fn parses_rrule() {
+        assert_eq!(
+            Recurrence::from_arg("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH"),
+            Ok(Recurrence::Weekly {
+                interval: 2,
+                days: vec![Weekday::Mon, Weekday::Thu]
+            })
+        );
+        assert_eq!(
+            Recurrence::from_arg("FREQ=MONTHLY;BYMONTHDAY=15"),
+            Ok(Recurrence::Monthly {
+                interval: 1,
+                day: Some(15)
+            })
+        );
+        assert_eq!(
+            Recurrence::from_arg("FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29"),
+            Ok(Recurrence::Yearly {
+                interval: 1,
+                on: Some((2, 29))
+            })
+        );
+    }
+
+    #[test]
+    fn reports_invalid_recurrence() {
+        for arg in [
+            "hourly",
+            "monthly:32",
+            "weekly:someday",
+            "after:0d",
+            "after:3",
+            "FREQ=DAILY;BYDAY=MO",
+            "FREQ=WEEKLY;INTERVAL=0",
+            "FREQ=WEEKLY;INTERVAL=4294967295;BYDAY=MO",
+            "FREQ=YEARLY;BYMONTH=2",
+            "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30",
+            "FREQ=SECONDLY",
+        ] {
+            assert_eq!(
+                Recurrence::from_arg(arg),
+                Err(format!("invalid recurrence: `{}`", arg))
+            );
+        }
+    }
+
+    #[test]
+    fn formats_recurrence_so_it_parses_back() {
+        for recurrence in [
+            Recurrence::Daily { interval: 1 },
+            Recurrence::Daily { interval: 3 },
+            Recurrence::Weekly {
+                interval: 2,
+                days: vec![Weekday::Mon, Weekday::Thu],
+            },
+            Recurrence::Monthly {
+                interval: 1,
+                day: Some(31),
+            },
+            Recurrence::Yearly {
+                interval: 1,
+                on: None,
+            },
+            Recurrence::Yearly {
+                interval: 2,
+                on: Some((2, 29)),
+            },
+            Recurrence::After { days: 3 },
+        ] {
+            assert_eq!(
+                Recurrence::from_arg(&recurrence.to_string()),
+                Ok(recurrence)
+            );
+        }
+    }
+
+    #[test]
+    fn anchors_monthly_recurrence_to_day() {
+        assert_eq!(
+            Recurrence::from_arg("monthly")
+                .unwrap()
+                .anchor(date(2021, 1, 31)),
+            Recurrence::Monthly {
+                interval: 1,
+                day: Some(31)
+            }
+        );
+    }
+
+    #[test]
+    fn anchors_yearly_recurrence_to_month_and_day() {
+        assert_eq!(
+            Recurrence::from_arg("yearly")
+                .unwrap()
+                .anchor(date(2028, 2, 29)),
+            Recurrence::Yearly {
+                interval: 1,
+                on: Some((2, 29))
+            }
+        );
+    }
+
+    #[test]
+    fn repeats_weekly_on_given_days() {
+        let recurrence = Recurrence::from_arg("weekly:mon,thu").unwrap();
+        // 2021-05-14 is a Friday.
+        let friday = date(2021, 5, 14);
+        assert_eq!(recurrence.next(friday, friday), Some(date(2021, 5, 17)));
+        assert_eq!(
+            recurrence.next(date(2021, 5, 17), friday),
+            Some(date(2021, 5, 20))
+        );
+    }
+
+    #[test]
+    fn skips_weeks_by_interval() {
+        let recurrence = Recurrence::from_arg("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH").unwrap();
+        let thursday = date(2021, 5, 20);
+        assert_eq!(recurrence.next(thursday, thursday), Some(date(2021, 5, 31)));
+        let monday = date(2021, 5, 17);
+        assert_eq!(recurrence.next(monday, monday), Some(thursday));
+    }
+
+    #[test]
+    fn skips_weeks_by_largest_interval() {
+        let recurrence = Recurrence::from_arg("FREQ=WEEKLY;INTERVAL=1000;BYDAY=MO").unwrap();
+        let monday = date(2021, 5, 17);
+        assert_eq!(
+            recurrence.next(monday, monday),
+            monday.checked_add_signed(chrono::Duration::weeks(1000))
+        );
+    }
+
+    #[test]
+    fn repeats_monthly_on_31st_through_short_months() {
+        let recurrence = Recurrence::from_arg("monthly:31").unwrap();
+        let done = date(2021, 1, 1);
+        let february = recurrence.next(date(2021, 1, 31), done).unwrap();
+        assert_eq!(february, date(2021, 2, 28));
+        let march = recurrence.next(february, done).unwrap();
+        assert_eq!(march, date(2021, 3, 31));
+        assert_eq!(recurrence.next(march, done), Some(date(2021, 4, 30)));
+    }
+
+    #[test]
+    fn repeats_monthly_on_29th_in_leap_year() {
+        let recurrence = Recurrence::from_arg("monthly:29").unwrap();
+        let done = date(2020, 1, 1);
+        assert_eq!(
+            recurrence.next(date(2020, 1, 29), done),
+            Some(date(2020, 2, 29))
+        );
+    }
+
+    #[test]
+    fn repeats_yearly_from_leap_day() {
+        let recurrence = Recurrence::from_arg("yearly").unwrap();
+        assert_eq!(
+            recurrence.next(date(2020, 2, 29), date(2020, 3, 1)),
+            Some(date(2021, 2, 28))
+        );
+    }
+
+    #[test]
+    fn repeats_anchored_yearly_on_leap_day() {
+        let recurrence = Recurrence::from_arg("yearly")
+            .unwrap()
+            .anchor(date(2028, 2, 29));
+        let done = date(2028, 3, 1);
+        let next = recurrence.next(date(2028, 2, 29), done).unwrap();
+        assert_eq!(next, date(2029, 2, 28));
+        let next = recurrence.next(next, done).unwrap();
+        assert_eq!(next, date(2030, 2, 28));
+        assert_eq!(
+            recurrence.next(date(2031, 2, 28), done),
+            Some(date(2032, 2, 29))
+        );
+    }
+
+    #[test]
+    fn repeats_after_completion() {
+        let recurrence = Recurrence::from_arg("after:3d").unwrap();
+        assert_eq!(
+            recurrence.next(date(2021, 5, 1), date(2021, 5, 14)),
+            Some(date(2021, 5, 17))
+        );
+    }
+}
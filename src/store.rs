@@ -0,0 +1,2201 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    command::{self, parse_date, today, TEMPLATE_FIELDS},
+    database::{self, Location},
+    doctor::{self, Problem},
+    error::Error,
+    filter::Filter,
+    recurrence::Recurrence,
+    template::Template,
+    urgency,
+};
+use chrono::NaiveDate;
+use rusqlite::{types::Value, Connection, OptionalExtension, ToSql};
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
+
+/// An odo database, for programs that use odo as a library.  The command-line interface is built on
+/// the same operations, so the two always agree.  Items are named by their descriptions, which may
+/// also be given as IDs, as on the command line.
+pub struct Store {
+    connection: Connection,
+}
+
+/// An action, with the fields of the records that odo(1) documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Action {
+    pub id: i64,
+    pub description: String,
+    pub done: Option<String>,
+    pub created: Option<String>,
+    pub due: Option<String>,
+    pub scheduled: Option<String>,
+    pub priority: Option<i64>,
+    pub context: Option<String>,
+    pub recurrence: Option<String>,
+    pub goals: Vec<i64>,
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+}
+
+/// A goal, with the fields of the records that odo(1) documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Goal {
+    pub id: i64,
+    pub description: String,
+    pub done: Option<String>,
+    pub created: Option<String>,
+    pub due: Option<String>,
+    pub scheduled: Option<String>,
+    pub priority: Option<i64>,
+    pub parent: Option<i64>,
+    pub sequential: bool,
+    pub actions: Vec<i64>,
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+}
+
+/// A note added to an action or goal, with the UTC time it was added.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub created: String,
+    pub text: String,
+}
+
+/// A context, which says where actions can be done.  Contexts are listed by position, and those
+/// without one last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Context {
+    pub name: String,
+    pub description: Option<String>,
+    pub position: Option<i64>,
+}
+
+/// A tag, with the number of actions and goals it is on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub count: i64,
+}
+
+/// A saved listing, which runs `odo action ls` or `odo goal ls` with its arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    pub name: String,
+    pub item: Item,
+    pub arguments: Vec<String>,
+}
+
+/// A setting, with its default value if it is not set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+}
+
+/// The next actions for a goal, or for no goal if `goal` is `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NextActions {
+    pub goal: Option<Goal>,
+    pub actions: Vec<Action>,
+}
+
+/// An item that a search found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Match {
+    Action(Action),
+    Goal(Goal),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Item {
+    Action,
+    Goal,
+}
+
+impl Item {
+    pub(crate) fn from_arg(arg: &str) -> Result<Self, Error> {
+        match arg {
+            "action" => Ok(Self::Action),
+            "goal" => Ok(Self::Goal),
+            _ => Err(Error::Usage(format!("invalid item: `{}`", arg))),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Action => "action",
+            Self::Goal => "goal",
+        }
+    }
+}
+
+/// Whether items are finished.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Open,
+    Done,
+    All,
+}
+
+impl Status {
+    fn condition(self) -> &'static str {
+        match self {
+            Self::Open => "done IS NULL",
+            Self::Done => "done IS NOT NULL",
+            Self::All => "1",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateField {
+    Due,
+    Scheduled,
+}
+
+impl DateField {
+    fn column(self) -> &'static str {
+        match self {
+            Self::Due => "due",
+            Self::Scheduled => "scheduled",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GoalType {
+    Parallel,
+    Sequential,
+}
+
+impl GoalType {
+    pub(crate) fn from_arg(arg: &str) -> Result<Self, Error> {
+        match arg {
+            "parallel" => Ok(Self::Parallel),
+            "sequential" => Ok(Self::Sequential),
+            _ => Err(Error::Usage(format!("invalid type: `{}`", arg))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Sort {
+    Id,
+    Due,
+    Scheduled,
+    Urgency,
+}
+
+impl Sort {
+    pub(crate) fn from_arg(arg: &str) -> Result<Self, Error> {
+        match arg {
+            "id" => Ok(Self::Id),
+            "due" => Ok(Self::Due),
+            "scheduled" => Ok(Self::Scheduled),
+            "urgency" => Ok(Self::Urgency),
+            _ => Err(Error::Usage(format!("invalid sort: `{}`", arg))),
+        }
+    }
+
+    // Items without the date sort last, the most urgent item sorts first, and ties go to the oldest
+    // item.
+    fn order(&self, urgency: &str) -> String {
+        match self {
+            Self::Id => "id".into(),
+            Self::Due => "due IS NULL, due, id".into(),
+            Self::Scheduled => "scheduled IS NULL, scheduled, id".into(),
+            Self::Urgency => format!("{} DESC, id", urgency),
+        }
+    }
+}
+
+// Which of the items with a given status a listing includes, and in what order.  Unfinished items
+// scheduled for a later day are left out unless `later` is set.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Selection {
+    pub(crate) later: bool,
+    pub(crate) overdue: bool,
+    pub(crate) due_before: Option<NaiveDate>,
+    pub(crate) include: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+    pub(crate) filter: Option<Filter>,
+    pub(crate) sort: Sort,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Self {
+            later: false,
+            overdue: false,
+            due_before: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            filter: None,
+            sort: Sort::Id,
+        }
+    }
+}
+
+impl Selection {
+    // Every item that `filter`, a filter expression, matches.
+    fn with_filter(filter: Option<&str>, item: Item) -> Result<Self, Error> {
+        Ok(Self {
+            later: true,
+            filter: filter
+                .map(|filter| Filter::parse(filter, item))
+                .transpose()
+                .map_err(Error::Usage)?,
+            ..Self::default()
+        })
+    }
+
+    // Tags are passed to SQLite as one comma-separated list, which tag names cannot contain.
+    fn tag_list(tags: &[String]) -> String {
+        format!(",{},", tags.join(","))
+    }
+
+    // The condition an item must meet to be listed, using the parameters that `params` binds.
+    fn condition(&self, item: Item) -> String {
+        format!(
+            "{} AND {} AND {} AND {}",
+            NOT_SCHEDULED,
+            DATE_FILTER,
+            tag_filter(item.name()),
+            self.filter.as_ref().map_or("1", Filter::sql)
+        )
+    }
+
+    fn params(&self) -> Vec<(String, Value)> {
+        let mut params = vec![
+            (":today".to_string(), Value::from(today().to_string())),
+            (":later".into(), Value::from(self.later)),
+            (":overdue".into(), Value::from(self.overdue)),
+            (
+                ":due_before".into(),
+                Value::from(self.due_before.map(|date| date.to_string())),
+            ),
+            (
+                ":include".into(),
+                Value::from(Self::tag_list(&self.include)),
+            ),
+            (
+                ":include_count".into(),
+                Value::from(self.include.len() as i64),
+            ),
+            (
+                ":exclude".into(),
+                Value::from(Self::tag_list(&self.exclude)),
+            ),
+        ];
+        if let Some(filter) = &self.filter {
+            params.extend_from_slice(filter.params());
+        }
+        params
+    }
+}
+
+// An unfinished item is scheduled if its start date has not arrived yet.
+const NOT_SCHEDULED: &str =
+    "(:later OR done IS NOT NULL OR scheduled IS NULL OR scheduled <= :today)";
+
+const DATE_FILTER: &str =
+    "(NOT :overdue OR due < :today) AND (:due_before IS NULL OR due < :due_before)";
+
+// An action's urgency counts whether an unfinished goal depends on it, and a goal's whether it is a
+// sub-goal of an unfinished goal.
+const ACTION_URGENCY: &str = "urgency(actions.priority, actions.due, actions.created, EXISTS (SELECT * FROM goal_actions JOIN goals AS urgent_goals ON urgent_goals.id = goal_actions.goal WHERE goal_actions.action = actions.id AND urgent_goals.done IS NULL))";
+
+const GOAL_URGENCY: &str = "urgency(goals.priority, goals.due, goals.created, EXISTS (SELECT * FROM goals AS parents WHERE parents.id = goals.parent AND parents.done IS NULL))";
+
+// A goal is stuck if it is unfinished but has no unfinished action to move it forward.
+const STUCK: &str = "goals.done IS NULL AND NOT EXISTS (SELECT * FROM goal_actions JOIN actions ON actions.id = goal_actions.action WHERE goal_actions.goal = goals.id AND actions.done IS NULL)";
+
+// An unfinished action linked to an unfinished goal is available unless the goal is sequential and
+// the action is not the goal's first unfinished action.
+const AVAILABLE: &str = "actions.done IS NULL AND goals.done IS NULL AND (NOT goals.sequential OR goal_actions.position = (SELECT min(first.position) FROM goal_actions AS first JOIN actions AS first_action ON first_action.id = first.action WHERE first.goal = goals.id AND first_action.done IS NULL))";
+
+// The default templates for `action ls` and `goal ls`, which have no value until one is set.
+const TEMPLATE_SETTINGS: &[&str] = &["template.action", "template.goal"];
+
+impl Store {
+    /// Opens the database at `path`, creating it if it does not exist and upgrading it if it is
+    /// from an older version.
+    pub fn open<T: Into<PathBuf>>(path: T) -> Result<Self, Error> {
+        Self::open_location(&Location::Path(path.into()))
+    }
+
+    /// Opens the database that odo uses when neither `--database` nor `--profile` is given.
+    pub fn open_default() -> Result<Self, Error> {
+        Self::open_location(&Location::Default)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Error> {
+        let connection = Connection::open_in_memory()
+            .map_err(|e| Error::Storage(format!("unable to open database: {}", e)))?;
+        Self::new(connection)
+    }
+
+    pub(crate) fn open_location(location: &Location) -> Result<Self, Error> {
+        Self::new(database::open(location)?)
+    }
+
+    fn new(connection: Connection) -> Result<Self, Error> {
+        database::initialize(&connection)
+            .map_err(|e| Error::Storage(format!("unable to initialize database: {}", e)))?;
+        Ok(Self { connection })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn add_action(&self, description: &str) -> Result<(), Error> {
+        self.connection
+            .execute(
+                "INSERT INTO actions (description, created) VALUES(?1, datetime('now'))",
+                rusqlite::params![description],
+            )
+            .map(|_| ())
+            .map_err(|e| {
+                if is_constraint_violation(&e) {
+                    Error::AlreadyExists("action already exists".into())
+                } else {
+                    Error::Storage(format!("unable to add action: {}", e))
+                }
+            })
+    }
+
+    pub fn action(&self, description: &str) -> Result<Action, Error> {
+        read_action(&self.connection, action_id(&self.connection, description)?)
+    }
+
+    /// The actions with `status` that `filter`, a filter expression as described in odo(1),
+    /// matches, including those scheduled for a later day, in the order that `odo action ls` lists
+    /// them.
+    pub fn list_actions(&self, status: Status, filter: Option<&str>) -> Result<Vec<Action>, Error> {
+        self.query_actions(status, None, &Selection::with_filter(filter, Item::Action)?)
+    }
+
+    // The actions with `status` that `selection` includes, limited to the context named `context`
+    // if one is given.
+    pub(crate) fn query_actions(
+        &self,
+        status: Status,
+        context: Option<&str>,
+        selection: &Selection,
+    ) -> Result<Vec<Action>, Error> {
+        let context = context
+            .map(|context| context_id(&self.connection, context))
+            .transpose()?;
+        let mut params = selection.params();
+        params.push((":context".into(), Value::from(context)));
+        read_actions(
+            &self.connection,
+            select_ids(
+                &self.connection,
+                &format!(
+                    "SELECT id FROM actions WHERE {} AND (:context IS NULL OR context = :context) AND {} ORDER BY {}",
+                    status.condition(),
+                    selection.condition(Item::Action),
+                    selection.sort.order(ACTION_URGENCY)
+                ),
+                named_params(&params).as_slice(),
+            )?,
+        )
+    }
+
+    pub fn annotate_action(&self, description: &str, text: &str) -> Result<(), Error> {
+        let id = action_id(&self.connection, description)?;
+        annotate(&self.connection, Item::Action, id, text)
+    }
+
+    /// An action's annotations, oldest first.
+    pub fn action_annotations(&self, description: &str) -> Result<Vec<Annotation>, Error> {
+        let id = action_id(&self.connection, description)?;
+        read_annotations(&self.connection, Item::Action, id)
+    }
+
+    /// Finishes an action, adding its next occurrence if it recurs, and returns the goals that are
+    /// left without an unfinished action.
+    pub fn complete_action(&self, description: &str) -> Result<Vec<Goal>, Error> {
+        let id = action_id(&self.connection, description)?;
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+        match transaction
+            .execute(
+                "UPDATE actions SET done = datetime('now') WHERE id = ?1 AND done IS NULL",
+                rusqlite::params![id],
+            )
+            .map_err(|e| Error::Storage(format!("unable to complete action: {}", e)))?
+        {
+            0 => return Err(Error::Constraint("action is already done".into())),
+            1 => {}
+            _ => unreachable!(),
+        }
+        repeat_action(&transaction, id)?;
+        transaction
+            .commit()
+            .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))?;
+        read_goals(
+            &self.connection,
+            select_ids(
+                &self.connection,
+                &format!(
+                    "SELECT id FROM goals JOIN goal_actions ON goal_actions.goal = goals.id WHERE goal_actions.action = ?1 AND {}",
+                    STUCK
+                ),
+                rusqlite::params![id],
+            )?,
+        )
+    }
+
+    pub fn remove_action(&self, description: &str) -> Result<(), Error> {
+        let id = action_id(&self.connection, description)?;
+        self.connection
+            .execute("DELETE FROM actions WHERE id = ?1", rusqlite::params![id])
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to remove action: {}", e)))
+    }
+
+    /// Puts an action in the context named `context`, which must exist, or in none.
+    pub fn set_action_context(
+        &self,
+        description: &str,
+        context: Option<&str>,
+    ) -> Result<(), Error> {
+        let id = action_id(&self.connection, description)?;
+        let context = context
+            .map(|context| context_id(&self.connection, &context_name(context)?))
+            .transpose()?;
+        self.connection
+            .execute(
+                "UPDATE actions SET context = ?1 WHERE id = ?2",
+                rusqlite::params![context, id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set context: {}", e)))
+    }
+
+    pub fn set_action_date(
+        &self,
+        description: &str,
+        field: DateField,
+        date: Option<NaiveDate>,
+    ) -> Result<(), Error> {
+        let id = action_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                &format!("UPDATE actions SET {} = ?1 WHERE id = ?2", field.column()),
+                rusqlite::params![date.map(|date| date.to_string()), id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set {} date: {}", field.column(), e)))
+    }
+
+    pub fn set_action_description(
+        &self,
+        description: &str,
+        new_description: &str,
+    ) -> Result<(), Error> {
+        let id = action_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                "UPDATE actions SET description = ?1 WHERE id = ?2",
+                rusqlite::params![new_description, id],
+            )
+            .map(|_| ())
+            .map_err(|e| {
+                if is_constraint_violation(&e) {
+                    Error::AlreadyExists("action already exists".into())
+                } else {
+                    Error::Storage(format!("unable to set description: {}", e))
+                }
+            })
+    }
+
+    /// Sets an action's note, dropping trailing whitespace.  A note with nothing else in it is
+    /// removed.
+    pub fn set_action_note(&self, description: &str, note: Option<&str>) -> Result<(), Error> {
+        let id = action_id(&self.connection, description)?;
+        save_note(&self.connection, Item::Action, id, note)
+    }
+
+    /// Sets an action's priority, from 1 (high) to 5 (low).
+    pub fn set_action_priority(
+        &self,
+        description: &str,
+        priority: Option<i64>,
+    ) -> Result<(), Error> {
+        let priority = check_priority(priority)?;
+        let id = action_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                "UPDATE actions SET priority = ?1 WHERE id = ?2",
+                rusqlite::params![priority, id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set priority: {}", e)))
+    }
+
+    /// Sets the rule an action recurs by.  Rules that leave the weekday or day of the month open
+    /// take it from the action's due date, its scheduled date, or today.
+    pub fn set_action_recurrence(
+        &self,
+        description: &str,
+        recurrence: Option<Recurrence>,
+    ) -> Result<(), Error> {
+        let id = action_id(&self.connection, description)?;
+        let recurrence = match recurrence {
+            Some(recurrence) => {
+                let date = self
+                    .connection
+                    .query_row(
+                        "SELECT coalesce(due, scheduled) FROM actions WHERE id = ?1",
+                        rusqlite::params![id],
+                        |row| row.get::<_, Option<String>>(0),
+                    )
+                    .map_err(|e| Error::Storage(format!("unable to read action: {}", e)))?
+                    .map(|date| parse_date(&date, today()))
+                    .transpose()
+                    .map_err(Error::Storage)?
+                    .unwrap_or_else(today);
+                Some(recurrence.anchor(date).to_string())
+            }
+            None => None,
+        };
+        self.connection
+            .execute(
+                "UPDATE actions SET recurrence = ?1 WHERE id = ?2",
+                rusqlite::params![recurrence, id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set recurrence: {}", e)))
+    }
+
+    /// Tags an action, creating any tags that do not exist yet.
+    pub fn tag_action(&self, description: &str, tags: &[String]) -> Result<(), Error> {
+        let tags = tag_names(tags)?;
+        let id = action_id(&self.connection, description)?;
+        tag(&self.connection, Item::Action, id, tags)
+    }
+
+    /// Removes tags from an action, forgetting those that are no longer on anything.
+    pub fn untag_action(&self, description: &str, tags: &[String]) -> Result<(), Error> {
+        let tags = tag_names(tags)?;
+        let id = action_id(&self.connection, description)?;
+        untag(&self.connection, Item::Action, id, tags)
+    }
+
+    pub fn add_goal(
+        &self,
+        description: &str,
+        action: Option<&str>,
+        parent: Option<&str>,
+        goal_type: GoalType,
+    ) -> Result<(), Error> {
+        let action = action
+            .map(|action| action_id(&self.connection, action))
+            .transpose()?;
+        let parent = parent
+            .map(|parent| goal_id(&self.connection, parent))
+            .transpose()?;
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+        transaction
+            .execute(
+                "INSERT INTO goals (description, parent, sequential, created) VALUES(?1, ?2, ?3, datetime('now'))",
+                rusqlite::params![description, parent, goal_type == GoalType::Sequential],
+            )
+            .map_err(|e| {
+                if is_constraint_violation(&e) {
+                    Error::AlreadyExists("goal already exists".into())
+                } else {
+                    Error::Storage(format!("unable to add goal: {}", e))
+                }
+            })?;
+        if let Some(action) = action {
+            transaction
+                .execute(
+                    "INSERT INTO goal_actions VALUES(?1, ?2, 0)",
+                    rusqlite::params![transaction.last_insert_rowid(), action],
+                )
+                .map_err(|e| Error::Storage(format!("unable to add action: {}", e)))?;
+        }
+        transaction
+            .commit()
+            .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))
+    }
+
+    pub fn goal(&self, description: &str) -> Result<Goal, Error> {
+        read_goal(&self.connection, goal_id(&self.connection, description)?)
+    }
+
+    /// The goals with `status` that `filter`, a filter expression as described in odo(1), matches,
+    /// including those scheduled for a later day, in the order that `odo goal ls` lists them.  If
+    /// `stuck` is set, only the unfinished goals without an unfinished action are included.
+    pub fn list_goals(
+        &self,
+        status: Status,
+        stuck: bool,
+        filter: Option<&str>,
+    ) -> Result<Vec<Goal>, Error> {
+        self.query_goals(status, stuck, &Selection::with_filter(filter, Item::Goal)?)
+    }
+
+    // The goals with `status` that `selection` includes, only those without an unfinished action if
+    // `stuck` is set.
+    pub(crate) fn query_goals(
+        &self,
+        status: Status,
+        stuck: bool,
+        selection: &Selection,
+    ) -> Result<Vec<Goal>, Error> {
+        read_goals(
+            &self.connection,
+            select_ids(
+                &self.connection,
+                &format!(
+                    "SELECT id FROM goals WHERE {} AND {} AND {} ORDER BY {}",
+                    status.condition(),
+                    if stuck { STUCK } else { "1" },
+                    selection.condition(Item::Goal),
+                    selection.sort.order(GOAL_URGENCY)
+                ),
+                named_params(&selection.params()).as_slice(),
+            )?,
+        )
+    }
+
+    /// A goal's unfinished actions, in order.
+    pub fn goal_actions(&self, description: &str) -> Result<Vec<Action>, Error> {
+        let id = goal_id(&self.connection, description)?;
+        read_actions(
+            &self.connection,
+            select_ids(
+                &self.connection,
+                "SELECT id FROM actions JOIN goal_actions ON goal_actions.action = actions.id WHERE goal_actions.goal = ?1 AND actions.done IS NULL ORDER BY goal_actions.position",
+                rusqlite::params![id],
+            )?,
+        )
+    }
+
+    /// Links an action to a goal, after the goal's other actions.
+    pub fn add_goal_action(&self, description: &str, action: &str) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        let action = action_id(&self.connection, action)?;
+        self.connection
+            .execute(
+                "INSERT INTO goal_actions SELECT ?1, ?2, ifnull(max(position) + 1, 0) FROM goal_actions WHERE goal = ?1",
+                rusqlite::params![id, action],
+            )
+            .map(|_| ())
+            .map_err(|e| {
+                if is_constraint_violation(&e) {
+                    Error::AlreadyExists("goal already has action".into())
+                } else {
+                    Error::Storage(format!("unable to add action: {}", e))
+                }
+            })
+    }
+
+    pub fn annotate_goal(&self, description: &str, text: &str) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        annotate(&self.connection, Item::Goal, id, text)
+    }
+
+    /// A goal's annotations, oldest first.
+    pub fn goal_annotations(&self, description: &str) -> Result<Vec<Annotation>, Error> {
+        let id = goal_id(&self.connection, description)?;
+        read_annotations(&self.connection, Item::Goal, id)
+    }
+
+    pub fn complete_goal(&self, description: &str) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        match self
+            .connection
+            .execute(
+                "UPDATE goals SET done = datetime('now') WHERE id = ?1 AND done IS NULL",
+                rusqlite::params![id],
+            )
+            .map_err(|e| Error::Storage(format!("unable to complete goal: {}", e)))?
+        {
+            0 => Err(Error::Constraint("goal is already done".into())),
+            1 => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Moves one of a goal's actions to `position`, counting from 1, among its unfinished actions.
+    /// Links to finished actions are kept, after the unfinished ones.
+    pub fn move_goal_action(
+        &self,
+        description: &str,
+        position: usize,
+        action: &str,
+    ) -> Result<(), Error> {
+        if position == 0 {
+            return Err(Error::Usage("invalid position: `0`".into()));
+        }
+        let id = goal_id(&self.connection, description)?;
+        let action = action_id(&self.connection, action)?;
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+        let mut actions = select_ids(
+            &transaction,
+            "SELECT action FROM goal_actions JOIN actions ON actions.id = goal_actions.action WHERE goal_actions.goal = ?1 ORDER BY actions.done IS NOT NULL, goal_actions.position",
+            rusqlite::params![id],
+        )?;
+        let index = actions
+            .iter()
+            .position(|&linked| linked == action)
+            .ok_or_else(|| Error::NotFound("goal does not have action".into()))?;
+        actions.remove(index);
+        actions.insert((position - 1).min(actions.len()), action);
+        for (position, action) in actions.iter().enumerate() {
+            transaction
+                .execute(
+                    "UPDATE goal_actions SET position = ?1 WHERE goal = ?2 AND action = ?3",
+                    rusqlite::params![position as i64, id, action],
+                )
+                .map_err(|e| Error::Storage(format!("unable to move action: {}", e)))?;
+        }
+        transaction
+            .commit()
+            .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))
+    }
+
+    /// Removes a goal, along with its sub-goals if `recursive` is set.  Without it, a goal that has
+    /// sub-goals is not removed, so that sub-goals are never silently orphaned.
+    pub fn remove_goal(&self, description: &str, recursive: bool) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        if recursive {
+            database::snapshot(&self.connection)?;
+            self.connection
+                .execute(
+                    "WITH RECURSIVE descendants (id) AS (SELECT ?1 UNION ALL SELECT goals.id FROM goals JOIN descendants ON goals.parent = descendants.id) DELETE FROM goals WHERE id IN descendants",
+                    rusqlite::params![id],
+                )
+                .map(|_| ())
+                .map_err(|e| Error::Storage(format!("unable to remove goal: {}", e)))
+        } else {
+            let has_subgoals = self
+                .connection
+                .prepare("SELECT * FROM goals WHERE parent = ?1")
+                .and_then(|mut statement| statement.exists(rusqlite::params![id]))
+                .map_err(|e| Error::Storage(format!("unable to find sub-goals: {}", e)))?;
+            if has_subgoals {
+                return Err(Error::Constraint("goal has sub-goals".into()));
+            }
+            self.connection
+                .execute("DELETE FROM goals WHERE id = ?1", rusqlite::params![id])
+                .map(|_| ())
+                .map_err(|e| Error::Storage(format!("unable to remove goal: {}", e)))
+        }
+    }
+
+    pub fn remove_goal_action(&self, description: &str, action: &str) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        let action = action_id(&self.connection, action)?;
+        match self
+            .connection
+            .execute(
+                "DELETE FROM goal_actions WHERE goal = ?1 AND action = ?2",
+                rusqlite::params![id, action],
+            )
+            .map_err(|e| Error::Storage(format!("unable to remove action: {}", e)))?
+        {
+            0 => Err(Error::NotFound("goal does not have action".into())),
+            1 => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Makes `action` the goal's only action.
+    pub fn set_goal_action(&self, description: &str, action: &str) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        let action = action_id(&self.connection, action)?;
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+        transaction
+            .execute(
+                "DELETE FROM goal_actions WHERE goal = ?1",
+                rusqlite::params![id],
+            )
+            .and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO goal_actions VALUES(?1, ?2, 0)",
+                    rusqlite::params![id, action],
+                )
+            })
+            .map_err(|e| Error::Storage(format!("unable to set action: {}", e)))?;
+        transaction
+            .commit()
+            .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))
+    }
+
+    pub fn set_goal_date(
+        &self,
+        description: &str,
+        field: DateField,
+        date: Option<NaiveDate>,
+    ) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                &format!("UPDATE goals SET {} = ?1 WHERE id = ?2", field.column()),
+                rusqlite::params![date.map(|date| date.to_string()), id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set {} date: {}", field.column(), e)))
+    }
+
+    pub fn set_goal_description(
+        &self,
+        description: &str,
+        new_description: &str,
+    ) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                "UPDATE goals SET description = ?1 WHERE id = ?2",
+                rusqlite::params![new_description, id],
+            )
+            .map(|_| ())
+            .map_err(|e| {
+                if is_constraint_violation(&e) {
+                    Error::AlreadyExists("goal already exists".into())
+                } else {
+                    Error::Storage(format!("unable to set description: {}", e))
+                }
+            })
+    }
+
+    /// Sets a goal's note, dropping trailing whitespace.  A note with nothing else in it is
+    /// removed.
+    pub fn set_goal_note(&self, description: &str, note: Option<&str>) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        save_note(&self.connection, Item::Goal, id, note)
+    }
+
+    /// Makes a goal a sub-goal of `parent`, or of no goal.
+    pub fn set_goal_parent(&self, description: &str, parent: Option<&str>) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        let parent = match parent {
+            Some(parent) => Some(goal_id(&self.connection, parent)?),
+            None => None,
+        };
+        if let Some(parent) = parent {
+            let cycle = self
+                .connection
+                .prepare(
+                    "WITH RECURSIVE ancestors (id) AS (SELECT ?1 UNION ALL SELECT goals.parent FROM goals JOIN ancestors ON goals.id = ancestors.id WHERE goals.parent IS NOT NULL) SELECT * FROM ancestors WHERE id = ?2",
+                )
+                .and_then(|mut statement| statement.exists(rusqlite::params![parent, id]))
+                .map_err(|e| Error::Storage(format!("unable to find ancestors: {}", e)))?;
+            if cycle {
+                return Err(Error::Constraint(
+                    "goal cannot be a sub-goal of itself".into(),
+                ));
+            }
+        }
+        self.connection
+            .execute(
+                "UPDATE goals SET parent = ?1 WHERE id = ?2",
+                rusqlite::params![parent, id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set parent: {}", e)))
+    }
+
+    /// Sets a goal's priority, from 1 (high) to 5 (low).
+    pub fn set_goal_priority(&self, description: &str, priority: Option<i64>) -> Result<(), Error> {
+        let priority = check_priority(priority)?;
+        let id = goal_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                "UPDATE goals SET priority = ?1 WHERE id = ?2",
+                rusqlite::params![priority, id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set priority: {}", e)))
+    }
+
+    pub fn set_goal_type(&self, description: &str, goal_type: GoalType) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                "UPDATE goals SET sequential = ?1 WHERE id = ?2",
+                rusqlite::params![goal_type == GoalType::Sequential, id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to set type: {}", e)))
+    }
+
+    /// Tags a goal, creating any tags that do not exist yet.
+    pub fn tag_goal(&self, description: &str, tags: &[String]) -> Result<(), Error> {
+        let tags = tag_names(tags)?;
+        let id = goal_id(&self.connection, description)?;
+        tag(&self.connection, Item::Goal, id, tags)
+    }
+
+    /// Removes tags from a goal, forgetting those that are no longer on anything.
+    pub fn untag_goal(&self, description: &str, tags: &[String]) -> Result<(), Error> {
+        let tags = tag_names(tags)?;
+        let id = goal_id(&self.connection, description)?;
+        untag(&self.connection, Item::Goal, id, tags)
+    }
+
+    /// Unlinks all of a goal's actions.
+    pub fn unset_goal_action(&self, description: &str) -> Result<(), Error> {
+        let id = goal_id(&self.connection, description)?;
+        self.connection
+            .execute(
+                "DELETE FROM goal_actions WHERE goal = ?1",
+                rusqlite::params![id],
+            )
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to unset action: {}", e)))
+    }
+
+    /// The available actions, each beneath the goals it moves forward, followed, beneath no goal,
+    /// by the unfinished actions that no unfinished goal references.  Only actions in the context
+    /// named `context` are included if one is given.  The most urgent actions come first, and goals
+    /// are ordered by their most urgent action.
+    pub fn next_actions(&self, context: Option<&str>) -> Result<Vec<NextActions>, Error> {
+        let connection = &self.connection;
+        let context = context
+            .map(|context| context_id(connection, &context_name(context)?))
+            .transpose()?;
+        let today = today().to_string();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT goals.id, actions.id, {} FROM actions JOIN goal_actions ON goal_actions.action = actions.id JOIN goals ON goals.id = goal_actions.goal WHERE {} AND (actions.scheduled IS NULL OR actions.scheduled <= ?2) AND (goals.scheduled IS NULL OR goals.scheduled <= ?2) AND (?1 IS NULL OR actions.context = ?1) ORDER BY goals.id, 3 DESC, goal_actions.position",
+                ACTION_URGENCY, AVAILABLE
+            ))
+            .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+        let rows = statement
+            .query_map(rusqlite::params![context, today], |row| {
+                Ok((
+                    row.get::<usize, i64>(0)?,
+                    row.get::<usize, i64>(1)?,
+                    row.get::<usize, f64>(2)?,
+                ))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| Error::Storage(format!("unable to read actions: {}", e)))?;
+        let mut goals = Vec::<(i64, f64, Vec<i64>)>::new();
+        for (goal, id, urgency) in rows {
+            match goals.last_mut() {
+                Some((previous_goal, _, actions)) if *previous_goal == goal => actions.push(id),
+                _ => goals.push((goal, urgency, vec![id])),
+            }
+        }
+        goals.sort_by(|(_, a, _), (_, b, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        let unreferenced = select_ids(
+            connection,
+            &format!("SELECT id FROM actions WHERE done IS NULL AND (scheduled IS NULL OR scheduled <= ?2) AND (?1 IS NULL OR context = ?1) AND NOT EXISTS (SELECT * FROM goal_actions JOIN goals ON goals.id = goal_actions.goal WHERE goal_actions.action = actions.id AND goals.done IS NULL) ORDER BY {} DESC, id", ACTION_URGENCY),
+            rusqlite::params![context, today],
+        )?;
+        let mut next = goals
+            .into_iter()
+            .map(|(goal, _, actions)| {
+                Ok(NextActions {
+                    goal: Some(read_goal(connection, goal)?),
+                    actions: read_actions(connection, actions)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if !unreferenced.is_empty() {
+            next.push(NextActions {
+                goal: None,
+                actions: read_actions(connection, unreferenced)?,
+            });
+        }
+        Ok(next)
+    }
+
+    /// The unfinished items that `query`, an FTS5 query, matches, best first, weighting matches in
+    /// descriptions above those in notes.  Done items are included too if `all` is set.
+    pub fn search(&self, query: &str, all: bool) -> Result<Vec<Match>, Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT 'action', actions.id, bm25(action_search, 2.0, 1.0) AS rank FROM action_search JOIN actions ON actions.id = action_search.rowid WHERE action_search MATCH ?1 AND (?2 OR actions.done IS NULL) UNION ALL SELECT 'goal', goals.id, bm25(goal_search, 2.0, 1.0) FROM goal_search JOIN goals ON goals.id = goal_search.rowid WHERE goal_search MATCH ?1 AND (?2 OR goals.done IS NULL) ORDER BY rank, 1, 2")
+            .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+        let results = statement
+            .query_map(rusqlite::params![query, all], |row| {
+                Ok((row.get::<usize, String>(0)?, row.get::<usize, i64>(1)?))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            // The statement is known to be valid, so a generic error can only come from the query.
+            .map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(
+                    libsqlite3_sys::Error {
+                        code: libsqlite3_sys::ErrorCode::Unknown,
+                        ..
+                    },
+                    _,
+                ) => Error::Usage(format!("invalid query: `{}`", query)),
+                _ => Error::Storage(format!("unable to search: {}", e)),
+            })?;
+        results
+            .into_iter()
+            .map(|(item, id)| match Item::from_arg(&item)? {
+                Item::Action => read_action(&self.connection, id).map(Match::Action),
+                Item::Goal => read_goal(&self.connection, id).map(Match::Goal),
+            })
+            .collect()
+    }
+
+    /// Adds a context, which is listed at `position` if one is given and after those with a
+    /// position otherwise.
+    pub fn add_context(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        position: Option<i64>,
+    ) -> Result<(), Error> {
+        self.connection
+            .execute(
+                "INSERT INTO contexts (name, description, position) VALUES(?1, ?2, ?3)",
+                rusqlite::params![context_name(name)?, description, position],
+            )
+            .map(|_| ())
+            .map_err(|e| {
+                if is_constraint_violation(&e) {
+                    Error::AlreadyExists("context already exists".into())
+                } else {
+                    Error::Storage(format!("unable to add context: {}", e))
+                }
+            })
+    }
+
+    pub fn contexts(&self) -> Result<Vec<Context>, Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, description, position FROM contexts ORDER BY position IS NULL, position, name")
+            .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+        let contexts = statement
+            .query_map([], |row| {
+                Ok(Context {
+                    name: row.get("name")?,
+                    description: row.get("description")?,
+                    position: row.get("position")?,
+                })
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| Error::Storage(format!("unable to read contexts: {}", e)))?;
+        Ok(contexts)
+    }
+
+    /// Removes a context, taking the actions in it out of it.
+    pub fn remove_context(&self, name: &str) -> Result<(), Error> {
+        match self
+            .connection
+            .execute(
+                "DELETE FROM contexts WHERE name = ?1",
+                rusqlite::params![context_name(name)?],
+            )
+            .map_err(|e| Error::Storage(format!("unable to remove context: {}", e)))?
+        {
+            0 => Err(Error::NotFound("context does not exist".into())),
+            1 => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// The tags that are on an action or goal, in order of name.
+    pub fn tags(&self) -> Result<Vec<Tag>, Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, (SELECT count(*) FROM action_tags WHERE action_tags.tag = tags.id) + (SELECT count(*) FROM goal_tags WHERE goal_tags.tag = tags.id) AS count FROM tags WHERE count > 0 ORDER BY name")
+            .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+        let tags = statement
+            .query_map([], |row| {
+                Ok(Tag {
+                    name: row.get("name")?,
+                    count: row.get("count")?,
+                })
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| Error::Storage(format!("unable to read tags: {}", e)))?;
+        Ok(tags)
+    }
+
+    /// Renames a tag on every action and goal.  Renaming a tag to one that already exists merges
+    /// the two.
+    pub fn rename_tag(&self, old_name: &str, new_name: &str) -> Result<(), Error> {
+        let old_name = tag_name(old_name)?;
+        let new_name = tag_name(new_name)?;
+        let old_id = tag_id(&self.connection, &old_name)?
+            .ok_or_else(|| Error::NotFound("tag does not exist".into()))?;
+        database::snapshot(&self.connection)?;
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+        match tag_id(&transaction, &new_name)? {
+            Some(new_id) if new_id != old_id => transaction.execute_batch(&format!(
+                "INSERT OR IGNORE INTO action_tags (action, tag) SELECT action, {new} FROM action_tags WHERE tag = {old}; INSERT OR IGNORE INTO goal_tags (goal, tag) SELECT goal, {new} FROM goal_tags WHERE tag = {old}; DELETE FROM tags WHERE id = {old};",
+                new = new_id,
+                old = old_id
+            )),
+            Some(_) => Ok(()),
+            None => transaction
+                .execute(
+                    "UPDATE tags SET name = ?1 WHERE id = ?2",
+                    rusqlite::params![new_name, old_id],
+                )
+                .map(|_| ()),
+        }
+        .map_err(|e| Error::Storage(format!("unable to rename tag: {}", e)))?;
+        transaction
+            .commit()
+            .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))
+    }
+
+    /// Saves a listing of `item`s as a view called `name`.  Only the views called `default` may
+    /// exist for both actions and goals, since other names must say which view `odo <view>` runs.
+    pub fn add_view(&self, name: &str, item: Item, arguments: &[String]) -> Result<(), Error> {
+        command::check_view(name, item, arguments)?;
+        let item_filter = if name == "default" { Some(item) } else { None };
+        if self.view(name, item_filter)?.is_some() {
+            return Err(Error::AlreadyExists("view already exists".into()));
+        }
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+        transaction
+            .execute(
+                "INSERT INTO views (name, item) VALUES(?1, ?2)",
+                rusqlite::params![name, item.name()],
+            )
+            .map_err(|e| Error::Storage(format!("unable to add view: {}", e)))?;
+        let id = transaction.last_insert_rowid();
+        for (position, argument) in arguments.iter().enumerate() {
+            transaction
+                .execute(
+                    "INSERT INTO view_arguments (view, position, argument) VALUES(?1, ?2, ?3)",
+                    rusqlite::params![id, position as i64, argument],
+                )
+                .map_err(|e| Error::Storage(format!("unable to add view: {}", e)))?;
+        }
+        transaction
+            .commit()
+            .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))
+    }
+
+    /// The view called `name` that lists `item`, or, if `item` is not given, the action view
+    /// before the goal view.
+    pub fn view(&self, name: &str, item: Option<Item>) -> Result<Option<View>, Error> {
+        find_view(&self.connection, name, item)?
+            .map(|(id, item)| {
+                Ok(View {
+                    name: name.into(),
+                    item,
+                    arguments: view_arguments(&self.connection, id)?,
+                })
+            })
+            .transpose()
+    }
+
+    /// Every view, in order of name and then of item.
+    pub fn views(&self) -> Result<Vec<View>, Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, name, item FROM views ORDER BY name, item")
+            .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+        let views = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<usize, i64>(0)?,
+                    row.get::<usize, String>(1)?,
+                    row.get::<usize, String>(2)?,
+                ))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| Error::Storage(format!("unable to read views: {}", e)))?;
+        views
+            .into_iter()
+            .map(|(id, name, item)| {
+                Ok(View {
+                    name,
+                    item: Item::from_arg(&item)?,
+                    arguments: view_arguments(&self.connection, id)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Removes the view that `view` would find.
+    pub fn remove_view(&self, name: &str, item: Option<Item>) -> Result<(), Error> {
+        let (id, _) = find_view(&self.connection, name, item)?
+            .ok_or_else(|| Error::NotFound("view does not exist".into()))?;
+        self.connection
+            .execute("DELETE FROM views WHERE id = ?1", rusqlite::params![id])
+            .map(|_| ())
+            .map_err(|e| Error::Storage(format!("unable to remove view: {}", e)))
+    }
+
+    /// The value of the setting called `name`, or `None` if it is not set.
+    pub fn config(&self, name: &str) -> Result<Option<String>, Error> {
+        check_setting_name(name)?;
+        config_value(&self.connection, name)
+    }
+
+    /// Every setting, with the default for those that are not set.  Templates have no default, so
+    /// they are only included once they are set.
+    pub fn settings(&self) -> Result<Vec<Setting>, Error> {
+        let mut settings = Vec::new();
+        for (name, default) in database::RETENTION {
+            let value =
+                config_value(&self.connection, name)?.unwrap_or_else(|| default.to_string());
+            settings.push(Setting {
+                name: name.to_string(),
+                value,
+            });
+        }
+        for (name, default) in urgency::COEFFICIENTS {
+            let value =
+                config_value(&self.connection, name)?.unwrap_or_else(|| default.to_string());
+            settings.push(Setting {
+                name: name.to_string(),
+                value,
+            });
+        }
+        for name in TEMPLATE_SETTINGS {
+            if let Some(value) = config_value(&self.connection, name)? {
+                settings.push(Setting {
+                    name: name.to_string(),
+                    value,
+                });
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Sets the setting called `name`, or unsets it so that it takes its default.
+    pub fn set_config(&self, name: &str, value: Option<&str>) -> Result<(), Error> {
+        check_setting_name(name)?;
+        match value {
+            Some(value) => {
+                check_setting_value(name, value)?;
+                self.connection.execute(
+                    "INSERT INTO config (name, value) VALUES(?1, ?2) ON CONFLICT (name) DO UPDATE SET value = excluded.value",
+                    rusqlite::params![name, value],
+                )
+            }
+            None => self.connection.execute(
+                "DELETE FROM config WHERE name = ?1",
+                rusqlite::params![name],
+            ),
+        }
+        .map(|_| ())
+        .map_err(|e| Error::Storage(format!("unable to set setting: {}", e)))
+    }
+
+    /// Backs the database up to `path`.
+    pub fn backup(&self, path: &Path) -> Result<(), Error> {
+        database::backup(&self.connection, path)
+    }
+
+    /// Backs the database up into the directory beside it where odo keeps its backups, pruning
+    /// those beyond the retention settings, and returns the path of the backup.
+    pub fn snapshot(&self) -> Result<PathBuf, Error> {
+        database::snapshot(&self.connection)?
+            .ok_or_else(|| Error::Storage("unable to back up a database that is not a file".into()))
+    }
+
+    /// Replaces the database with the backup at `path`.
+    pub fn restore(&self, path: &Path) -> Result<(), Error> {
+        database::restore(&self.connection, path)
+    }
+
+    /// The problems that `odo doctor` reports.
+    pub fn check(&self) -> Result<Vec<Problem>, Error> {
+        doctor::check(&self.connection)
+    }
+
+    /// Fixes every problem that can be, after backing the database up if there are any, and
+    /// vacuums the database.  Returns each problem along with whether it was fixed.
+    pub fn repair(&self) -> Result<Vec<(Problem, bool)>, Error> {
+        let problems = doctor::check(&self.connection)?;
+        if problems.iter().any(Problem::is_fixable) {
+            database::snapshot(&self.connection)?;
+        }
+        let mut repaired = Vec::new();
+        for problem in problems {
+            let fixed = problem.fix(&self.connection)?;
+            repaired.push((problem, fixed));
+        }
+        self.connection
+            .execute_batch("VACUUM")
+            .map_err(|e| Error::Storage(format!("unable to vacuum database: {}", e)))?;
+        Ok(repaired)
+    }
+}
+
+// The command line checks these when it parses its arguments, but other callers may not have.
+fn check_priority(priority: Option<i64>) -> Result<Option<i64>, Error> {
+    match priority {
+        Some(priority) if !(1..=5).contains(&priority) => {
+            Err(Error::Usage(format!("invalid priority: `{}`", priority)))
+        }
+        _ => Ok(priority),
+    }
+}
+
+fn tag_name(name: &str) -> Result<String, Error> {
+    command::tag_name(name.into()).map_err(Error::Usage)
+}
+
+fn tag_names(tags: &[String]) -> Result<Vec<String>, Error> {
+    tags.iter().map(|tag| tag_name(tag)).collect()
+}
+
+fn context_name(name: &str) -> Result<String, Error> {
+    command::context_name(name.into()).map_err(Error::Usage)
+}
+
+pub(crate) fn check_setting_name(name: &str) -> Result<(), Error> {
+    if urgency::COEFFICIENTS
+        .iter()
+        .any(|(setting, _)| *setting == name)
+        || database::RETENTION
+            .iter()
+            .any(|(setting, _)| *setting == name)
+        || TEMPLATE_SETTINGS.contains(&name)
+    {
+        Ok(())
+    } else {
+        Err(Error::Usage(format!("no such setting: `{}`", name)))
+    }
+}
+
+// Templates must parse, backup retention is counted in whole backups or days, and urgency
+// coefficients may be any finite number.
+pub(crate) fn check_setting_value(name: &str, value: &str) -> Result<(), Error> {
+    if TEMPLATE_SETTINGS.contains(&name) {
+        Template::parse(value, TEMPLATE_FIELDS).map_err(Error::Usage)?;
+    } else if name.starts_with("backup.") {
+        if value.parse::<u32>().is_err() {
+            return Err(Error::Usage(format!("invalid value: `{}`", value)));
+        }
+    } else if !value.parse::<f64>().is_ok_and(f64::is_finite) {
+        return Err(Error::Usage(format!("invalid value: `{}`", value)));
+    }
+    Ok(())
+}
+
+// Borrows owned parameters in the form rusqlite binds.
+fn named_params(params: &[(String, Value)]) -> Vec<(&str, &dyn ToSql)> {
+    params
+        .iter()
+        .map(|(name, value)| (name.as_str(), value as &dyn ToSql))
+        .collect()
+}
+
+// Items must have every included tag and no excluded one.
+fn tag_filter(item: &str) -> String {
+    format!(
+        "(SELECT count(*) FROM {item}_tags JOIN tags ON tags.id = {item}_tags.tag WHERE {item}_tags.{item} = {item}s.id AND instr(:include, ',' || tags.name || ',')) = :include_count AND NOT EXISTS (SELECT * FROM {item}_tags JOIN tags ON tags.id = {item}_tags.tag WHERE {item}_tags.{item} = {item}s.id AND instr(:exclude, ',' || tags.name || ','))",
+        item = item
+    )
+}
+
+// Adds the next occurrence of a finished recurring action, moving it to the due date (or, failing
+// that, the scheduled date) that the rule gives, giving it the same tags and note, and handing it
+// the unfinished goals' links.
+fn repeat_action(connection: &Connection, id: i64) -> Result<(), Error> {
+    let (description, context, due, scheduled, recurrence, priority, note) = connection
+        .query_row(
+            "SELECT description, context, due, scheduled, recurrence, priority, note FROM actions WHERE id = ?1",
+            rusqlite::params![id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<i64>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )
+        .map_err(|e| Error::Storage(format!("unable to read action: {}", e)))?;
+    let recurrence = match recurrence {
+        Some(recurrence) => Recurrence::from_arg(&recurrence).map_err(Error::Storage)?,
+        None => return Ok(()),
+    };
+    let due = due
+        .map(|due| parse_date(&due, today()))
+        .transpose()
+        .map_err(Error::Storage)?;
+    let scheduled = scheduled
+        .map(|scheduled| parse_date(&scheduled, today()))
+        .transpose()
+        .map_err(Error::Storage)?;
+    let today = today();
+    let date = due.or(scheduled).unwrap_or(today);
+    // Rules stored before they were anchored are anchored now, so that the copies do not drift.
+    let recurrence = recurrence.anchor(date);
+    let next = recurrence
+        .next(date, today)
+        .ok_or_else(|| Error::Constraint("unable to compute next occurrence".into()))?;
+    // A scheduled date keeps its distance from the due date, and an action with neither date is
+    // hidden until the next occurrence.
+    let offset = next.signed_duration_since(date);
+    let (due, scheduled) = if due.is_some() {
+        (
+            Some(next),
+            scheduled.and_then(|scheduled| scheduled.checked_add_signed(offset)),
+        )
+    } else {
+        (None, Some(next))
+    };
+    connection
+        .execute(
+            "INSERT INTO actions (description, context, due, scheduled, recurrence, priority, note, created) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'))",
+            rusqlite::params![
+                description,
+                context,
+                due.map(|due| due.to_string()),
+                scheduled.map(|scheduled| scheduled.to_string()),
+                recurrence.to_string(),
+                priority,
+                note
+            ],
+        )
+        .map_err(|e| {
+            if is_constraint_violation(&e) {
+                Error::AlreadyExists("action already exists".into())
+            } else {
+                Error::Storage(format!("unable to repeat action: {}", e))
+            }
+        })?;
+    let next_id = connection.last_insert_rowid();
+    connection
+        .execute(
+            "INSERT INTO action_tags SELECT ?1, tag FROM action_tags WHERE action = ?2",
+            rusqlite::params![next_id, id],
+        )
+        .map_err(|e| Error::Storage(format!("unable to copy tags: {}", e)))?;
+    connection
+        .execute(
+            "UPDATE goal_actions SET action = ?1 WHERE action = ?2 AND goal IN (SELECT id FROM goals WHERE done IS NULL)",
+            rusqlite::params![next_id, id],
+        )
+        .map(|_| ())
+        .map_err(|e| Error::Storage(format!("unable to move goal actions: {}", e)))
+}
+
+fn read_action(connection: &Connection, id: i64) -> Result<Action, Error> {
+    let mut action = connection
+        .query_row(
+            "SELECT actions.id AS id, actions.description AS description, actions.done AS done, actions.created AS created, actions.due AS due, actions.scheduled AS scheduled, actions.priority AS priority, contexts.name AS context, actions.recurrence AS recurrence, actions.note AS note FROM actions LEFT JOIN contexts ON contexts.id = actions.context WHERE actions.id = ?1",
+            rusqlite::params![id],
+            |row| {
+                Ok(Action {
+                    id: row.get("id")?,
+                    description: row.get("description")?,
+                    done: row.get("done")?,
+                    created: row.get("created")?,
+                    due: row.get("due")?,
+                    scheduled: row.get("scheduled")?,
+                    priority: row.get("priority")?,
+                    context: row.get("context")?,
+                    recurrence: row.get("recurrence")?,
+                    goals: Vec::new(),
+                    tags: Vec::new(),
+                    note: row.get("note")?,
+                })
+            },
+        )
+        .map_err(|e| Error::Storage(format!("unable to read action: {}", e)))?;
+    action.goals = select_ids(
+        connection,
+        "SELECT goal FROM goal_actions WHERE action = ?1 ORDER BY goal",
+        rusqlite::params![id],
+    )?;
+    action.tags = read_tags(connection, Item::Action, id)?;
+    Ok(action)
+}
+
+fn read_actions(connection: &Connection, ids: Vec<i64>) -> Result<Vec<Action>, Error> {
+    ids.into_iter()
+        .map(|id| read_action(connection, id))
+        .collect()
+}
+
+fn read_goal(connection: &Connection, id: i64) -> Result<Goal, Error> {
+    let mut goal = connection
+        .query_row(
+            "SELECT id, description, done, created, due, scheduled, priority, parent, sequential, note FROM goals WHERE id = ?1",
+            rusqlite::params![id],
+            |row| {
+                Ok(Goal {
+                    id: row.get("id")?,
+                    description: row.get("description")?,
+                    done: row.get("done")?,
+                    created: row.get("created")?,
+                    due: row.get("due")?,
+                    scheduled: row.get("scheduled")?,
+                    priority: row.get("priority")?,
+                    parent: row.get("parent")?,
+                    sequential: row.get("sequential")?,
+                    actions: Vec::new(),
+                    tags: Vec::new(),
+                    note: row.get("note")?,
+                })
+            },
+        )
+        .map_err(|e| Error::Storage(format!("unable to read goal: {}", e)))?;
+    goal.actions = select_ids(
+        connection,
+        "SELECT action FROM goal_actions WHERE goal = ?1 ORDER BY position",
+        rusqlite::params![id],
+    )?;
+    goal.tags = read_tags(connection, Item::Goal, id)?;
+    Ok(goal)
+}
+
+fn read_goals(connection: &Connection, ids: Vec<i64>) -> Result<Vec<Goal>, Error> {
+    ids.into_iter()
+        .map(|id| read_goal(connection, id))
+        .collect()
+}
+
+fn read_tags(connection: &Connection, item: Item, id: i64) -> Result<Vec<String>, Error> {
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT tags.name FROM tags JOIN {item}_tags ON {item}_tags.tag = tags.id WHERE {item}_tags.{item} = ?1 ORDER BY tags.name",
+            item = item.name()
+        ))
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let tags = statement
+        .query_map(rusqlite::params![id], |row| row.get::<usize, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to read tags: {}", e)))?;
+    Ok(tags)
+}
+
+fn read_annotations(
+    connection: &Connection,
+    item: Item,
+    id: i64,
+) -> Result<Vec<Annotation>, Error> {
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT created, text FROM {item}_annotations WHERE {item} = ?1 ORDER BY created, id",
+            item = item.name()
+        ))
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let annotations = statement
+        .query_map(rusqlite::params![id], |row| {
+            Ok(Annotation {
+                created: row.get("created")?,
+                text: row.get("text")?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to read annotations: {}", e)))?;
+    Ok(annotations)
+}
+
+// The IDs of the items that `statement` selects, whose first column must be the ID.
+fn select_ids<P: rusqlite::Params>(
+    connection: &Connection,
+    statement: &str,
+    params: P,
+) -> Result<Vec<i64>, Error> {
+    let mut statement = connection
+        .prepare(statement)
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let ids = statement
+        .query_map(params, |row| row.get::<usize, i64>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to read items: {}", e)))?;
+    Ok(ids)
+}
+
+pub(crate) fn config_value(connection: &Connection, name: &str) -> Result<Option<String>, Error> {
+    connection
+        .query_row(
+            "SELECT value FROM config WHERE name = ?1",
+            rusqlite::params![name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::Storage(format!("unable to look up setting: {}", e)))
+}
+
+fn tag(connection: &Connection, item: Item, id: i64, tags: Vec<String>) -> Result<(), Error> {
+    let transaction = connection
+        .unchecked_transaction()
+        .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+    for tag in tags {
+        transaction
+            .execute(
+                "INSERT OR IGNORE INTO tags (name) VALUES(?1)",
+                rusqlite::params![tag],
+            )
+            .and_then(|_| {
+                transaction.execute(
+                    &format!(
+                        "INSERT OR IGNORE INTO {item}_tags ({item}, tag) SELECT ?1, id FROM tags WHERE name = ?2",
+                        item = item.name()
+                    ),
+                    rusqlite::params![id, tag],
+                )
+            })
+            .map_err(|e| Error::Storage(format!("unable to add tag: {}", e)))?;
+    }
+    transaction
+        .commit()
+        .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))
+}
+
+fn untag(connection: &Connection, item: Item, id: i64, tags: Vec<String>) -> Result<(), Error> {
+    let transaction = connection
+        .unchecked_transaction()
+        .map_err(|e| Error::Storage(format!("unable to begin transaction: {}", e)))?;
+    for tag in tags {
+        match transaction
+            .execute(
+                &format!(
+                    "DELETE FROM {item}_tags WHERE {item} = ?1 AND tag = (SELECT id FROM tags WHERE name = ?2)",
+                    item = item.name()
+                ),
+                rusqlite::params![id, tag],
+            )
+            .map_err(|e| Error::Storage(format!("unable to remove tag: {}", e)))?
+        {
+            0 => {
+                return Err(Error::NotFound(format!(
+                    "{} does not have tag: `+{}`",
+                    item.name(),
+                    tag
+                )))
+            }
+            1 => {}
+            _ => unreachable!(),
+        }
+    }
+    transaction
+        .execute_batch("DELETE FROM tags WHERE NOT EXISTS (SELECT * FROM action_tags WHERE action_tags.tag = tags.id) AND NOT EXISTS (SELECT * FROM goal_tags WHERE goal_tags.tag = tags.id)")
+        .map_err(|e| Error::Storage(format!("unable to remove unused tags: {}", e)))?;
+    transaction
+        .commit()
+        .map_err(|e| Error::Storage(format!("unable to commit transaction: {}", e)))
+}
+
+fn tag_id(connection: &Connection, name: &str) -> Result<Option<i64>, Error> {
+    connection
+        .query_row(
+            "SELECT id FROM tags WHERE name = ?1",
+            rusqlite::params![name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::Storage(format!("unable to look up tag: {}", e)))
+}
+
+fn annotate(connection: &Connection, item: Item, id: i64, text: &str) -> Result<(), Error> {
+    connection
+        .execute(
+            &format!(
+                "INSERT INTO {item}_annotations ({item}, created, text) VALUES(?1, datetime('now'), ?2)",
+                item = item.name()
+            ),
+            rusqlite::params![id, text],
+        )
+        .map(|_| ())
+        .map_err(|e| Error::Storage(format!("unable to add annotation: {}", e)))
+}
+
+// Trailing whitespace is dropped, and a note with nothing else in it is removed.
+fn save_note(
+    connection: &Connection,
+    item: Item,
+    id: i64,
+    note: Option<&str>,
+) -> Result<(), Error> {
+    let note = note.map(str::trim_end).filter(|note| !note.is_empty());
+    connection
+        .execute(
+            &format!("UPDATE {}s SET note = ?1 WHERE id = ?2", item.name()),
+            rusqlite::params![note, id],
+        )
+        .map(|_| ())
+        .map_err(|e| Error::Storage(format!("unable to set note: {}", e)))
+}
+
+// Finds the view called `name` that lists `item`, or, if `item` is not given, the action view
+// before the goal view.
+fn find_view(
+    connection: &Connection,
+    name: &str,
+    item: Option<Item>,
+) -> Result<Option<(i64, Item)>, Error> {
+    connection
+        .query_row(
+            "SELECT id, item FROM views WHERE name = ?1 AND (?2 IS NULL OR item = ?2) ORDER BY item",
+            rusqlite::params![name, item.map(Item::name)],
+            |row| Ok((row.get::<usize, i64>(0)?, row.get::<usize, String>(1)?)),
+        )
+        .optional()
+        .map_err(|e| Error::Storage(format!("unable to look up view: {}", e)))?
+        .map(|(id, item)| Ok((id, Item::from_arg(&item)?)))
+        .transpose()
+}
+
+fn view_arguments(connection: &Connection, id: i64) -> Result<Vec<String>, Error> {
+    let mut statement = connection
+        .prepare("SELECT argument FROM view_arguments WHERE view = ?1 ORDER BY position")
+        .map_err(|e| Error::Storage(format!("unable to prepare statement: {}", e)))?;
+    let arguments = statement
+        .query_map(rusqlite::params![id], |row| row.get::<usize, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::Storage(format!("unable to read view: {}", e)))?;
+    Ok(arguments)
+}
+
+fn context_id(connection: &Connection, name: &str) -> Result<i64, Error> {
+    connection
+        .query_row(
+            "SELECT id FROM contexts WHERE name = ?1",
+            rusqlite::params![name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::Storage(format!("unable to look up context: {}", e)))?
+        .ok_or_else(|| Error::NotFound("context does not exist".into()))
+}
+
+fn action_id(connection: &Connection, action: &str) -> Result<i64, Error> {
+    find_id(connection, "actions", action)?
+        .ok_or_else(|| Error::NotFound("action does not exist".into()))
+}
+
+fn goal_id(connection: &Connection, goal: &str) -> Result<i64, Error> {
+    find_id(connection, "goals", goal)?.ok_or_else(|| Error::NotFound("goal does not exist".into()))
+}
+
+// Items can be named by ID, written as `#12` or `12`, or by description.  A number that is not an ID
+// is looked up as a description so that descriptions like "42" remain reachable.  Only open items
+// must have unique descriptions, so a description names the open item if there is one and the most
+// recently added done item otherwise.
+fn find_id(connection: &Connection, table: &str, item: &str) -> Result<Option<i64>, Error> {
+    let digits = item.strip_prefix('#').unwrap_or(item);
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(id) = digits.parse::<i64>() {
+            let found = connection
+                .query_row(
+                    &format!("SELECT id FROM {} WHERE id = ?1", table),
+                    rusqlite::params![id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| Error::Storage(format!("unable to look up ID: {}", e)))?;
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+    }
+    connection
+        .query_row(
+            &format!(
+                "SELECT id FROM {} WHERE description = ?1 ORDER BY done IS NOT NULL, id DESC",
+                table
+            ),
+            rusqlite::params![item],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::Storage(format!("unable to look up description: {}", e)))
+}
+
+pub(crate) fn is_constraint_violation(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(
+            libsqlite3_sys::Error {
+                code: libsqlite3_sys::ErrorCode::ConstraintViolation,
+                ..
+            },
+            _,
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_actions() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        store.add_action("Water the plants").unwrap();
+        store.complete_action("Water the plants").unwrap();
+        let actions = store.list_actions(Status::Open, None).unwrap();
+        assert_eq!(
+            actions
+                .iter()
+                .map(|action| action.description.as_str())
+                .collect::<Vec<_>>(),
+            ["Email Alice"]
+        );
+        assert_eq!(actions[0], store.action("Email Alice").unwrap());
+        assert!(store.action("Water the plants").unwrap().done.is_some());
+        assert_eq!(
+            store
+                .list_actions(Status::All, Some("description~plants"))
+                .unwrap()[0]
+                .description,
+            "Water the plants"
+        );
+    }
+
+    #[test]
+    fn links_goal_to_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal("Read *Network Effect*.", None, None, GoalType::Sequential)
+            .unwrap();
+        assert_eq!(
+            store.list_goals(Status::Open, true, None).unwrap(),
+            [Goal {
+                actions: Vec::new(),
+                ..store.goal("Read *Network Effect*.").unwrap()
+            }]
+        );
+        store
+            .set_goal_action("Read *Network Effect*.", "Borrow *Network Effect*.")
+            .unwrap();
+        let goal = store.goal("Read *Network Effect*.").unwrap();
+        assert!(goal.sequential);
+        assert_eq!(goal.actions, [1]);
+        assert_eq!(store.action("#1").unwrap().goals, [goal.id]);
+        assert_eq!(store.list_goals(Status::Open, true, None), Ok(Vec::new()));
+        store.unset_goal_action("Read *Network Effect*.").unwrap();
+        assert_eq!(store.goal("#1").unwrap().actions, Vec::<i64>::new());
+    }
+
+    #[test]
+    fn completing_action_returns_goals_needing_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
+        assert_eq!(
+            store.goal_actions("Read *Network Effect*."),
+            Ok(vec![store.action("Borrow *Network Effect*.").unwrap()])
+        );
+        assert_eq!(
+            store.complete_action("Borrow *Network Effect*."),
+            Ok(vec![store.goal("Read *Network Effect*.").unwrap()])
+        );
+        assert_eq!(store.goal_actions("Read *Network Effect*."), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn edits_action() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Water the plants").unwrap();
+        store
+            .set_action_description("Water the plants", "Water the ferns")
+            .unwrap();
+        store
+            .set_action_date(
+                "Water the ferns",
+                DateField::Due,
+                NaiveDate::from_ymd_opt(2021, 6, 1),
+            )
+            .unwrap();
+        store.set_action_priority("#1", Some(1)).unwrap();
+        store
+            .set_action_recurrence("#1", Recurrence::from_arg("weekly").ok())
+            .unwrap();
+        store
+            .tag_action("#1", &["garden".into(), "home".into()])
+            .unwrap();
+        store.untag_action("#1", &["home".into()]).unwrap();
+        store
+            .set_action_note("#1", Some("Not the cactus.\n"))
+            .unwrap();
+        let action = store.action("#1").unwrap();
+        assert_eq!(action.description, "Water the ferns");
+        assert_eq!(action.due.as_deref(), Some("2021-06-01"));
+        assert_eq!(action.priority, Some(1));
+        assert_eq!(action.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=TU"));
+        assert_eq!(action.tags, ["garden"]);
+        assert_eq!(action.note.as_deref(), Some("Not the cactus."));
+    }
+
+    #[test]
+    fn rejects_values_the_command_line_would_not_parse() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        assert_eq!(
+            store.set_action_priority("Email Alice", Some(9)),
+            Err(Error::Usage("invalid priority: `9`".into()))
+        );
+        assert_eq!(
+            store.tag_action("Email Alice", &["work,home".into()]),
+            Err(Error::Usage("invalid tag: `work,home`".into()))
+        );
+    }
+
+    #[test]
+    fn reports_typed_errors() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_goal("Read more", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                None,
+                Some("Read more"),
+                GoalType::Parallel,
+            )
+            .unwrap();
+        assert_eq!(
+            store.add_goal("Read more", None, None, GoalType::Parallel),
+            Err(Error::AlreadyExists("goal already exists".into()))
+        );
+        assert_eq!(
+            store.set_goal_action("Read more", "Email Alice"),
+            Err(Error::NotFound("action does not exist".into()))
+        );
+        assert_eq!(
+            store.remove_goal("Read more", false),
+            Err(Error::Constraint("goal has sub-goals".into()))
+        );
+        assert_eq!(
+            store.list_goals(Status::All, false, Some("priority<<2")),
+            Err(Error::Usage(
+                "invalid filter at column 10: expected a value".into()
+            ))
+        );
+        store.remove_goal("Read more", true).unwrap();
+        assert_eq!(store.list_goals(Status::All, false, None), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn lists_goals_by_completion() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
+        store
+            .add_goal("Read more", None, None, GoalType::Parallel)
+            .unwrap();
+        store
+            .add_goal("Read less", None, None, GoalType::Parallel)
+            .unwrap();
+        store.complete_goal("Read less").unwrap();
+        let descriptions = |status, stuck| {
+            store
+                .list_goals(status, stuck, None)
+                .unwrap()
+                .into_iter()
+                .map(|goal| goal.description)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            descriptions(Status::Open, false),
+            ["Read *Network Effect*.", "Read more"]
+        );
+        assert_eq!(descriptions(Status::Open, true), ["Read more"]);
+        assert_eq!(descriptions(Status::Done, false), ["Read less"]);
+        assert_eq!(descriptions(Status::All, false).len(), 3);
+    }
+
+    #[test]
+    fn manages_contexts() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Call Alice").unwrap();
+        store.add_context("@phone", Some("Calls"), None).unwrap();
+        store.add_context("home", None, Some(1)).unwrap();
+        assert_eq!(
+            store.contexts(),
+            Ok(vec![
+                Context {
+                    name: "home".into(),
+                    description: None,
+                    position: Some(1)
+                },
+                Context {
+                    name: "phone".into(),
+                    description: Some("Calls".into()),
+                    position: None
+                }
+            ])
+        );
+        store
+            .set_action_context("Call Alice", Some("@phone"))
+            .unwrap();
+        assert_eq!(
+            store.action("Call Alice").unwrap().context.as_deref(),
+            Some("phone")
+        );
+        assert_eq!(store.next_actions(Some("home")), Ok(Vec::new()));
+        store.remove_context("phone").unwrap();
+        assert_eq!(store.action("Call Alice").unwrap().context, None);
+        assert_eq!(
+            store.remove_context("phone"),
+            Err(Error::NotFound("context does not exist".into()))
+        );
+    }
+
+    #[test]
+    fn renames_tags() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Email Alice").unwrap();
+        store.add_action("Email Bob").unwrap();
+        store.tag_action("Email Alice", &["work".into()]).unwrap();
+        store.tag_action("Email Bob", &["job".into()]).unwrap();
+        store.rename_tag("+job", "work").unwrap();
+        assert_eq!(
+            store.tags(),
+            Ok(vec![Tag {
+                name: "work".into(),
+                count: 2
+            }])
+        );
+        assert_eq!(
+            store.rename_tag("job", "work"),
+            Err(Error::NotFound("tag does not exist".into()))
+        );
+    }
+
+    #[test]
+    fn manages_views() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .add_view("work", Item::Action, &["+work".into()])
+            .unwrap();
+        assert_eq!(
+            store.add_view("ls", Item::Goal, &[]),
+            Err(Error::Usage("invalid view name: `ls`".into()))
+        );
+        assert_eq!(
+            store.add_view("home", Item::Goal, &["--context".into()]),
+            Err(Error::Usage("extra argument: `--context`".into()))
+        );
+        let view = View {
+            name: "work".into(),
+            item: Item::Action,
+            arguments: vec!["+work".into()],
+        };
+        assert_eq!(store.view("work", None), Ok(Some(view.clone())));
+        assert_eq!(store.views(), Ok(vec![view]));
+        store.remove_view("work", None).unwrap();
+        assert_eq!(store.view("work", None), Ok(None));
+    }
+
+    #[test]
+    fn manages_settings() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(store.config("urgency.due"), Ok(None));
+        store.set_config("urgency.due", Some("2.5")).unwrap();
+        assert_eq!(store.config("urgency.due"), Ok(Some("2.5".into())));
+        assert!(store.settings().unwrap().contains(&Setting {
+            name: "urgency.due".into(),
+            value: "2.5".into()
+        }));
+        assert_eq!(
+            store.set_config("urgency.due", Some("high")),
+            Err(Error::Usage("invalid value: `high`".into()))
+        );
+        assert_eq!(
+            store.config("color"),
+            Err(Error::Usage("no such setting: `color`".into()))
+        );
+        store.set_config("urgency.due", None).unwrap();
+        assert_eq!(store.config("urgency.due"), Ok(None));
+    }
+
+    #[test]
+    fn searches_and_lists_next_actions() {
+        let store = Store::open_in_memory().unwrap();
+        store.add_action("Borrow *Network Effect*.").unwrap();
+        store.add_action("Email Alice").unwrap();
+        store
+            .add_goal(
+                "Read *Network Effect*.",
+                Some("Borrow *Network Effect*."),
+                None,
+                GoalType::Parallel,
+            )
+            .unwrap();
+        assert_eq!(
+            store.search("network", false),
+            Ok(vec![
+                Match::Goal(store.goal("#1").unwrap()),
+                Match::Action(store.action("#1").unwrap())
+            ])
+        );
+        assert_eq!(
+            store.next_actions(None),
+            Ok(vec![
+                NextActions {
+                    goal: Some(store.goal("#1").unwrap()),
+                    actions: vec![store.action("#1").unwrap()]
+                },
+                NextActions {
+                    goal: None,
+                    actions: vec![store.action("#2").unwrap()]
+                }
+            ])
+        );
+        store
+            .annotate_action("Email Alice", "Sent a draft")
+            .unwrap();
+        let annotations = store.action_annotations("Email Alice").unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].text, "Sent a draft");
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use std::io::Write;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub struct Column<'a> {
+    pub heading: &'a str,
+    pub align: Align,
+    // Whether the column may be narrowed to fit the table in the terminal.
+    pub flexible: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+// Writes `rows` beneath a heading line, in columns separated by a space and sized to fit in `width`
+// terminal columns if they can.  Fixed columns keep their natural width, and flexible ones share
+// what is left, with the narrower keeping theirs, and their overlong cells cut short.
+pub fn write_table<T: Write>(
+    writer: &mut T,
+    columns: &[Column],
+    rows: &[Vec<String>],
+    width: usize,
+) -> Result<(), Error> {
+    let mut widths = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            rows.iter()
+                .map(|row| display_width(&row[index]))
+                .chain(Some(display_width(column.heading)))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let fixed = columns
+        .iter()
+        .zip(&widths)
+        .filter(|(column, _)| !column.flexible)
+        .map(|(_, width)| width + 1)
+        .sum::<usize>();
+    let mut flexible = (0..columns.len())
+        .filter(|index| columns[*index].flexible)
+        .collect::<Vec<_>>();
+    flexible.sort_by_key(|index| widths[*index]);
+    let mut available = width.saturating_sub(fixed + flexible.len().saturating_sub(1));
+    let mut count = flexible.len();
+    for index in flexible {
+        widths[index] = widths[index].min(available / count).max(1);
+        available = available.saturating_sub(widths[index]);
+        count -= 1;
+    }
+    let headings = columns
+        .iter()
+        .map(|column| column.heading.to_string())
+        .collect::<Vec<_>>();
+    for row in Some(&headings).into_iter().chain(rows) {
+        let cells = columns
+            .iter()
+            .zip(row)
+            .zip(&widths)
+            .map(|((column, cell), width)| {
+                let cell = truncate(cell, *width);
+                let padding = " ".repeat(width - display_width(&cell));
+                match column.align {
+                    Align::Left => format!("{}{}", cell, padding),
+                    Align::Right => format!("{}{}", padding, cell),
+                }
+            })
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", cells.join(" ").trim_end())
+            .map_err(|e| Error::Storage(format!("unable to write table: {}", e)))?;
+    }
+    Ok(())
+}
+
+// The number of terminal columns `text` takes, counting wide characters such as CJK ideographs and
+// emoji as two.
+pub(crate) fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+// Cuts `text` to at most `width` terminal columns, the last of which is an ellipsis if anything was
+// removed.
+pub(crate) fn truncate(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.into();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLUMNS: &[Column] = &[
+        Column {
+            heading: "ID",
+            align: Align::Right,
+            flexible: false,
+        },
+        Column {
+            heading: "Goal",
+            align: Align::Left,
+            flexible: true,
+        },
+        Column {
+            heading: "Due",
+            align: Align::Left,
+            flexible: false,
+        },
+    ];
+
+    fn write(rows: &[Vec<String>], width: usize) -> String {
+        let mut output = Vec::new();
+        write_table(&mut output, COLUMNS, rows, width).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn rows() -> Vec<Vec<String>> {
+        vec![
+            vec!["9".into(), "Read 三体".into(), "2021-06-01".into()],
+            vec!["10".into(), "Plan 🎉 party".into(), "".into()],
+        ]
+    }
+
+    #[test]
+    fn writes_table() {
+        assert_eq!(
+            write(&rows(), 80),
+            "ID Goal          Due\n 9 Read 三体     2021-06-01\n10 Plan 🎉 party\n"
+        );
+    }
+
+    #[test]
+    fn truncates_flexible_columns() {
+        assert_eq!(
+            write(&rows(), 22),
+            "ID Goal     Due\n 9 Read 三… 2021-06-01\n10 Plan 🎉…\n"
+        );
+    }
+
+    #[test]
+    fn measures_wide_characters() {
+        assert_eq!(display_width("三体"), 4);
+        assert_eq!(display_width("🎉"), 2);
+        assert_eq!(truncate("三体问题", 5), "三体…");
+        assert_eq!(truncate("三体", 4), "三体");
+    }
+}
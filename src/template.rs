@@ -0,0 +1,261 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    format::Value,
+    table::{display_width, truncate},
+};
+
+// A listing template such as `{id:>4} {description:.30} [{action}]`.  Each field may be followed by
+// a specification of the form `[[fill]align][width][.limit]`, where `align` is `<`, `^`, or `>`,
+// values narrower than `width` terminal columns are padded with `fill`, which defaults to a space,
+// and values wider than `limit` columns are cut short with an ellipsis.  `{{` and `}}` stand for
+// braces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Field {
+        index: usize,
+        fill: char,
+        align: Align,
+        width: usize,
+        limit: Option<usize>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Template {
+    // Fields are looked up in `fields`, and `render` takes their values in the same order.
+    pub fn parse(text: &str, fields: &[&str]) -> Result<Self, String> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut index = 0;
+        while index < chars.len() {
+            match chars[index] {
+                '{' if chars.get(index + 1) == Some(&'{') => {
+                    literal.push('{');
+                    index += 2;
+                }
+                '}' if chars.get(index + 1) == Some(&'}') => {
+                    literal.push('}');
+                    index += 2;
+                }
+                '}' => return Err(error(index + 1, "unmatched `}`".into())),
+                '{' => {
+                    let start = index + 1;
+                    let end = chars[start..]
+                        .iter()
+                        .position(|c| *c == '}')
+                        .map(|end| start + end)
+                        .ok_or_else(|| error(index + 1, "unterminated field".into()))?;
+                    let field = chars[start..end].iter().collect::<String>();
+                    let (name, specification) = match field.find(':') {
+                        Some(colon) => (&field[..colon], Some(&field[colon + 1..])),
+                        None => (field.as_str(), None),
+                    };
+                    let field_index = fields
+                        .iter()
+                        .position(|field| *field == name)
+                        .ok_or_else(|| error(start + 1, format!("no such field: `{}`", name)))?;
+                    let (fill, align, width, limit) = match specification {
+                        Some(specification) => {
+                            parse_specification(specification).ok_or_else(|| {
+                                error(
+                                    start + name.chars().count() + 2,
+                                    format!("invalid specification: `{}`", specification),
+                                )
+                            })?
+                        }
+                        None => (' ', Align::Left, 0, None),
+                    };
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Text(literal.split_off(0)));
+                    }
+                    pieces.push(Piece::Field {
+                        index: field_index,
+                        fill,
+                        align,
+                        width,
+                        limit,
+                    });
+                    index = end + 1;
+                }
+                c => {
+                    literal.push(c);
+                    index += 1;
+                }
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Ok(Self { pieces })
+    }
+
+    // Renders the template with `values`, which hold a value for every field the template was
+    // parsed with.  Null values are empty and lists are separated by commas.
+    pub fn render(&self, values: &[Value]) -> String {
+        let mut output = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => output.push_str(text),
+                Piece::Field {
+                    index,
+                    fill,
+                    align,
+                    width,
+                    limit,
+                } => {
+                    let mut value = crate::format::plain(&values[*index]);
+                    if let Some(limit) = limit {
+                        value = truncate(&value, *limit);
+                    }
+                    let padding = width.saturating_sub(display_width(&value));
+                    let (before, after) = match align {
+                        Align::Left => (0, padding),
+                        Align::Center => (padding / 2, padding - padding / 2),
+                        Align::Right => (padding, 0),
+                    };
+                    output.extend((0..before).map(|_| fill));
+                    output.push_str(&value);
+                    output.extend((0..after).map(|_| fill));
+                }
+            }
+        }
+        output
+    }
+}
+
+fn error(column: usize, message: String) -> String {
+    format!("invalid template at column {}: {}", column, message)
+}
+
+fn parse_specification(specification: &str) -> Option<(char, Align, usize, Option<usize>)> {
+    let chars = specification.chars().collect::<Vec<_>>();
+    let (fill, align, rest) = match (chars.first().copied(), chars.get(1).and_then(|c| align(*c))) {
+        (Some(fill), Some(align)) => (fill, align, &chars[2..]),
+        (Some(c), None) if align(c).is_some() => (' ', align(c)?, &chars[1..]),
+        _ => (' ', Align::Left, &chars[..]),
+    };
+    let rest = rest.iter().collect::<String>();
+    let (width, limit) = match rest.find('.') {
+        Some(dot) => (&rest[..dot], Some(&rest[dot + 1..])),
+        None => (rest.as_str(), None),
+    };
+    let width = if width.is_empty() { 0 } else { number(width)? };
+    let limit = match limit {
+        Some(limit) => Some(number(limit)?),
+        None => None,
+    };
+    Some((fill, align, width, limit))
+}
+
+fn align(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    }
+}
+
+fn number(text: &str) -> Option<usize> {
+    if text.chars().all(|c| c.is_ascii_digit()) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[&str] = &["id", "description", "tags", "due"];
+
+    fn render(template: &str) -> String {
+        Template::parse(template, FIELDS).unwrap().render(&[
+            Value::Integer(7),
+            Value::Text("Read \"Network Effect\"".into()),
+            Value::List(vec![
+                Value::Text("reading".into()),
+                Value::Text("fun".into()),
+            ]),
+            Value::Null,
+        ])
+    }
+
+    #[test]
+    fn renders_fields() {
+        assert_eq!(
+            render("{id} {description} [{tags}] {due}"),
+            "7 Read \"Network Effect\" [reading,fun] "
+        );
+    }
+
+    #[test]
+    fn renders_braces() {
+        assert_eq!(render("{{{id}}}"), "{7}");
+    }
+
+    #[test]
+    fn aligns_fields() {
+        assert_eq!(
+            render("{id:>4}|{id:<3}|{id:^5}|{id:0>3}"),
+            "   7|7  |  7  |007"
+        );
+    }
+
+    #[test]
+    fn truncates_fields() {
+        assert_eq!(
+            render("{description:.9}|{description:12.4}|"),
+            "Read \"Ne…|Rea…        |"
+        );
+        assert_eq!(render("{tags:.20}"), "reading,fun");
+    }
+
+    #[test]
+    fn reports_invalid_templates() {
+        assert_eq!(
+            Template::parse("{id} {name}", FIELDS),
+            Err("invalid template at column 7: no such field: `name`".into())
+        );
+        assert_eq!(
+            Template::parse("{id:>x}", FIELDS),
+            Err("invalid template at column 5: invalid specification: `>x`".into())
+        );
+        assert_eq!(
+            Template::parse("{id", FIELDS),
+            Err("invalid template at column 1: unterminated field".into())
+        );
+        assert_eq!(
+            Template::parse("id}", FIELDS),
+            Err("invalid template at column 3: unmatched `}`".into())
+        );
+    }
+}
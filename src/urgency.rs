@@ -0,0 +1,237 @@
+// Copyright 2021 Matthew James Kraai
+//
+// This file is part of odo.
+//
+// odo is free software: you can redistribute it and/or modify it under the terms of the GNU Affero
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// odo is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the
+// implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU Affero
+// General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with odo.  If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use chrono::{Local, NaiveDate};
+use rusqlite::{functions::FunctionFlags, Connection};
+
+// The configurable coefficients and their defaults, which follow Taskwarrior's.  Each is multiplied
+// by a factor between 0 and 1, and an item's urgency is the sum of the products.
+pub const COEFFICIENTS: &[(&str, f64)] = &[
+    ("urgency.age", 2.0),
+    ("urgency.due", 12.0),
+    ("urgency.goal", 8.0),
+    ("urgency.priority", 6.0),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coefficients {
+    age: f64,
+    due: f64,
+    goal: f64,
+    priority: f64,
+}
+
+impl Coefficients {
+    pub fn load(connection: &Connection) -> Result<Self, Error> {
+        let mut values = Vec::new();
+        for (name, default) in COEFFICIENTS {
+            values.push(
+                crate::store::config_value(connection, name)?
+                    .map(|value| {
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|value| value.is_finite())
+                            .ok_or_else(|| {
+                                Error::Storage(format!("invalid value for `{}`: `{}`", name, value))
+                            })
+                    })
+                    .transpose()?
+                    .unwrap_or(*default),
+            );
+        }
+        Ok(Self {
+            age: values[0],
+            due: values[1],
+            goal: values[2],
+            priority: values[3],
+        })
+    }
+
+    // `priority` runs from 1 (highest) to 5, `due` is a date, `created` is a date and time, and
+    // `goal` is whether an unfinished goal depends on the item.
+    pub fn urgency(
+        &self,
+        priority: Option<i64>,
+        due: Option<&str>,
+        created: Option<&str>,
+        goal: bool,
+        today: NaiveDate,
+    ) -> f64 {
+        let priority = priority.map_or(0.0, |priority| (6 - priority.clamp(1, 5)) as f64 / 5.0);
+        // Due dates count from two weeks away, rising to the full weight once a week overdue.
+        let due = date(due).map_or(0.0, |due| {
+            let overdue = today.signed_duration_since(due).num_days().clamp(-14, 7);
+            (overdue + 14) as f64 * 0.8 / 21.0 + 0.2
+        });
+        // Age reaches its full weight after a year.
+        let age = date(created).map_or(0.0, |created| {
+            (today.signed_duration_since(created).num_days().max(0) as f64 / 365.0).min(1.0)
+        });
+        let goal = if goal { 1.0 } else { 0.0 };
+        self.priority * priority + self.due * due + self.age * age + self.goal * goal
+    }
+}
+
+fn date(value: Option<&str>) -> Option<NaiveDate> {
+    value.and_then(|value| NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok())
+}
+
+// Registers `urgency(priority, due, created, goal)` so that queries can sort by it.  The
+// coefficients and today's date are fixed when it is registered.
+// A coefficient that cannot be read only fails the statements that compute urgency, so that
+// `config set` and `config unset` can still replace it.
+pub fn register(connection: &Connection) -> Result<(), Error> {
+    let coefficients = Coefficients::load(connection).map_err(|e| e.to_string());
+    let today = Local::now().date_naive();
+    connection
+        .create_scalar_function(
+            "urgency",
+            4,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |context| {
+                let coefficients = coefficients
+                    .as_ref()
+                    .map_err(|e| rusqlite::Error::UserFunctionError(e.clone().into()))?;
+                Ok(coefficients.urgency(
+                    context.get::<Option<i64>>(0)?,
+                    context.get::<Option<String>>(1)?.as_deref(),
+                    context.get::<Option<String>>(2)?.as_deref(),
+                    context.get::<bool>(3)?,
+                    today,
+                ))
+            },
+        )
+        .map_err(|e| Error::Storage(format!("unable to register urgency: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2021, 5, 14).unwrap()
+    }
+
+    fn defaults() -> Coefficients {
+        Coefficients {
+            age: 2.0,
+            due: 12.0,
+            goal: 8.0,
+            priority: 6.0,
+        }
+    }
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{} != {}", left, right);
+    }
+
+    #[test]
+    fn loads_default_coefficients() {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::initialize(&connection).unwrap();
+        assert_eq!(Coefficients::load(&connection), Ok(defaults()));
+    }
+
+    #[test]
+    fn loads_configured_coefficients() {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::initialize(&connection).unwrap();
+        connection
+            .execute(
+                "INSERT INTO config (name, value) VALUES('urgency.goal', '0.5')",
+                [],
+            )
+            .unwrap();
+        assert_eq!(
+            Coefficients::load(&connection),
+            Ok(Coefficients {
+                goal: 0.5,
+                ..defaults()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_non_finite_coefficients() {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::initialize(&connection).unwrap();
+        connection
+            .execute(
+                "INSERT INTO config (name, value) VALUES('urgency.age', 'NaN')",
+                [],
+            )
+            .unwrap();
+        assert_eq!(
+            Coefficients::load(&connection),
+            Err(Error::Storage(
+                "invalid value for `urgency.age`: `NaN`".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn registers_urgency_despite_invalid_coefficient() {
+        let connection = Connection::open_in_memory().unwrap();
+        crate::database::initialize(&connection).unwrap();
+        connection
+            .execute(
+                "INSERT INTO config (name, value) VALUES('urgency.age', 'NaN')",
+                [],
+            )
+            .unwrap();
+        register(&connection).unwrap();
+        assert_eq!(
+            connection
+                .query_row("SELECT urgency(1, NULL, NULL, 0)", [], |row| row
+                    .get::<usize, f64>(0))
+                .map_err(|e| e.to_string()),
+            Err("invalid value for `urgency.age`: `NaN`".into())
+        );
+    }
+
+    #[test]
+    fn has_no_urgency_without_attributes() {
+        assert_eq!(defaults().urgency(None, None, None, false, today()), 0.0);
+    }
+
+    #[test]
+    fn weighs_priority() {
+        assert_close(defaults().urgency(Some(1), None, None, false, today()), 6.0);
+        assert_close(defaults().urgency(Some(3), None, None, false, today()), 3.6);
+        assert_close(defaults().urgency(Some(5), None, None, false, today()), 1.2);
+    }
+
+    #[test]
+    fn weighs_due_date() {
+        let urgency = |due| defaults().urgency(None, Some(due), None, false, today());
+        assert_close(urgency("2021-06-30"), 2.4);
+        assert_close(urgency("2021-05-07"), 12.0);
+        assert!(urgency("2021-05-13") > urgency("2021-05-20"));
+    }
+
+    #[test]
+    fn weighs_age() {
+        let urgency = |created| defaults().urgency(None, None, Some(created), false, today());
+        assert_close(urgency("2019-01-01 12:00:00"), 2.0);
+        assert_close(urgency("2021-05-14 12:00:00"), 0.0);
+    }
+
+    #[test]
+    fn weighs_goal() {
+        assert_eq!(defaults().urgency(None, None, None, true, today()), 8.0);
+    }
+}
@@ -62,7 +62,7 @@ fn odo_action_add_adds_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "add", "Read", "*Network", "Effect*."])
+        .args(["action", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -75,7 +75,7 @@ fn odo_action_add_creates_data_directory() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "add", "Read", "*Network", "Effect*."])
+        .args(["action", "add", "Read", "*Network", "Effect*."])
         .assert();
     let data_dir = if cfg!(target_os = "macos") {
         home_dir
@@ -98,7 +98,7 @@ fn odo_action_add_creates_parent_directories_0o700() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "add", "Read", "*Network", "Effect*."])
+        .args(["action", "add", "Read", "*Network", "Effect*."])
         .assert();
     assert_eq!(
         home_dir.path().join(".local").metadata().unwrap().mode() & 0o700,
@@ -122,7 +122,7 @@ fn odo_action_ls_lists_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "add", "Read", "*Network", "Effect*."])
+        .args(["action", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -130,10 +130,10 @@ fn odo_action_ls_lists_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "ls"])
+        .args(["action", "ls"])
         .assert()
         .success()
-        .stdout("Read *Network Effect*.\n")
+        .stdout("1 Read *Network Effect*.\n")
         .stderr("");
 }
 
@@ -143,7 +143,7 @@ fn odo_action_rm_removes_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "add", "Read", "*Network", "Effect*."])
+        .args(["action", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -151,7 +151,7 @@ fn odo_action_rm_removes_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "rm", "Read", "*Network", "Effect*."])
+        .args(["action", "rm", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -159,7 +159,7 @@ fn odo_action_rm_removes_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "ls"])
+        .args(["action", "ls"])
         .assert()
         .success()
         .stdout("")
@@ -172,7 +172,7 @@ fn odo_action_set_description_sets_description() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "add", "Read", "*Network", "Efect*."])
+        .args(["action", "add", "Read", "*Network", "Efect*."])
         .assert()
         .success()
         .stdout("")
@@ -180,7 +180,7 @@ fn odo_action_set_description_sets_description() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&[
+        .args([
             "action",
             "set",
             "description",
@@ -196,10 +196,10 @@ fn odo_action_set_description_sets_description() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "ls"])
+        .args(["action", "ls"])
         .assert()
         .success()
-        .stdout("Read *Network Effect*.\n")
+        .stdout("1 Read *Network Effect*.\n")
         .stderr("");
 }
 
@@ -209,7 +209,7 @@ fn odo_goal_add_adds_goal() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "add", "Read", "*Network", "Effect*."])
+        .args(["goal", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -222,7 +222,7 @@ fn odo_goal_ls_lists_goal() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "add", "Read", "*Network", "Effect*."])
+        .args(["goal", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -230,20 +230,68 @@ fn odo_goal_ls_lists_goal() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "ls"])
+        .args(["goal", "ls"])
         .assert()
         .success()
-        .stdout("Read *Network Effect*.\n")
+        .stdout("1 Read *Network Effect*.\n")
         .stderr("");
 }
 
+// Goal tables are only for terminals, so piped output stays one goal per line even when standard
+// error is a terminal, as it is when the completion script runs `odo goal ls` in a shell.
+#[cfg(unix)]
+#[test]
+fn odo_goal_ls_does_not_write_table_to_pipe() {
+    use std::{fs::File, os::unix::io::FromRawFd, process::Stdio};
+
+    let home_dir = TempHomeDir::new();
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "add", "Read", "*Network", "Effect*."])
+        .assert()
+        .success();
+    let terminal = unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(master >= 0);
+        assert_eq!(libc::grantpt(master), 0);
+        assert_eq!(libc::unlockpt(master), 0);
+        let name = libc::ptsname(master);
+        assert!(!name.is_null());
+        let slave = libc::open(name, libc::O_RDWR | libc::O_NOCTTY);
+        assert!(slave >= 0);
+        let size = libc::winsize {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        assert_eq!(libc::ioctl(slave, libc::TIOCSWINSZ, &size), 0);
+        (File::from_raw_fd(master), File::from_raw_fd(slave))
+    };
+    let output = std::process::Command::new(assert_cmd::cargo::cargo_bin("odo"))
+        .env("HOME", home_dir.path())
+        .env_remove("XDG_DATA_DIR")
+        .args(["goal", "ls"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(terminal.1)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1 Read *Network Effect*.\n"
+    );
+}
+
 #[test]
 fn odo_goal_rm_removes_goal() {
     let home_dir = TempHomeDir::new();
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "add", "Read", "*Network", "Effect*."])
+        .args(["goal", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -251,7 +299,7 @@ fn odo_goal_rm_removes_goal() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "rm", "Read", "*Network", "Effect*."])
+        .args(["goal", "rm", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -259,7 +307,7 @@ fn odo_goal_rm_removes_goal() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "ls"])
+        .args(["goal", "ls"])
         .assert()
         .success()
         .stdout("")
@@ -272,7 +320,7 @@ fn odo_goal_set_description_sets_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["action", "add", "Borrow", "*Network", "Effect*."])
+        .args(["action", "add", "Borrow", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -280,7 +328,7 @@ fn odo_goal_set_description_sets_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "add", "Read", "*Network", "Effect*."])
+        .args(["goal", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -288,7 +336,7 @@ fn odo_goal_set_description_sets_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&[
+        .args([
             "goal",
             "set",
             "action",
@@ -309,7 +357,7 @@ fn odo_goal_set_description_sets_description() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "add", "Read", "*Network", "Efect*."])
+        .args(["goal", "add", "Read", "*Network", "Efect*."])
         .assert()
         .success()
         .stdout("")
@@ -317,7 +365,7 @@ fn odo_goal_set_description_sets_description() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&[
+        .args([
             "goal",
             "set",
             "description",
@@ -333,10 +381,10 @@ fn odo_goal_set_description_sets_description() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "ls"])
+        .args(["goal", "ls"])
         .assert()
         .success()
-        .stdout("Read *Network Effect*.\n")
+        .stdout("1 Read *Network Effect*.\n")
         .stderr("");
 }
 
@@ -346,7 +394,57 @@ fn odo_goal_unset_action_unsets_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "add", "Read", "*Network", "Effect*."])
+        .args(["goal", "add", "Read", "*Network", "Effect*."])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "unset", "action", "Read", "*Network", "Effect*."])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+}
+
+#[test]
+fn odo_action_rm_removes_action_by_id() {
+    let home_dir = TempHomeDir::new();
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "add", "Read", "*Network", "Effect*."])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "rm", "#1"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+}
+
+#[test]
+fn odo_goal_set_action_accepts_ids() {
+    let home_dir = TempHomeDir::new();
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "add", "Borrow", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
@@ -354,9 +452,875 @@ fn odo_goal_unset_action_unsets_action() {
     Command::cargo_bin("odo")
         .unwrap()
         .home_dir(home_dir.path())
-        .args(&["goal", "unset", "action", "Read", "*Network", "Effect*."])
+        .args(["goal", "add", "Read", "*Network", "Effect*."])
         .assert()
         .success()
         .stdout("")
         .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "set", "action", "1", "#1"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "ls"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+}
+
+#[test]
+fn odo_action_done_lists_goals_needing_action() {
+    let home_dir = TempHomeDir::new();
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "add", "Borrow", "*Network", "Effect*."])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args([
+            "goal", "add", "--action", "1", "Read", "*Network", "Effect*.",
+        ])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "done", "1"])
+        .assert()
+        .success()
+        .stdout("1 Read *Network Effect*.\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--done"])
+        .assert()
+        .success()
+        .stdout("1 Borrow *Network Effect*.\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_goal_actions_lists_actions() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Write", "release", "notes"][..],
+        &["action", "add", "Tag", "release"],
+        &["goal", "add", "--action", "1", "Ship", "2.0"],
+        &["goal", "add-action", "Ship 2.0", "Tag", "release"],
+        &["goal", "move-action", "1", "1", "2"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "actions", "Ship", "2.0"])
+        .assert()
+        .success()
+        .stdout("2 Tag release\n1 Write release notes\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_next_lists_next_actions_by_goal() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Borrow", "*Network", "Effect*."][..],
+        &["action", "add", "Call", "the", "library"],
+        &[
+            "goal", "add", "--action", "1", "Read", "*Network", "Effect*.",
+        ],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .arg("next")
+        .assert()
+        .success()
+        .stdout("1 Read *Network Effect*.\n  1 Borrow *Network Effect*.\nno goal\n  2 Call the library\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_next_filters_by_context() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["context", "add", "--description", "At my desk", "@computer"][..],
+        &["action", "add", "Email", "Alice"],
+        &["action", "add", "Water", "the", "plants"],
+        &["action", "set", "context", "1", "@computer"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["context", "ls"])
+        .assert()
+        .success()
+        .stdout("@computer At my desk\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["next", "--context", "@computer"])
+        .assert()
+        .success()
+        .stdout("no goal\n  1 Email Alice\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_action_ls_sorts_by_due_date() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Renew", "passport"][..],
+        &["action", "add", "File", "taxes"],
+        &["action", "set", "due", "1", "2999-01-01"],
+        &["action", "set", "due", "2", "2000-04-15"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--sort", "due"])
+        .assert()
+        .success()
+        .stdout("2 File taxes\n1 Renew passport\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--overdue"])
+        .assert()
+        .success()
+        .stdout("2 File taxes\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_action_done_repeats_recurring_action() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Pay", "rent"][..],
+        &["action", "set", "due", "1", "2021-01-31"],
+        &["action", "set", "recurrence", "1", "monthly"],
+        &["action", "done", "1"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--all"])
+        .assert()
+        .success()
+        .stdout("1 Pay rent\n2 Pay rent\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--due-before", "2021-03-01"])
+        .assert()
+        .success()
+        .stdout("2 Pay rent\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_action_ls_sorts_by_urgency() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Water", "the", "plants"][..],
+        &["action", "add", "File", "taxes"],
+        &["action", "set", "priority", "2", "H"],
+        &["config", "set", "urgency.priority", "1"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--sort", "urgency"])
+        .assert()
+        .success()
+        .stdout("2 File taxes\n1 Water the plants\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["config", "ls"])
+        .assert()
+        .success()
+        .stdout(
+            "backup.count 10\nbackup.days 30\nurgency.age 2\nurgency.due 12\nurgency.goal 8\nurgency.priority 1\n",
+        )
+        .stderr("");
+}
+
+#[test]
+fn odo_goal_ls_filters_by_tag() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["goal", "add", "Ship", "2.0"][..],
+        &["goal", "add", "Read", "more"],
+        &["goal", "tag", "1", "+work"],
+        &["goal", "tag", "2", "+reading"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "ls", "-work"])
+        .assert()
+        .success()
+        .stdout("2 Read more\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["tag", "ls"])
+        .assert()
+        .success()
+        .stdout("+reading 1\n+work 1\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_action_note_edits_note() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Borrow", "*Network Effect*."][..],
+        &["action", "note", "1", "Ask for the paperback."],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    if cfg!(all(unix, not(target_os = "macos"))) {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .env_remove("VISUAL")
+            .env("EDITOR", "sed -i s/paperback/hardcover/")
+            .args(["action", "note", "--edit", "1"])
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["show", "action", "1"])
+        .assert()
+        .success()
+        .stdout(if cfg!(all(unix, not(target_os = "macos"))) {
+            "1 Borrow *Network Effect*.\nnote:\n  Ask for the hardcover.\n"
+        } else {
+            "1 Borrow *Network Effect*.\nnote:\n  Ask for the paperback.\n"
+        })
+        .stderr("");
+}
+
+#[test]
+fn odo_search_finds_actions_and_goals() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Borrow", "*Network Effect*."][..],
+        &["goal", "add", "Read", "*Network Effect*."],
+        &["action", "add", "Water", "the", "plants"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["search", "read", "OR", "borr*"])
+        .assert()
+        .success()
+        .stdout("action 1 Borrow *Network Effect*.\ngoal 1 Read *Network Effect*.\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_goal_ls_filters_by_expression() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["goal", "add", "Ship", "2.0"][..],
+        &["goal", "add", "Read", "more"],
+        &["goal", "tag", "1", "+work"],
+        &["goal", "set", "priority", "2", "H"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "ls", "not tag:work and priority<=2"])
+        .assert()
+        .success()
+        .stdout("2 Read more\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["goal", "ls", "tag:work and", "priority<<2"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("odo: invalid filter at column 23: expected a value\n");
+}
+
+#[test]
+fn odo_view_runs_saved_listing() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Email Alice"][..],
+        &["action", "add", "File taxes"],
+        &["action", "set", "priority", "2", "H"],
+        &["view", "add", "urgent", "priority<=2"],
+        &["view", "add", "default", "--sort", "urgency"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    for args in [&["urgent"][..], &["view", "urgent"]] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("2 File taxes\n")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls"])
+        .assert()
+        .success()
+        .stdout("2 File taxes\n1 Email Alice\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["view", "ls"])
+        .assert()
+        .success()
+        .stdout("default action --sort urgency\nurgent action 'priority<=2'\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_action_ls_writes_csv() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Email Alice, Bob"][..],
+        &["action", "tag", "1", "+work"],
+        &["goal", "add", "--action", "1", "Ship 2.0"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    let output = Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--format", "csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(
+        lines.next(),
+        Some("type,id,description,done,created,due,scheduled,priority,context,recurrence,parent,sequential,goals,actions,tags,note")
+    );
+    let record = lines.next().unwrap();
+    assert!(record.starts_with("action,1,\"Email Alice, Bob\",,"));
+    assert!(record.ends_with(",,,,,,,1,,work,"));
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn odo_action_ls_uses_template() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Email Alice"][..],
+        &["goal", "add", "--action", "1", "Plan party"],
+        &[
+            "config",
+            "set",
+            "template.action",
+            "{id:0>3} {description:.8}",
+        ],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls"])
+        .assert()
+        .success()
+        .stdout("001 Email A…\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls", "--template", "{description} ({goal})"])
+        .assert()
+        .success()
+        .stdout("Email Alice (Plan party)\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_database_option_chooses_database() {
+    let home_dir = TempHomeDir::new();
+    let database = home_dir.path().join("todo.sqlite3");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .arg("--database")
+        .arg(&database)
+        .args(["action", "add", "Email Alice"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    assert!(database.is_file());
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .env("ODO_DATABASE", &database)
+        .args(["action", "ls"])
+        .assert()
+        .success()
+        .stdout("1 Email Alice\n")
+        .stderr("");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+}
+
+#[test]
+fn odo_does_not_create_database_for_invalid_arguments() {
+    let home_dir = TempHomeDir::new();
+    let database = home_dir.path().join("todo.sqlite3");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .arg("--database")
+        .arg(&database)
+        .arg("action")
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr("odo: missing subcommand\n");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .arg("--database")
+        .arg(&database)
+        .args(["--format", "json"])
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr("odo: no such command: `--format`\n");
+    assert!(!database.exists());
+}
+
+#[test]
+fn odo_profiles_keep_separate_lists() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["--profile", "work", "action", "add", "File report"][..],
+        &["action", "add", "Water the plants"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    for (args, stdout) in [
+        (
+            &["--profile", "work", "action", "ls"][..],
+            "1 File report\n",
+        ),
+        (
+            &["--profile", "default", "action", "ls"],
+            "1 Water the plants\n",
+        ),
+        (&["profile", "ls"], "default\nwork\n"),
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout(stdout)
+            .stderr("");
+    }
+}
+
+#[test]
+fn odo_restore_restores_backup() {
+    let home_dir = TempHomeDir::new();
+    let backup = home_dir.path().join("backup.sqlite3");
+    for args in [
+        &["action", "add", "Email Alice"][..],
+        &["backup", backup.to_str().unwrap()],
+        &["action", "add", "Water the plants"],
+        &["restore", backup.to_str().unwrap()],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "ls"])
+        .assert()
+        .success()
+        .stdout("1 Email Alice\n")
+        .stderr("");
+}
+
+#[test]
+fn odo_restore_recovers_unusable_database() {
+    let home_dir = TempHomeDir::new();
+    let database = home_dir.path().join("todo.sqlite3");
+    let backup = home_dir.path().join("backup.sqlite3");
+    for args in [
+        &["action", "add", "Email Alice"][..],
+        &["backup", backup.to_str().unwrap()],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .arg("--database")
+            .arg(&database)
+            .args(args)
+            .assert()
+            .success();
+    }
+    let newer = || {
+        rusqlite::Connection::open(&database)
+            .unwrap()
+            .pragma_update(None, "user_version", &99)
+            .unwrap()
+    };
+    let damaged = || fs::write(&database, "not a database").unwrap();
+    for damage in [&newer as &dyn Fn(), &damaged] {
+        damage();
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .arg("--database")
+            .arg(&database)
+            .args(["action", "ls"])
+            .assert()
+            .code(1);
+        for args in [
+            &["restore", backup.to_str().unwrap()][..],
+            &["action", "ls"],
+        ] {
+            Command::cargo_bin("odo")
+                .unwrap()
+                .home_dir(home_dir.path())
+                .arg("--database")
+                .arg(&database)
+                .args(args)
+                .assert()
+                .success()
+                .stderr("");
+        }
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .arg("--database")
+            .arg(&database)
+            .args(["action", "ls"])
+            .assert()
+            .success()
+            .stdout("1 Email Alice\n");
+    }
+}
+
+#[test]
+fn odo_doctor_finds_no_problems() {
+    let home_dir = TempHomeDir::new();
+    for args in [
+        &["action", "add", "Email Alice"][..],
+        &["doctor"],
+        &["doctor", "--fix"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+    }
+}
+
+#[test]
+fn odo_exits_with_status_for_error() {
+    let home_dir = TempHomeDir::new();
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "add", "Email Alice"])
+        .assert()
+        .success();
+    for (args, code, stderr) in [
+        (
+            &["action", "frobnicate"][..],
+            2,
+            "odo: no such subcommand: `frobnicate`\n",
+        ),
+        (
+            &["action", "done", "Water the plants"],
+            3,
+            "odo: action does not exist\n",
+        ),
+        (
+            &["action", "add", "Email Alice"],
+            4,
+            "odo: action already exists\n",
+        ),
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .args(args)
+            .assert()
+            .code(code)
+            .stdout("")
+            .stderr(stderr);
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "done", "Email Alice"])
+        .assert()
+        .success();
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .args(["action", "done", "Email Alice"])
+        .assert()
+        .code(5)
+        .stdout("")
+        .stderr("odo: action is already done\n");
+}
+
+#[test]
+fn store_shares_database_with_odo() {
+    let home_dir = TempHomeDir::new();
+    let database = home_dir.path().join("todo.sqlite3");
+    {
+        let store = odo::Store::open(&database).unwrap();
+        store.add_action("Email Alice").unwrap();
+        store
+            .add_goal(
+                "Plan the party",
+                Some("Email Alice"),
+                None,
+                odo::GoalType::Parallel,
+            )
+            .unwrap();
+    }
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .arg("--database")
+        .arg(&database)
+        .args(["goal", "unset", "action", "Plan the party"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    let store = odo::Store::open(&database).unwrap();
+    assert_eq!(
+        store
+            .list_goals(odo::Status::Open, true, None)
+            .unwrap()
+            .into_iter()
+            .map(|goal| goal.description)
+            .collect::<Vec<_>>(),
+        ["Plan the party"]
+    );
+    assert_eq!(
+        store.remove_action("Water the plants"),
+        Err(odo::Error::NotFound("action does not exist".into()))
+    );
+}
+
+#[test]
+fn odo_config_unset_replaces_invalid_coefficient() {
+    let home_dir = TempHomeDir::new();
+    let database = home_dir.path().join("todo.sqlite3");
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .arg("--database")
+        .arg(&database)
+        .args(["action", "add", "Email Alice"])
+        .assert()
+        .success();
+    rusqlite::Connection::open(&database)
+        .unwrap()
+        .execute(
+            "INSERT INTO config (name, value) VALUES('urgency.age', 'NaN')",
+            [],
+        )
+        .unwrap();
+    Command::cargo_bin("odo")
+        .unwrap()
+        .home_dir(home_dir.path())
+        .arg("--database")
+        .arg(&database)
+        .args(["action", "ls", "--sort", "urgency"])
+        .assert()
+        .code(1);
+    for args in [
+        &["config", "unset", "urgency.age"][..],
+        &["action", "ls", "--sort", "urgency"],
+    ] {
+        Command::cargo_bin("odo")
+            .unwrap()
+            .home_dir(home_dir.path())
+            .arg("--database")
+            .arg(&database)
+            .args(args)
+            .assert()
+            .success();
+    }
 }